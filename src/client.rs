@@ -1,24 +1,33 @@
+use jsonrpsee::core::ClientError;
 use jsonrpsee::core::client::{
     Client, ClientT, Subscription as WsSubscription, SubscriptionClientT,
 };
 use jsonrpsee::core::params::ObjectParams;
+use jsonrpsee::core::traits::ToRpcParams;
 use jsonrpsee::rpc_params;
+use jsonrpsee::types::ErrorObjectOwned;
 use jsonrpsee::ws_client::WsClientBuilder;
 
 use serde::Deserialize;
+use serde::de::DeserializeOwned;
 use serde_json::{Map, Value};
 
 use iced::futures::channel::mpsc::{Receiver, Sender, channel};
+use iced::futures::channel::oneshot;
+use iced::futures::stream::FusedStream;
 use iced::futures::{SinkExt, StreamExt};
 
 use tokio::select;
-use tokio::time::{Duration, interval};
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant, interval};
 use tokio_stream::StreamMap;
 
-use std::error::Error;
+use std::collections::HashMap;
+use std::fmt;
 use std::sync::Arc;
 
 use crate::koditypes::*;
+use crate::wol;
 use tracing::{debug, error};
 
 // TODO: muncher to allow nesting?
@@ -40,70 +49,222 @@ macro_rules! rpc_obj_params {
 }
 
 #[derive(Debug, Clone)]
-pub struct Connection(Sender<KodiCommand>);
+pub struct Connection(Sender<Queued>);
 
 impl Connection {
     pub fn send(&mut self, message: KodiCommand) {
+        self.queue(message, None);
+    }
+
+    /// Like send, but finds out how it went. Failures go to the caller
+    /// instead of Event::CommandFailed.
+    pub fn request(
+        &mut self,
+        message: KodiCommand,
+    ) -> impl Future<Output = Result<(), KodiError>> + use<> {
+        let (done, result) = oneshot::channel();
+        self.queue(message, Some(done));
+        // Dropped unanswered means it was never sent, the offline state ignores most commands
+        async move { result.await.unwrap_or(Err(KodiError::NotConnected)) }
+    }
+
+    fn queue(
+        &mut self,
+        command: KodiCommand,
+        done: Option<oneshot::Sender<Result<(), KodiError>>>,
+    ) {
         self.0
-            .try_send(message)
+            .try_send(Queued { command, done })
             .expect("Should be able to send to kodi client");
     }
 }
 
-pub async fn connect(svr: Arc<KodiServer>, sender: Sender<Event>) {
-    handle_connection(sender, svr).await;
+#[derive(Debug)]
+struct Queued {
+    command: KodiCommand,
+    done: Option<oneshot::Sender<Result<(), KodiError>>>,
+}
+
+// After a wake-on-LAN, until it connects or WAKE_TIMEOUT runs out
+const WAKE_RETRY: Duration = Duration::from_secs(1);
+const WAKE_TIMEOUT: Duration = Duration::from_secs(120);
+
+// How long kodi gets to answer, a sleeping NAS can take a while to spin up.
+// Whole library lists get longer, kodi is slow to build them for big libraries.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const LIBRARY_TIMEOUT: Duration = Duration::from_secs(180);
+// Connecting over HTTP, so an unreachable kodi gives up as quickly as the websocket
+const PING_TIMEOUT: Duration = Duration::from_secs(10);
+// How often a cancellable request checks whether anyone still wants it
+const CANCEL_CHECK: Duration = Duration::from_millis(100);
+// The websocket's notifications keep the status current, polling only
+// catches drift. HTTP has nothing but polling.
+const WS_POLL: Duration = Duration::from_secs(15);
+const HTTP_POLL: Duration = Duration::from_secs(1);
+// What HTTP polls for in place of the playlist, keyboard and scan notifications
+const HTTP_WATCH: Duration = Duration::from_secs(3);
+// Going to a chapter steps one at a time, each step is given up to
+// CHAPTER_CHECKS checks to land before the next one.
+const CHAPTER_CHECK: Duration = Duration::from_millis(100);
+const CHAPTER_CHECKS: usize = 10;
+
+const CHAPTER_LABELS: [&str; 3] = [
+    "Player.Chapter",
+    "Player.ChapterCount",
+    "Player.ChapterName",
+];
+
+// Any of these can change what's playing, its time, speed or streams.
+// See handle_notification for which ones still need the players polled.
+const PLAYER_NOTIFICATIONS: [&str; 9] = [
+    "Player.OnPlay",
+    "Player.OnStop",
+    "Player.OnPause",
+    "Player.OnResume",
+    "Player.OnSeek",
+    "Player.OnSpeedChanged",
+    "Player.OnPropertyChanged",
+    "Player.OnAVStart",
+    "Player.OnAVChange",
+];
+
+pub async fn connect(svr: Arc<KodiServer>, sender: Sender<Event>, backoff: Backoff) {
+    handle_connection(sender, svr, backoff).await;
 }
 
-async fn handle_connection(mut output: Sender<Event>, mut server: Arc<KodiServer>) -> ! {
+async fn handle_connection(
+    mut output: Sender<Event>,
+    mut server: Arc<KodiServer>,
+    mut backoff: Backoff,
+) -> ! {
     let mut state = State::Disconnected;
-    let mut poller = interval(Duration::from_secs(1));
+    let mut poller = interval(HTTP_POLL);
     let mut notifications: StreamMap<&str, WsSubscription<Value>> = StreamMap::new();
+    let mut watch = HttpWatch::default();
+    // Set by a wake, retries quicker until then
+    let mut waking_until: Option<Instant> = None;
+    // Failed attempts in a row, for the backoff
+    let mut failures = 0;
+    let mut last_error = String::new();
+    // Nothing needs polling while the window is minimised and nothing plays
+    let mut minimized = false;
+    let mut playing = false;
+    // See SetNowPlayingOpen
+    let mut with_chapters = false;
+    // Stepping to a chapter takes a while, so it runs beside the loop
+    let mut chapter_seek: Option<JoinHandle<()>> = None;
 
     loop {
         match &mut state {
             State::Disconnected => {
+                if let Some(seek) = chapter_seek.take() {
+                    seek.abort();
+                }
                 let (ol_sender, ol_reciever) = channel(100);
                 let _ = output
                     .send(Event::Disconnected(Connection(ol_sender)))
                     .await;
-                match WsClientBuilder::default()
-                    .build(server.websocket_url())
-                    .await
-                {
+                let _ = output
+                    .send(Event::ConnectionStatus(ConnectionStatus::Connecting))
+                    .await;
+
+                notifications.clear();
+                let connected = match open_client(&server).await {
+                    Ok(client) => start_notifications(&client, &mut notifications)
+                        .await
+                        .map(|_| client),
+                    Err(err) => Err(err),
+                };
+                match connected {
                     Ok(client) => {
+                        waking_until = None;
+                        failures = 0;
+                        watch = HttpWatch::default();
+                        // First tick is straight away
+                        poller = interval(match client {
+                            KodiClient::Ws(_) => WS_POLL,
+                            KodiClient::Http(_) => HTTP_POLL,
+                        });
                         let (sender, reciever) = channel(100);
                         let _ = output.send(Event::Connected(Connection(sender))).await;
-                        // TODO: More notifications?
-                        ws_subscribe(
-                            vec!["Player.OnPlay", "Player.OnStop", "Input.OnInputRequested"],
-                            &client,
-                            &mut notifications,
-                        )
-                        .await;
-
-                        state = State::Connected(client, reciever);
+                        let _ = output
+                            .send(Event::ConnectionStatus(ConnectionStatus::Connected))
+                            .await;
+                        // The websocket only hears about volume changes after this
+                        match poll_kodi_app_status(&client).await {
+                            Ok(event) => {
+                                let _ = output.send(event).await;
+                            }
+                            Err(err) => error!("Failed to get volume: {:?}", err),
+                        }
+                        state = State::Connected(Arc::new(client), reciever);
                     }
                     Err(err) => {
-                        error!("Failed to build WS client: {:?}", err);
-                        state = State::Offline(ol_reciever);
+                        error!("Failed to connect to kodi: {:?}", err);
+                        let delay = match waking_until {
+                            Some(until) if Instant::now() < until => WAKE_RETRY,
+                            _ => backoff.delay(failures),
+                        };
+                        failures = failures.saturating_add(1);
+                        last_error = err.to_string();
+                        let _ = output
+                            .send(Event::ConnectionStatus(ConnectionStatus::retrying(
+                                delay,
+                                &last_error,
+                            )))
+                            .await;
+                        state = State::Offline(ol_reciever, Instant::now() + delay);
                     }
                 }
             }
 
-            State::Offline(reciever) => {
-                // May change this to an auto-backoff eventually.
-                match tokio::time::timeout(Duration::from_secs(5), reciever.next()).await {
-                    Ok(Some(item)) => match item {
-                        KodiCommand::ChangeServer(srv) => {
-                            server = srv;
+            State::Offline(reciever, retry_at) => {
+                let remaining = retry_at.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    state = State::Disconnected;
+                    continue;
+                }
+                // Wakes at least every second to count down the status
+                let tick = remaining.min(Duration::from_secs(1));
+                let command = if reciever.is_terminated() {
+                    // Nothing can be sent to this one any more, still wait out the backoff
+                    tokio::time::sleep(tick).await;
+                    None
+                } else {
+                    tokio::time::timeout(tick, reciever.next())
+                        .await
+                        .ok()
+                        .flatten()
+                };
+                match command.map(|queued| queued.command) {
+                    Some(KodiCommand::ChangeServer(srv)) => {
+                        server = srv;
+                        waking_until = None;
+                        failures = 0;
+                        state = State::Disconnected;
+                    }
+                    Some(KodiCommand::Wake) => match wol::wake(&server).await {
+                        Ok(()) => {
+                            debug!(server.name, "Sent wake-on-LAN packet");
+                            waking_until = Some(Instant::now() + WAKE_TIMEOUT);
                             state = State::Disconnected;
                         }
-                        _ => {}
+                        Err(err) => error!("Wake-on-LAN failed: {:?}", err),
                     },
-                    _ => {
-                        // retry connection on timeout
-                        // or reciever closed as that will make a new one
-                        state = State::Disconnected;
+                    Some(KodiCommand::SetBackoff(new)) => backoff = new,
+                    Some(KodiCommand::SetMinimized(now_minimized)) => minimized = now_minimized,
+                    Some(KodiCommand::SetNowPlayingOpen(open)) => with_chapters = open,
+                    Some(_) => {}
+                    None => {
+                        let remaining = retry_at.saturating_duration_since(Instant::now());
+                        if !remaining.is_zero() {
+                            let _ = output
+                                .send(Event::ConnectionStatus(ConnectionStatus::retrying(
+                                    remaining,
+                                    &last_error,
+                                )))
+                                .await;
+                        }
                     }
                 }
             }
@@ -117,28 +278,55 @@ async fn handle_connection(mut output: Sender<Event>, mut server: Arc<KodiServer
                         let result = handle_notification(
                             client,
                             function,
-                            data
+                            data,
+                            with_chapters,
                         ).await;
 
-                        if let Err(err) = result {
-                            error!("Notification handler error: {:?}", err);
-                            state = State::Disconnected;
-                        } else {
-                            let _ = output.send(result.unwrap()).await;
-                        };
+                        match result {
+                            Ok(event) => {
+                                if let Event::UpdatePlayers(players) = &event {
+                                    playing = !players.is_empty();
+                                }
+                                let _ = output.send(event).await;
+                            }
+                            Err(err) => {
+                                error!("Notification handler error: {:?}", err);
+                                if err.needs_reconnect() {
+                                    state = State::Disconnected;
+                                }
+                            }
+                        }
 
                     }
 
-                    _ = poller.tick() => {
-                        match poll_all_status(client).await {
+                    err = disconnected(client) => {
+                        error!("Lost the connection to kodi: {:?}", err);
+                        state = State::Disconnected;
+                    }
+
+                    _ = poller.tick(), if playing || !minimized => {
+                        let polled = match &**client {
+                            KodiClient::Ws(_) => {
+                                poll_player_status(client, with_chapters).await.map(|e| vec![e])
+                            }
+                            KodiClient::Http(_) => poll_http(client, &mut watch, with_chapters).await,
+                        };
+                        match polled {
                             Ok(events) => {
                                 for event in events {
+                                    if let Event::UpdatePlayers(players) = &event {
+                                        playing = !players.is_empty();
+                                    }
                                     let _ = output.send(event).await;
                                 }
                             }
                             Err(err) => {
                                 error!("Polling error: {:?}", err);
-                            state = State::Disconnected;
+                                // Status timing out is all HTTP has to notice kodi going away.
+                                // Anything else gets another try on the next tick.
+                                if err.needs_reconnect() || matches!(err, KodiError::Timeout { .. }) {
+                                    state = State::Disconnected;
+                                }
                             }
                         }
                     }
@@ -147,25 +335,78 @@ async fn handle_connection(mut output: Sender<Event>, mut server: Arc<KodiServer
 
 
 
-                    message = input.select_next_some() => {
+                    queued = input.select_next_some() => {
+                        let Queued { command: message, done } = queued;
                         debug!(?message, "Processing Kodi command");
 
                         if let KodiCommand::ChangeServer(srv) = message {
                             server = srv;
+                            failures = 0;
                             state = State::Disconnected;
                             // let _ = output.send(Event::Disconnected);
                             continue;
                         };
+                        if let KodiCommand::SetBackoff(new) = message {
+                            backoff = new;
+                            continue;
+                        }
+                        if let KodiCommand::SetMinimized(now_minimized) = message {
+                            // Catch up on whatever happened while it was down
+                            if minimized && !now_minimized {
+                                poller.reset_immediately();
+                            }
+                            minimized = now_minimized;
+                            continue;
+                        }
+                        if let KodiCommand::SetNowPlayingOpen(open) = message {
+                            // Fetch the chapters now rather than on the next tick
+                            if open && !with_chapters {
+                                poller.reset_immediately();
+                            }
+                            with_chapters = open;
+                            continue;
+                        }
 
+                        if let KodiCommand::PlayerGoToChapter(chapter) = message {
+                            // A newer pick replaces one still on its way
+                            if let Some(seek) = chapter_seek.take() {
+                                seek.abort();
+                            }
+                            chapter_seek = Some(tokio::spawn(seek_chapter(
+                                client.clone(),
+                                output.clone(),
+                                chapter,
+                                done,
+                            )));
+                            continue;
+                        }
+
+                        let changes_streams = message.changes_streams();
                         match handle_kodi_command(message, client).await {
                             Ok(event) => {
                                 let _ = output.send(event).await;
+                                if let Some(done) = done {
+                                    let _ = done.send(Ok(()));
+                                }
+                                if changes_streams {
+                                    match poll_player_status(client, with_chapters).await {
+                                        Ok(event) => {
+                                            let _ = output.send(event).await;
+                                        }
+                                        Err(err) => error!("Polling error: {:?}", err),
+                                    }
+                                }
                             }
                             Err(err) => {
                                 error!("Kodi command error: {:?}", err);
-                                if is_transport_error(&err) {
+                                if err.needs_reconnect() {
                                     state = State::Disconnected;
                                 }
+                                if let Some(done) = done {
+                                    let _ = done.send(Err(err));
+                                } else if !err.needs_reconnect() {
+                                    let _ = output.send(Event::CommandFailed(err)).await;
+                                }
                             }
                         }
                     }
@@ -175,94 +416,736 @@ async fn handle_connection(mut output: Sender<Event>, mut server: Arc<KodiServer
     }
 }
 
+// The websocket client, or kodi's web server for when the websocket
+// (tcp control) is turned off. HTTP has no notifications so the
+// connection polls for changes instead, see poll_http.
+#[derive(Debug)]
+enum KodiClient {
+    Ws(Client),
+    Http(HttpClient),
+}
+
+impl KodiClient {
+    async fn request<R, Params>(&self, method: &str, params: Params) -> Result<R, KodiError>
+    where
+        R: DeserializeOwned,
+        Params: ToRpcParams + Send,
+    {
+        self.request_within(method, params, timeout_for(method))
+            .await
+    }
+
+    async fn request_within<R, Params>(
+        &self,
+        method: &str,
+        params: Params,
+        timeout: Duration,
+    ) -> Result<R, KodiError>
+    where
+        R: DeserializeOwned,
+        Params: ToRpcParams + Send,
+    {
+        let request = async {
+            match self {
+                KodiClient::Ws(client) => client.request(method, params).await,
+                KodiClient::Http(client) => client.request(method, params).await,
+            }
+        };
+        match tokio::time::timeout(timeout, request).await {
+            Ok(result) => result.map_err(|err| KodiError::from_client(method, err)),
+            Err(_) => Err(KodiError::Timeout {
+                method: method.to_string(),
+            }),
+        }
+    }
+}
+
+/// Why a request to kodi failed
+#[derive(Debug, Clone, PartialEq)]
+pub enum KodiError {
+    // Kodi can't be reached or the connection dropped
+    Transport(String),
+    // Kodi's own JSON-RPC error object, e.g. bad params to Player.Open
+    Rpc {
+        method: String,
+        code: i32,
+        message: String,
+        data: Option<String>,
+    },
+    // Kodi answered but `field` isn't what the types here expect
+    Deserialize {
+        method: String,
+        field: String,
+        error: String,
+    },
+    Timeout {
+        method: String,
+    },
+    // Asked to go to a chapter the playing video doesn't have
+    NoChapter {
+        chapter: u32,
+        count: u32,
+    },
+    // Never sent, kodi was offline
+    NotConnected,
+}
+
+impl KodiError {
+    fn from_client(method: &str, err: ClientError) -> Self {
+        match err {
+            ClientError::Call(err) => KodiError::Rpc {
+                method: method.to_string(),
+                code: err.code(),
+                message: err.message().to_string(),
+                data: err.data().map(|data| data.get().to_string()),
+            },
+            ClientError::ParseError(err) => KodiError::deserialize(method, "result", err),
+            ClientError::RequestTimeout => KodiError::Timeout {
+                method: method.to_string(),
+            },
+            err => KodiError::Transport(err.to_string()),
+        }
+    }
+
+    fn deserialize(method: &str, field: &str, err: serde_json::Error) -> Self {
+        KodiError::Deserialize {
+            method: method.to_string(),
+            field: field.to_string(),
+            error: err.to_string(),
+        }
+    }
+
+    /// Only a broken connection is fixed by reconnecting, kodi saying no isn't
+    pub fn needs_reconnect(&self) -> bool {
+        matches!(self, KodiError::Transport(_))
+    }
+}
+
+impl fmt::Display for KodiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KodiError::Transport(err) => write!(f, "Connection to kodi failed: {}", err),
+            KodiError::Rpc {
+                method,
+                code,
+                message,
+                ..
+            } => write!(f, "{} failed: {} ({})", method, message, code),
+            KodiError::Deserialize {
+                method,
+                field,
+                error,
+            } => write!(
+                f,
+                "Unexpected `{}` in {} response: {}",
+                field, method, error
+            ),
+            KodiError::Timeout { method } => write!(f, "{} timed out", method),
+            KodiError::NoChapter { chapter, count } => {
+                write!(f, "There's no chapter {}, the video has {}", chapter, count)
+            }
+            KodiError::NotConnected => write!(f, "Kodi is disconnected, nothing was sent"),
+        }
+    }
+}
+
+impl std::error::Error for KodiError {}
+
+fn timeout_for(method: &str) -> Duration {
+    match method {
+        "VideoLibrary.GetMovies"
+        | "VideoLibrary.GetTVShows"
+        | "VideoLibrary.GetEpisodes"
+        | "AudioLibrary.GetArtists"
+        | "AudioLibrary.GetAlbums"
+        | "AudioLibrary.GetSongs" => LIBRARY_TIMEOUT,
+        _ => REQUEST_TIMEOUT,
+    }
+}
+
+// Resolves once whoever asked has stopped listening.
+// futures' mpsc has no way to wait for that so it's checked every CANCEL_CHECK.
+async fn reply_dropped<T>(sender: &Sender<T>) {
+    while !sender.is_closed() {
+        tokio::time::sleep(CANCEL_CHECK).await;
+    }
+}
+
+struct HttpClient {
+    http: reqwest::Client,
+    url: String,
+    username: String,
+    password: String,
+}
+
+// Same as KodiServer's, the password stays out of logs
+impl fmt::Debug for HttpClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpClient")
+            .field("http", &self.http)
+            .field("url", &self.url)
+            .field("username", &self.username)
+            .field("password", &"<hidden>")
+            .finish()
+    }
+}
+
+impl HttpClient {
+    async fn request<R, Params>(&self, method: &str, params: Params) -> Result<R, ClientError>
+    where
+        R: DeserializeOwned,
+        Params: ToRpcParams + Send,
+    {
+        let params = params.to_rpc_params()?;
+        // One request per POST so the id doesn't matter
+        let body = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let transport = |err: reqwest::Error| {
+            if err.is_timeout() {
+                ClientError::RequestTimeout
+            } else {
+                ClientError::Transport(format!("HTTP transport: {}", err).into())
+            }
+        };
+        let mut request = self.http.post(&self.url);
+        if !self.username.is_empty() {
+            request = request.basic_auth(&self.username, Some(&self.password));
+        }
+        let response = request
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(transport)?
+            .bytes()
+            .await
+            .map_err(transport)?;
+
+        let mut response: Value = serde_json::from_slice(&response)?;
+        if let Some(err) = response.get_mut("error") {
+            let err: ErrorObjectOwned = serde_json::from_value(err.take())?;
+            return Err(ClientError::Call(err));
+        }
+        Ok(serde_json::from_value(response["result"].take())?)
+    }
+}
+
+// Auto only picks when connecting. Once on HTTP it stays there until the
+// connection is lost, the websocket is tried again on the next connect.
+async fn open_client(server: &KodiServer) -> Result<KodiClient, KodiError> {
+    match server.transport {
+        Transport::WebSocket => Ok(KodiClient::Ws(open_ws(server).await?)),
+        Transport::Http => open_http(server).await,
+        Transport::Auto => match open_ws(server).await {
+            Ok(client) => Ok(KodiClient::Ws(client)),
+            Err(err) => {
+                debug!(?err, "Websocket unavailable, trying HTTP");
+                open_http(server).await
+            }
+        },
+    }
+}
+
+async fn open_ws(server: &KodiServer) -> Result<Client, KodiError> {
+    // request_within does the timing out
+    WsClientBuilder::default()
+        .request_timeout(LIBRARY_TIMEOUT)
+        .build(server.websocket_url())
+        .await
+        .map_err(|err| KodiError::Transport(err.to_string()))
+}
+
+// HTTP is connectionless so ping to find out if kodi is actually there
+async fn open_http(server: &KodiServer) -> Result<KodiClient, KodiError> {
+    let client = KodiClient::Http(HttpClient {
+        http: reqwest::Client::builder()
+            .timeout(LIBRARY_TIMEOUT)
+            .build()
+            .map_err(|err| KodiError::Transport(err.to_string()))?,
+        url: server.jsonrpc_url(),
+        username: server.username.clone(),
+        password: server.password.clone(),
+    });
+    let _: Value = client
+        .request_within("JSONRPC.Ping", rpc_params!(), PING_TIMEOUT)
+        .await?;
+    Ok(client)
+}
+
+// Polling is too slow to notice the websocket closing
+async fn disconnected(client: &KodiClient) -> KodiError {
+    match client {
+        KodiClient::Ws(ws) => KodiError::Transport(ws.on_disconnect().await.to_string()),
+        KodiClient::Http(_) => std::future::pending().await,
+    }
+}
+
+// What poll_http saw last time. None until the first poll so
+// connecting doesn't look like a change.
+#[derive(Debug, Default)]
+struct HttpWatch {
+    // player id -> file and label
+    playing: Option<HashMap<u8, String>>,
+    playlist_sizes: [Option<u32>; 2],
+    window: Option<u32>,
+    scanning: Option<bool>,
+    // Last look at the playlists, window and scan, see HTTP_WATCH
+    watched: Option<Instant>,
+}
+
+// Kodi's on screen keyboard
+const KEYBOARD_WINDOW: u32 = 10103;
+
+/// Status polling plus the changes the websocket notifications would have sent
+async fn poll_http(
+    client: &KodiClient,
+    watch: &mut HttpWatch,
+    with_chapters: bool,
+) -> Result<Vec<Event>, KodiError> {
+    let mut events = vec![poll_kodi_app_status(client).await?];
+
+    // One list for the status and Player.OnPlay, stopped players drop out of both
+    let players: Vec<ActivePlayer> = client
+        .request("Player.GetActivePlayers", rpc_params!())
+        .await?;
+    events.push(player_status(client, &players, with_chapters).await?);
+    let mut playing = HashMap::new();
+    for player in &players {
+        let item: Value = client
+            .request(
+                "Player.GetItem",
+                rpc_obj_params!("playerid" = player.playerid, "properties" = ["file"]),
+            )
+            .await?;
+        let item = format!("{}{}", item["item"]["file"], item["item"]["label"]);
+        let changed = watch
+            .playing
+            .as_ref()
+            .is_some_and(|prev| prev.get(&player.playerid) != Some(&item));
+        if changed {
+            events.push(
+                handle_kodi_command(KodiCommand::PlayerGetPlayingItem(player.playerid), client)
+                    .await?,
+            );
+        }
+        playing.insert(player.playerid, item);
+    }
+    watch.playing = Some(playing);
+
+    // The rest rarely changes, no need to ask every tick
+    if watch.watched.is_some_and(|at| at.elapsed() < HTTP_WATCH) {
+        return Ok(events);
+    }
+    watch.watched = Some(Instant::now());
+
+    // Playlist.OnAdd/OnRemove/OnClear, only catches changes in size
+    for playlist_id in 0..2u8 {
+        let params = rpc_obj_params!("playlistid" = playlist_id, "properties" = ["size"]);
+        let size: u32 = request_field(client, "Playlist.GetProperties", params, "size").await?;
+        let prev = &mut watch.playlist_sizes[playlist_id as usize];
+        if prev.is_some_and(|prev| prev != size) {
+            events.push(Event::PlaylistChanged(playlist_id));
+        }
+        *prev = Some(size);
+    }
+
+    // Input.OnInputRequested, the keyboard opening
+    #[derive(Deserialize)]
+    struct Window {
+        id: u32,
+    }
+    let params = rpc_obj_params!("properties" = ["currentwindow"]);
+    let Window { id: window } =
+        request_field(client, "GUI.GetProperties", params, "currentwindow").await?;
+    if window == KEYBOARD_WINDOW && watch.window.is_some_and(|prev| prev != window) {
+        events.push(Event::InputRequested(String::new()));
+    }
+    watch.window = Some(window);
+
+    // VideoLibrary.OnScanFinished, single item updates can't be seen
+    let scanning = "Library.IsScanningVideo";
+    let params = rpc_obj_params!("booleans" = [scanning]);
+    let scanning: bool = request_field(client, "XBMC.GetInfoBooleans", params, scanning).await?;
+    if watch.scanning == Some(true) && !scanning {
+        events.push(Event::LibraryScanned);
+    }
+    watch.scanning = Some(scanning);
+
+    Ok(events)
+}
+
+// Only the websocket has notifications, HTTP polls instead
+async fn start_notifications(
+    client: &KodiClient,
+    notifications: &mut StreamMap<&str, WsSubscription<Value>>,
+) -> Result<(), KodiError> {
+    let KodiClient::Ws(ws) = client else {
+        return Ok(());
+    };
+    // TODO: More notifications?
+    ws_subscribe(
+        [
+            PLAYER_NOTIFICATIONS.as_slice(),
+            &[
+                "Application.OnVolumeChanged",
+                "Input.OnInputRequested",
+                "Playlist.OnAdd",
+                "Playlist.OnRemove",
+                "Playlist.OnClear",
+                "VideoLibrary.OnUpdate",
+                "VideoLibrary.OnRemove",
+                "VideoLibrary.OnScanFinished",
+                "VideoLibrary.OnCleanFinished",
+            ],
+        ]
+        .concat(),
+        ws,
+        notifications,
+    )
+    .await?;
+    Ok(())
+}
+
 async fn ws_subscribe(
     names: Vec<&'static str>,
     client: &Client,
     notifications: &mut StreamMap<&str, WsSubscription<Value>>,
-) {
+) -> Result<(), KodiError> {
     for name in names {
         let sub: WsSubscription<Value> = client
             .subscribe_to_method(name)
             .await
-            .expect("Subscription should always work");
+            .map_err(|err| KodiError::from_client(name, err))?;
         notifications.insert(name, sub);
     }
-}
-
-/// Helper to check if an error should trigger a reconnection
-fn is_transport_error(err: &Box<dyn Error + Send + Sync>) -> bool {
-    // jsonrpsee errors related to connectivity usually involve the underlying transport
-    let s = err.to_string();
-    s.contains("Restart needed") || s.contains("Closed") || s.contains("transport")
-}
-
-/// Aggregated polling for status
-async fn poll_all_status(client: &Client) -> Result<Vec<Event>, Box<dyn Error + Send + Sync>> {
-    let mut events = Vec::new();
-    events.push(poll_kodi_app_status(client).await?);
-    events.push(poll_player_status(client).await?);
-    Ok(events)
+    Ok(())
 }
 
 /// Generic helper to request a field and deserialize it
 async fn request_field<T>(
-    client: &Client,
+    client: &KodiClient,
+    method: &str,
+    params: ObjectParams,
+    field: &str,
+) -> Result<T, KodiError>
+where
+    for<'de> T: Deserialize<'de>,
+{
+    let response: Value = client.request(method, params).await?;
+    debug!(?response, "Response for {}", method);
+    serde_json::from_value(response[field].clone())
+        .map_err(|err| KodiError::deserialize(method, field, err))
+}
+
+// Kodi leaves a list out entirely when it's empty, anything else has to parse
+async fn request_list<T>(
+    client: &KodiClient,
     method: &str,
     params: ObjectParams,
     field: &str,
-) -> Result<T, Box<dyn Error + Send + Sync>>
+) -> Result<Vec<T>, KodiError>
 where
     for<'de> T: Deserialize<'de>,
 {
     let response: Value = client.request(method, params).await?;
     debug!(?response, "Response for {}", method);
-    Ok(serde_json::from_value(response[field].clone())?)
+    if response[field].is_null() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_value(response[field].clone())
+        .map_err(|err| KodiError::deserialize(method, field, err))
+}
+
+// Requests a list with no properties, the ids are always returned.
+// Kodi leaves the list out entirely when it's empty.
+async fn request_ids(
+    client: &KodiClient,
+    method: &str,
+    params: ObjectParams,
+    list_field: &str,
+    id_field: &str,
+) -> Result<Vec<u32>, KodiError> {
+    let response: Value = client.request(method, params).await?;
+    let ids = response[list_field]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| item[id_field].as_u64().map(|id| id as u32))
+                .collect()
+        })
+        .unwrap_or_default();
+    Ok(ids)
+}
+
+// Like request_ids but with FINGERPRINT_PROPS, same empty list handling.
+// `art_key` is the art the list table keeps for this kind of item.
+async fn request_fingerprints(
+    client: &KodiClient,
+    method: &str,
+    params: ObjectParams,
+    list_field: &str,
+    art_key: &str,
+) -> Result<Vec<Fingerprint>, KodiError> {
+    let response: Value = client.request(method, params).await?;
+    if response[list_field].is_null() {
+        return Ok(Vec::new());
+    }
+    let mut fingerprints: Vec<Fingerprint> =
+        serde_json::from_value(response[list_field].clone())
+            .map_err(|err| KodiError::deserialize(method, list_field, err))?;
+    let items = response[list_field].as_array().into_iter().flatten();
+    for (fingerprint, item) in fingerprints.iter_mut().zip(items) {
+        fingerprint.art = item["art"][art_key]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+    }
+    Ok(fingerprints)
+}
+
+// Items with a resume point
+fn in_progress_filter() -> Value {
+    serde_json::json!({"field": "inprogress", "operator": "true", "value": ""})
+}
+
+// Params for the Set*Details calls, the id field depends on the item type.
+// Unwatching also clears the resume point, same as kodi's own context menu.
+fn edit_params(id_field: &str, id: u32, change: EditChange) -> ObjectParams {
+    let mut params = ObjectParams::new();
+    let result = params.insert(id_field, id).and_then(|_| match change {
+        EditChange::PlayCount(0) => params
+            .insert("playcount", 0)
+            .and_then(|_| params.insert("resume", serde_json::json!({"position": 0}))),
+        EditChange::PlayCount(count) => params.insert("playcount", count),
+        EditChange::UserRating(rating) => params.insert("userrating", rating),
+    });
+    if let Err(err) = result {
+        panic!("Edit params cannot be serialized: {:?}", err);
+    }
+    params
 }
 
-async fn poll_kodi_app_status(client: &Client) -> Result<Event, Box<dyn Error + Send + Sync>> {
+// One details request per id, 10 at a time. Failed ones are skipped.
+async fn request_details_by_ids<T>(
+    client: &KodiClient,
+    ids: Vec<u32>,
+    method: &str,
+    id_field: &str,
+    properties: &[&str],
+    field: &str,
+) -> Vec<T>
+where
+    for<'de> T: Deserialize<'de>,
+{
+    iced::futures::stream::iter(ids)
+        .map(|id| {
+            let mut params = ObjectParams::new();
+            params.insert(id_field, id).expect("u32 always serializes");
+            params
+                .insert("properties", properties)
+                .expect("&str always serializes");
+            request_field::<T>(client, method, params, field)
+        })
+        .buffer_unordered(10)
+        .filter_map(|res| async move { res.ok() })
+        .collect()
+        .await
+}
+
+async fn poll_kodi_app_status(client: &KodiClient) -> Result<Event, KodiError> {
+    let method = "Application.GetProperties";
     let response: Value = client
-        .request(
-            "Application.GetProperties",
-            rpc_obj_params!("properties" = ["volume", "muted"]),
-        )
+        .request(method, rpc_obj_params!("properties" = ["volume", "muted"]))
         .await?;
-    let muted: bool = response["muted"]
-        .as_bool()
-        .expect("`muted: bool` should exist in this response");
-    let app_status = KodiAppStatus { muted };
+    let app_status = app_status(method, &response)?;
     Ok(Event::UpdateKodiAppStatus(app_status))
 }
 
-async fn poll_player_status(client: &Client) -> Result<Event, Box<dyn Error + Send + Sync>> {
+// Same shape from the properties and OnVolumeChanged, except the
+// notification's volume is a float
+fn app_status(method: &str, value: &Value) -> Result<KodiAppStatus, KodiError> {
+    let muted = bool::deserialize(&value["muted"])
+        .map_err(|err| KodiError::deserialize(method, "muted", err))?;
+    let volume = f64::deserialize(&value["volume"])
+        .map_err(|err| KodiError::deserialize(method, "volume", err))?;
+    Ok(KodiAppStatus {
+        muted,
+        volume: volume.round().clamp(0.0, 100.0) as u8,
+    })
+}
+
+/// Properties for every active player, e.g. music alongside a slideshow
+async fn poll_player_status(client: &KodiClient, with_chapters: bool) -> Result<Event, KodiError> {
     let players: Vec<ActivePlayer> = client
         .request("Player.GetActivePlayers", rpc_params!())
         .await?;
+    player_status(client, &players, with_chapters).await
+}
 
-    if players.len() == 0 {
-        return Ok(Event::UpdatePlayerProps(None));
+async fn player_status(
+    client: &KodiClient,
+    players: &[ActivePlayer],
+    with_chapters: bool,
+) -> Result<Event, KodiError> {
+    let mut all_props = Vec::with_capacity(players.len());
+    for player in players {
+        let mut playerprops: PlayerProps = client
+            .request(
+                "Player.GetProperties",
+                rpc_obj_params! {"playerid"=player.playerid, "properties"=PLAYER_PROPS},
+            )
+            .await?;
+        playerprops.player_id = Some(player.playerid);
+        if with_chapters && playerprops.player_type == PlayerType::Video {
+            // Chapters are only shown, the rest of the status matters more
+            playerprops.chapters = chapters(client).await.unwrap_or_else(|err| {
+                error!("Failed to get chapters: {:?}", err);
+                Chapters::default()
+            });
+        }
+        all_props.push(playerprops);
     }
-    // For now only considering the first playerid it sees...
-    let player_id = players[0].playerid;
 
-    let mut playerprops: PlayerProps = client
+    Ok(Event::UpdatePlayers(all_props))
+}
+
+// Infolabels only ever describe the playing video
+async fn chapters(client: &KodiClient) -> Result<Chapters, KodiError> {
+    let labels: Value = client
         .request(
-            "Player.GetProperties",
-            rpc_obj_params! {"playerid"=player_id, "properties"=PLAYER_PROPS},
+            "XBMC.GetInfoLabels",
+            rpc_obj_params!("labels" = CHAPTER_LABELS),
         )
         .await?;
-    playerprops.player_id = Some(player_id);
+    // Numbers come zero padded, "03"
+    let number = |label: &str| {
+        labels[label]
+            .as_str()
+            .and_then(|n| n.trim().parse().ok())
+            .unwrap_or_default()
+    };
+    Ok(Chapters {
+        current: number("Player.Chapter"),
+        count: number("Player.ChapterCount"),
+        name: labels["Player.ChapterName"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string(),
+    })
+}
+
+// Kodi can't seek to a chapter, only step to the next or previous one.
+// Each step waits to see where it landed so the next starts from there.
+async fn go_to_chapter(client: &KodiClient, chapter: u32) -> Result<(), KodiError> {
+    let mut at = chapters(client).await?;
+    if !(1..=at.count).contains(&chapter) {
+        return Err(KodiError::NoChapter {
+            chapter,
+            count: at.count,
+        });
+    }
+    for _ in 0..=at.count {
+        if at.current == chapter {
+            break;
+        }
+        let action = if at.current < chapter {
+            "chapterorbigstepforward"
+        } else {
+            "chapterorbigstepback"
+        };
+        let _: Value = client
+            .request("Input.ExecuteAction", rpc_params![action])
+            .await?;
+
+        let from = at.current;
+        for _ in 0..CHAPTER_CHECKS {
+            tokio::time::sleep(CHAPTER_CHECK).await;
+            at = chapters(client).await?;
+            if at.current != from {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
 
-    Ok(Event::UpdatePlayerProps(Some(playerprops)))
+// Reports back the same way a command handled in the loop would
+async fn seek_chapter(
+    client: Arc<KodiClient>,
+    mut output: Sender<Event>,
+    chapter: u32,
+    done: Option<oneshot::Sender<Result<(), KodiError>>>,
+) {
+    match go_to_chapter(&client, chapter).await {
+        Ok(()) => {
+            if let Some(done) = done {
+                let _ = done.send(Ok(()));
+            }
+        }
+        Err(err) => {
+            error!("Kodi command error: {:?}", err);
+            if let Some(done) = done {
+                let _ = done.send(Err(err));
+            } else if !err.needs_reconnect() {
+                let _ = output.send(Event::CommandFailed(err)).await;
+            }
+        }
+    }
+}
+
+async fn set_details(client: &KodiClient, edit: LibraryEdit) -> Result<(), KodiError> {
+    match (edit.item, edit.change) {
+        // Kodi has no watched flag for shows, it's worked out from the episodes
+        (LibraryItem::TVShow(tvshowid), EditChange::PlayCount(_)) => {
+            let params = rpc_obj_params!("tvshowid" = tvshowid, "properties" = vec![] as Vec<&str>);
+            let ids = request_ids(
+                client,
+                "VideoLibrary.GetEpisodes",
+                params,
+                "episodes",
+                "episodeid",
+            )
+            .await?;
+            for episodeid in ids {
+                let params = edit_params("episodeid", episodeid, edit.change);
+                let _: Value = client
+                    .request("VideoLibrary.SetEpisodeDetails", params)
+                    .await?;
+            }
+        }
+        (item, change) => {
+            let method = match item {
+                LibraryItem::Movie(_) => "VideoLibrary.SetMovieDetails",
+                LibraryItem::TVShow(_) => "VideoLibrary.SetTVShowDetails",
+                LibraryItem::Episode(_) => "VideoLibrary.SetEpisodeDetails",
+            };
+            let params = edit_params(item.id_field(), item.id(), change);
+            let _: Value = client.request(method, params).await?;
+        }
+    }
+    Ok(())
 }
 
 async fn handle_kodi_command(
     message: KodiCommand,
-    client: &Client,
-) -> Result<Event, Box<dyn Error + Send + Sync>> {
+    client: &KodiClient,
+) -> Result<Event, KodiError> {
     match message {
         // this was already handled before it got here.
         KodiCommand::ChangeServer(_) => Ok(Event::None), //(Event::Disconnected),
 
+        // Already awake, and SetBackoff was handled before it got here
+        KodiCommand::Wake
+        | KodiCommand::SetBackoff(_)
+        | KodiCommand::SetMinimized(_)
+        | KodiCommand::SetNowPlayingOpen(_) => Ok(Event::None),
+
         KodiCommand::GetDirectory {
             mut sender,
             path,
@@ -278,9 +1161,21 @@ async fn handle_kodi_command(
                 }
             );
 
-            let files: Vec<DirList> =
-                request_field(client, "Files.GetDirectory", params, "files").await?;
-            let list = files.into_iter().map(|v| Box::new(v) as _).collect();
+            // Navigating away cancels, no point waiting on a slow share for nothing
+            let files: Vec<DirList> = select! {
+                files = request_field(client, "Files.GetDirectory", params, "files") => files?,
+                _ = reply_dropped(&sender) => {
+                    debug!("Directory load cancelled");
+                    return Ok(Event::None);
+                }
+            };
+            let list = files
+                .into_iter()
+                .map(|mut v| {
+                    v.media_type = media_type.clone();
+                    Box::new(v) as _
+                })
+                .collect();
             let _ = sender.send(list).await;
             Ok(Event::None)
         }
@@ -292,12 +1187,25 @@ async fn handle_kodi_command(
             let params = rpc_obj_params!("media" = media_type.as_str());
             let items: Vec<Sources> =
                 request_field(client, "Files.GetSources", params, "sources").await?;
-            let mut sources: Vec<Box<dyn IntoListData + Send>> =
-                items.into_iter().map(|v| Box::new(v) as _).collect();
+            let mut sources: Vec<Box<dyn IntoListData + Send>> = items
+                .into_iter()
+                .map(|mut v| {
+                    v.media_type = media_type.clone();
+                    Box::new(v) as _
+                })
+                .collect();
 
-            let db = Sources {
-                label: "- Database".to_string(),
-                file: "videoDB://".to_string(),
+            let db = match media_type {
+                MediaType::Video => Sources {
+                    label: "- Video Database".to_string(),
+                    file: "videoDB://".to_string(),
+                    media_type,
+                },
+                MediaType::Music => Sources {
+                    label: "- Music Database".to_string(),
+                    file: "musicdb://".to_string(),
+                    media_type,
+                },
             };
             sources.insert(0, Box::new(db));
 
@@ -311,42 +1219,185 @@ async fn handle_kodi_command(
             Ok(Event::None)
         }
 
-        KodiCommand::InputButtonEvent { button, keymap } => {
-            let params = rpc_obj_params!("button" = button, "keymap" = keymap);
-            let _: Value = client.request("Input.ButtonEvent", params).await?;
+        KodiCommand::PlayerOpenChannel(channelid) => {
+            let params = rpc_obj_params!("item" = serde_json::json!({"channelid": channelid}));
+            let _: Value = client.request("Player.Open", params).await?;
             Ok(Event::None)
         }
 
-        KodiCommand::InputExecuteAction(action) => {
-            let _: Value = client
-                .request("Input.ExecuteAction", rpc_params![action])
-                .await?;
+        KodiCommand::PlayerOpenRecording(recordingid) => {
+            let params = rpc_obj_params!("item" = serde_json::json!({"recordingid": recordingid}));
+            let _: Value = client.request("Player.Open", params).await?;
             Ok(Event::None)
         }
 
-        KodiCommand::GUIActivateWindow(window) => {
-            let _: Value = client
-                .request("GUI.ActivateWindow", rpc_params![window])
-                .await?;
+        KodiCommand::PlayerOpenResume(file) => {
+            let params = rpc_obj_params!(
+                "item" = serde_json::json!({"file": file}),
+                "options" = serde_json::json!({"resume": true})
+            );
+            let _: Value = client.request("Player.Open", params).await?;
             Ok(Event::None)
         }
 
-        KodiCommand::PlayerGetPlayingItem(player_id) => {
-            let params = rpc_obj_params!("playerid" = player_id, "properties" = PLAYING_ITEM_PROPS);
-            let item: PlayingItem = request_field(client, "Player.GetItem", params, "item").await?;
-            Ok(Event::UpdatePlayingItem(item))
+        KodiCommand::PlaylistAdd { playlist_id, item } => {
+            let params = rpc_obj_params!("playlistid" = playlist_id, "item" = item);
+            let _: Value = client.request("Playlist.Add", params).await?;
+            Ok(Event::None)
         }
 
-        KodiCommand::PlayerSeek(player_id, time) => {
+        KodiCommand::PlaylistGetItems {
+            mut sender,
+            playlist_id,
+        } => {
             let params = rpc_obj_params!(
-                "playerid" = player_id,
-                "value" = serde_json::json!({"time": time})
+                "playlistid" = playlist_id,
+                "properties" = PLAYING_ITEM_PROPS
             );
-            let _: Value = client.request("Player.Seek", params).await?;
+            let items: Vec<PlayingItem> =
+                request_list(client, "Playlist.GetItems", params, "items").await?;
+            let _ = sender.send(items).await;
             Ok(Event::None)
         }
 
-        KodiCommand::PlayerSetSubtitle {
+        KodiCommand::PlaylistRemove {
+            playlist_id,
+            position,
+        } => {
+            let params = rpc_obj_params!("playlistid" = playlist_id, "position" = position);
+            let _: Value = client.request("Playlist.Remove", params).await?;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PlaylistSwap {
+            playlist_id,
+            position1,
+            position2,
+        } => {
+            let params = rpc_obj_params!(
+                "playlistid" = playlist_id,
+                "position1" = position1,
+                "position2" = position2
+            );
+            let _: Value = client.request("Playlist.Swap", params).await?;
+            // Swap doesn't send a notification so report the change here
+            Ok(Event::PlaylistChanged(playlist_id))
+        }
+
+        KodiCommand::PlaylistClear(playlist_id) => {
+            let params = rpc_obj_params!("playlistid" = playlist_id);
+            let _: Value = client.request("Playlist.Clear", params).await?;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PlayerGoTo {
+            player_id,
+            position,
+        } => {
+            let params = rpc_obj_params!("playerid" = player_id, "to" = position);
+            let _: Value = client.request("Player.GoTo", params).await?;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PlayerSkip {
+            player_id,
+            direction,
+        } => {
+            let params = rpc_obj_params!("playerid" = player_id, "to" = direction);
+            let _: Value = client.request("Player.GoTo", params).await?;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PlayerPlayPause(player_id) => {
+            let params = rpc_obj_params!("playerid" = player_id, "play" = "toggle");
+            let _: Value = client.request("Player.PlayPause", params).await?;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PlayerStop(player_id) => {
+            let _: Value = client
+                .request("Player.Stop", rpc_obj_params!("playerid" = player_id))
+                .await?;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PlayerSetShuffle { player_id, shuffle } => {
+            let params = rpc_obj_params!("playerid" = player_id, "shuffle" = shuffle);
+            let _: Value = client.request("Player.SetShuffle", params).await?;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PlayerSetRepeat { player_id, repeat } => {
+            let params = rpc_obj_params!("playerid" = player_id, "repeat" = repeat);
+            let _: Value = client.request("Player.SetRepeat", params).await?;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PlayerSetPartymode {
+            player_id,
+            partymode,
+        } => {
+            let params = rpc_obj_params!("playerid" = player_id, "partymode" = partymode);
+            let _: Value = client.request("Player.SetPartymode", params).await?;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PlayerSetSpeed { player_id, speed } => {
+            let params = rpc_obj_params!("playerid" = player_id, "speed" = speed);
+            let _: Value = client.request("Player.SetSpeed", params).await?;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PlayerOpenPlaylist {
+            playlist_id,
+            position,
+        } => {
+            let params = rpc_obj_params!(
+                "item" = serde_json::json!({"playlistid": playlist_id, "position": position})
+            );
+            let _: Value = client.request("Player.Open", params).await?;
+            Ok(Event::None)
+        }
+
+        KodiCommand::InputButtonEvent { button, keymap } => {
+            let params = rpc_obj_params!("button" = button, "keymap" = keymap);
+            let _: Value = client.request("Input.ButtonEvent", params).await?;
+            Ok(Event::None)
+        }
+
+        KodiCommand::InputExecuteAction(action) => {
+            let _: Value = client
+                .request("Input.ExecuteAction", rpc_params![action])
+                .await?;
+            Ok(Event::None)
+        }
+
+        KodiCommand::GUIActivateWindow(window) => {
+            let _: Value = client
+                .request("GUI.ActivateWindow", rpc_params![window])
+                .await?;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PlayerGetPlayingItem(player_id) => {
+            let params = rpc_obj_params!("playerid" = player_id, "properties" = PLAYING_ITEM_PROPS);
+            let item: PlayingItem = request_field(client, "Player.GetItem", params, "item").await?;
+            Ok(Event::UpdatePlayingItem(player_id, Box::new(item)))
+        }
+
+        KodiCommand::PlayerSeek(player_id, time) => {
+            let params = rpc_obj_params!(
+                "playerid" = player_id,
+                "value" = serde_json::json!({"time": time})
+            );
+            let _: Value = client.request("Player.Seek", params).await?;
+            Ok(Event::None)
+        }
+
+        // Stepped beside the connection loop, see seek_chapter
+        KodiCommand::PlayerGoToChapter(_) => Ok(Event::None),
+
+        KodiCommand::PlayerSetSubtitle {
             player_id,
             subtitle_index,
             enabled,
@@ -371,6 +1422,13 @@ async fn handle_kodi_command(
             Ok(Event::None)
         }
 
+        KodiCommand::PlayerNextSubtitle(player_id) => {
+            let params =
+                rpc_obj_params!("playerid" = player_id, "subtitle" = "next", "enable" = true);
+            let _response: Value = client.request("Player.SetSubtitle", params).await?;
+            Ok(Event::None)
+        }
+
         KodiCommand::PlayerSetAudioStream {
             player_id,
             audio_index,
@@ -384,13 +1442,33 @@ async fn handle_kodi_command(
             Ok(Event::None)
         }
 
+        KodiCommand::PlayerSetVideoStream {
+            player_id,
+            video_index,
+        } => {
+            let _response: Value = client
+                .request(
+                    "Player.SetVideoStream",
+                    rpc_obj_params!("playerid" = player_id, "stream" = video_index),
+                )
+                .await?;
+            Ok(Event::None)
+        }
+
         KodiCommand::ToggleMute => {
             let _response: Value = client
                 .request("Application.SetMute", rpc_obj_params!("mute" = "toggle"))
                 .await?;
             // This returns 'false' for muted and 'true' for unmuted but it doesn't matter
-            // since we poll for it anyway.
+            // since OnVolumeChanged (or polling over HTTP) reports it anyway.
+
+            Ok(Event::None)
+        }
 
+        KodiCommand::SetVolume(volume) => {
+            let _: Value = client
+                .request("Application.SetVolume", rpc_obj_params!("volume" = volume))
+                .await?;
             Ok(Event::None)
         }
 
@@ -421,9 +1499,10 @@ async fn handle_kodi_command(
 
             let response: Value = client.request("VideoLibrary.GetMovies", params).await?;
 
-            let movies = <Vec<MovieListItem> as Deserialize>::deserialize(&response["movies"])?;
+            let movies = <Vec<MovieListItem> as Deserialize>::deserialize(&response["movies"])
+                .map_err(|err| KodiError::deserialize("VideoLibrary.GetMovies", "movies", err))?;
 
-            sender.send(movies).await?;
+            let _ = sender.send(movies).await;
 
             Ok(Event::None)
         }
@@ -446,9 +1525,27 @@ async fn handle_kodi_command(
             };
             let response: Value = client.request("VideoLibrary.GetTVShows", params).await?;
 
-            let shows = <Vec<TVShowListItem> as Deserialize>::deserialize(&response["tvshows"])?;
+            let shows = <Vec<TVShowListItem> as Deserialize>::deserialize(&response["tvshows"])
+                .map_err(|err| KodiError::deserialize("VideoLibrary.GetTVShows", "tvshows", err))?;
 
-            sender.send(shows).await?;
+            let _ = sender.send(shows).await;
+
+            Ok(Event::None)
+        }
+
+        KodiCommand::VideoLibraryGetMovieDetails {
+            mut sender,
+            movieid,
+        } => {
+            let params = rpc_obj_params!("movieid" = movieid, "properties" = DETAILED_MOVIE_PROPS);
+            let movie: MovieDetails = request_field(
+                client,
+                "VideoLibrary.GetMovieDetails",
+                params,
+                "moviedetails",
+            )
+            .await?;
+            let _ = sender.send(movie).await;
 
             Ok(Event::None)
         }
@@ -458,15 +1555,32 @@ async fn handle_kodi_command(
             tvshowid,
         } => {
             // this will fail if the tvshowid is no longer in kodi
-            let response: Value = client
-                .request(
-                    "VideoLibrary.GetTVShowDetails",
-                    rpc_obj_params!("tvshowid" = tvshowid, "properties" = MINIMAL_TV_PROPS),
-                )
-                .await?;
+            let params = rpc_obj_params!("tvshowid" = tvshowid, "properties" = DETAILED_TV_PROPS);
+            let show: TVShowDetails = request_field(
+                client,
+                "VideoLibrary.GetTVShowDetails",
+                params,
+                "tvshowdetails",
+            )
+            .await?;
+            let _ = sender.send(show).await;
 
-            let show = <TVShowListItem as Deserialize>::deserialize(&response["tvshowdetails"])?;
-            sender.send(show).await?;
+            Ok(Event::None)
+        }
+
+        KodiCommand::VideoLibraryGetEpisodeDetails {
+            mut sender,
+            episodeid,
+        } => {
+            let params = rpc_obj_params!("episodeid" = episodeid, "properties" = DETAILED_EP_PROPS);
+            let episode: EpisodeDetails = request_field(
+                client,
+                "VideoLibrary.GetEpisodeDetails",
+                params,
+                "episodedetails",
+            )
+            .await?;
+            let _ = sender.send(episode).await;
 
             Ok(Event::None)
         }
@@ -484,10 +1598,10 @@ async fn handle_kodi_command(
                 )
                 .await?;
 
-            let seasons =
-                <Vec<TVSeasonListItem> as Deserialize>::deserialize(&response["seasons"])?;
+            let seasons = <Vec<TVSeasonListItem> as Deserialize>::deserialize(&response["seasons"])
+                .map_err(|err| KodiError::deserialize("VideoLibrary.GetSeasons", "seasons", err))?;
 
-            sender.send(seasons).await?;
+            let _ = sender.send(seasons).await;
 
             Ok(Event::None)
         }
@@ -519,9 +1633,12 @@ async fn handle_kodi_command(
             let response: Value = client.request("VideoLibrary.GetEpisodes", params).await?;
 
             let episodes =
-                <Vec<TVEpisodeListItem> as Deserialize>::deserialize(&response["episodes"])?;
+                <Vec<TVEpisodeListItem> as Deserialize>::deserialize(&response["episodes"])
+                    .map_err(|err| {
+                        KodiError::deserialize("VideoLibrary.GetEpisodes", "episodes", err)
+                    })?;
 
-            sender.send(episodes).await?;
+            let _ = sender.send(episodes).await;
 
             Ok(Event::None)
         }
@@ -547,79 +1664,44 @@ async fn handle_kodi_command(
             Ok(Event::None)
         }
 
-        // ID-only fetches for efficient syncing
-        KodiCommand::VideoLibraryGetMovieIDs { mut sender } => {
-            // Request with empty properties array to get just the IDs (IDs are always returned)
-            let response: Value = client
-                .request(
-                    "VideoLibrary.GetMovies",
-                    rpc_obj_params!("properties" = vec![] as Vec<&str>),
-                )
-                .await?;
-
-            let movies = <Vec<serde_json::Map<String, Value>> as Deserialize>::deserialize(
-                &response["movies"],
-            )?;
-            let ids: Vec<u32> = movies
-                .iter()
-                .filter_map(|m| {
-                    m.get("movieid")
-                        .and_then(|v| v.as_u64().map(|id| id as u32))
-                })
-                .collect();
-
-            sender.send(ids).await?;
+        // Fingerprint fetches for efficient syncing
+        KodiCommand::VideoLibraryGetMovieFingerprints { mut sender } => {
+            let params = rpc_obj_params!("properties" = FINGERPRINT_PROPS);
+            let fingerprints =
+                request_fingerprints(client, "VideoLibrary.GetMovies", params, "movies", "poster")
+                    .await?;
+            let _ = sender.send(fingerprints).await;
             Ok(Event::None)
         }
 
-        KodiCommand::VideoLibraryGetTVShowIDs { mut sender } => {
-            // Request with empty properties array to get just the IDs (IDs are always returned)
-            let response: Value = client
-                .request(
-                    "VideoLibrary.GetTVShows",
-                    rpc_obj_params!("properties" = vec![] as Vec<&str>),
-                )
-                .await?;
-
-            let shows = <Vec<serde_json::Map<String, Value>> as Deserialize>::deserialize(
-                &response["tvshows"],
-            )?;
-            let ids: Vec<u32> = shows
-                .iter()
-                .filter_map(|s| {
-                    s.get("tvshowid")
-                        .and_then(|v| v.as_u64().map(|id| id as u32))
-                })
-                .collect();
-
-            sender.send(ids).await?;
+        KodiCommand::VideoLibraryGetTVShowFingerprints { mut sender } => {
+            let params = rpc_obj_params!("properties" = FINGERPRINT_PROPS);
+            let fingerprints = request_fingerprints(
+                client,
+                "VideoLibrary.GetTVShows",
+                params,
+                "tvshows",
+                "poster",
+            )
+            .await?;
+            let _ = sender.send(fingerprints).await;
             Ok(Event::None)
         }
 
-        KodiCommand::VideoLibraryGetTVEpisodeIDs {
+        KodiCommand::VideoLibraryGetTVEpisodeFingerprints {
             mut sender,
             tvshowid,
         } => {
-            // Request with empty properties array to get just the IDs (IDs are always returned)
-            let response: Value = client
-                .request(
-                    "VideoLibrary.GetEpisodes",
-                    rpc_obj_params!("tvshowid" = tvshowid, "properties" = vec![] as Vec<&str>),
-                )
-                .await?;
-
-            let episodes = <Vec<serde_json::Map<String, Value>> as Deserialize>::deserialize(
-                &response["episodes"],
-            )?;
-            let ids: Vec<u32> = episodes
-                .iter()
-                .filter_map(|e| {
-                    e.get("episodeid")
-                        .and_then(|v| v.as_u64().map(|id| id as u32))
-                })
-                .collect();
-
-            sender.send(ids).await?;
+            let params = rpc_obj_params!("tvshowid" = tvshowid, "properties" = FINGERPRINT_PROPS);
+            let fingerprints = request_fingerprints(
+                client,
+                "VideoLibrary.GetEpisodes",
+                params,
+                "episodes",
+                "thumb",
+            )
+            .await?;
+            let _ = sender.send(fingerprints).await;
             Ok(Event::None)
         }
 
@@ -652,7 +1734,7 @@ async fn handle_kodi_command(
                     movies.push(movie);
                 }
             }
-            sender.send(movies).await?;
+            let _ = sender.send(movies).await;
             Ok(Event::None)
         }
 
@@ -685,7 +1767,7 @@ async fn handle_kodi_command(
                     shows.push(show);
                 }
             }
-            sender.send(shows).await?;
+            let _ = sender.send(shows).await;
             Ok(Event::None)
         }
 
@@ -718,7 +1800,290 @@ async fn handle_kodi_command(
                     episodes.push(ep);
                 }
             }
-            sender.send(episodes).await?;
+            let _ = sender.send(episodes).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::VideoLibraryGetInProgressMovies { mut sender } => {
+            let params = rpc_obj_params!(
+                "properties" = MINIMAL_MOVIE_PROPS,
+                "filter" = in_progress_filter(),
+                "sort" = ListSort {
+                    method: "lastplayed",
+                    order: "descending",
+                }
+            );
+            let movies: Vec<MovieListItem> =
+                request_list(client, "VideoLibrary.GetMovies", params, "movies").await?;
+            let _ = sender.send(movies).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::VideoLibraryGetInProgressEpisodes { mut sender } => {
+            let params = rpc_obj_params!(
+                "properties" = CONTINUE_EP_PROPS,
+                "filter" = in_progress_filter(),
+                "sort" = ListSort {
+                    method: "lastplayed",
+                    order: "descending",
+                }
+            );
+            let episodes: Vec<ContinueEpisode> =
+                request_list(client, "VideoLibrary.GetEpisodes", params, "episodes").await?;
+            let _ = sender.send(episodes).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::VideoLibraryGetInProgressTVShows { mut sender } => {
+            let params = rpc_obj_params!(
+                "properties" = vec![] as Vec<&str>,
+                "sort" = ListSort {
+                    method: "lastplayed",
+                    order: "descending",
+                }
+            );
+            let ids = request_ids(
+                client,
+                "VideoLibrary.GetInProgressTVShows",
+                params,
+                "tvshows",
+                "tvshowid",
+            )
+            .await?;
+            let _ = sender.send(ids).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::VideoLibraryGetNextEpisode {
+            mut sender,
+            tvshowid,
+        } => {
+            // Specials are skipped, they'd always sort first
+            let filter = serde_json::json!({"and": [
+                {"field": "season", "operator": "greaterthan", "value": "0"},
+                {"or": [
+                    in_progress_filter(),
+                    {"field": "playcount", "operator": "is", "value": "0"},
+                ]},
+            ]});
+            let params = rpc_obj_params!(
+                "tvshowid" = tvshowid,
+                "properties" = CONTINUE_EP_PROPS,
+                "filter" = filter,
+                "sort" = ListSort {
+                    method: "episode",
+                    order: "ascending",
+                },
+                "limits" = ListLimits { end: 1 }
+            );
+            let episodes: Vec<ContinueEpisode> =
+                request_list(client, "VideoLibrary.GetEpisodes", params, "episodes").await?;
+            let _ = sender.send(episodes).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::VideoLibrarySetDetails { mut sender, edit } => {
+            let result = set_details(client, edit).await;
+            let _ = sender.send(result.clone()).await;
+            // The sender decides what a refusal means, it's not worth a toast
+            match result {
+                Err(err) if err.needs_reconnect() => Err(err),
+                _ => Ok(Event::None),
+            }
+        }
+
+        KodiCommand::VideoLibraryGetUserState { mut sender, item } => {
+            let (method, result_field) = match item {
+                LibraryItem::Movie(_) => ("VideoLibrary.GetMovieDetails", "moviedetails"),
+                LibraryItem::TVShow(_) => ("VideoLibrary.GetTVShowDetails", "tvshowdetails"),
+                LibraryItem::Episode(_) => ("VideoLibrary.GetEpisodeDetails", "episodedetails"),
+            };
+            let mut params = ObjectParams::new();
+            params
+                .insert(item.id_field(), item.id())
+                .expect("u32 always serializes");
+            params
+                .insert("properties", ["playcount", "userrating"])
+                .expect("&str always serializes");
+            let state: UserState = request_field(client, method, params, result_field).await?;
+
+            let mut edits = vec![LibraryEdit {
+                item,
+                change: EditChange::UserRating(state.userrating),
+            }];
+            // A show's playcount can't be set directly so it's left to the episodes
+            if !matches!(item, LibraryItem::TVShow(_)) {
+                edits.push(LibraryEdit {
+                    item,
+                    change: EditChange::PlayCount(state.playcount),
+                });
+            }
+            let _ = sender.send(edits).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::AudioLibraryGetArtistIDs { mut sender } => {
+            // Only album artists, otherwise every featured artist gets an (empty) entry
+            let params = rpc_obj_params!(
+                "albumartistsonly" = true,
+                "properties" = vec![] as Vec<&str>
+            );
+            let ids = request_ids(
+                client,
+                "AudioLibrary.GetArtists",
+                params,
+                "artists",
+                "artistid",
+            )
+            .await?;
+            let _ = sender.send(ids).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::AudioLibraryGetAlbumIDs {
+            mut sender,
+            artistid,
+        } => {
+            let params = rpc_obj_params!(
+                "filter" = serde_json::json!({"artistid": artistid}),
+                "properties" = vec![] as Vec<&str>
+            );
+            let ids = request_ids(
+                client,
+                "AudioLibrary.GetAlbums",
+                params,
+                "albums",
+                "albumid",
+            )
+            .await?;
+            let _ = sender.send(ids).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::AudioLibraryGetSongIDs {
+            mut sender,
+            albumid,
+        } => {
+            let params = rpc_obj_params!(
+                "filter" = serde_json::json!({"albumid": albumid}),
+                "properties" = vec![] as Vec<&str>
+            );
+            let ids =
+                request_ids(client, "AudioLibrary.GetSongs", params, "songs", "songid").await?;
+            let _ = sender.send(ids).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::AudioLibraryGetArtistsByIDs { mut sender, ids } => {
+            let artists = request_details_by_ids(
+                client,
+                ids,
+                "AudioLibrary.GetArtistDetails",
+                "artistid",
+                &MINIMAL_ARTIST_PROPS,
+                "artistdetails",
+            )
+            .await;
+            let _ = sender.send(artists).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::AudioLibraryGetAlbumsByIDs { mut sender, ids } => {
+            let albums = request_details_by_ids(
+                client,
+                ids,
+                "AudioLibrary.GetAlbumDetails",
+                "albumid",
+                &MINIMAL_ALBUM_PROPS,
+                "albumdetails",
+            )
+            .await;
+            let _ = sender.send(albums).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::AudioLibraryGetSongsByIDs { mut sender, ids } => {
+            let songs = request_details_by_ids(
+                client,
+                ids,
+                "AudioLibrary.GetSongDetails",
+                "songid",
+                &MINIMAL_SONG_PROPS,
+                "songdetails",
+            )
+            .await;
+            let _ = sender.send(songs).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PVRGetChannelGroups { mut sender } => {
+            let params = rpc_obj_params!("channeltype" = "tv");
+            let groups: Vec<PVRChannelGroup> =
+                request_field(client, "PVR.GetChannelGroups", params, "channelgroups").await?;
+            let _ = sender.send(groups).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PVRGetChannels {
+            mut sender,
+            channelgroupid,
+        } => {
+            let params = rpc_obj_params!(
+                "channelgroupid" = channelgroupid,
+                "properties" = PVR_CHANNEL_PROPS
+            );
+            let channels: Vec<PVRChannel> =
+                request_field(client, "PVR.GetChannels", params, "channels").await?;
+            let _ = sender.send(channels).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PVRGetBroadcasts {
+            mut sender,
+            channelid,
+        } => {
+            let params =
+                rpc_obj_params!("channelid" = channelid, "properties" = PVR_BROADCAST_PROPS);
+            // no epg data just leaves out "broadcasts"
+            let broadcasts: Vec<PVRBroadcast> =
+                request_list(client, "PVR.GetBroadcasts", params, "broadcasts").await?;
+            let _ = sender.send(broadcasts).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PVRGetRecordings { mut sender } => {
+            let params = rpc_obj_params!("properties" = PVR_RECORDING_PROPS);
+            let recordings: Vec<PVRRecording> =
+                request_list(client, "PVR.GetRecordings", params, "recordings").await?;
+            let _ = sender.send(recordings).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PVRGetTimers { mut sender } => {
+            let params = rpc_obj_params!("properties" = PVR_TIMER_PROPS);
+            let timers: Vec<PVRTimer> =
+                request_list(client, "PVR.GetTimers", params, "timers").await?;
+            let _ = sender.send(timers).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PVRAddTimer {
+            mut sender,
+            broadcastid,
+        } => {
+            let params = rpc_obj_params!("broadcastid" = broadcastid);
+            let _: Value = client.request("PVR.AddTimer", params).await?;
+            let _ = sender.send(()).await;
+            Ok(Event::None)
+        }
+
+        KodiCommand::PVRDeleteTimer {
+            mut sender,
+            timerid,
+        } => {
+            let params = rpc_obj_params!("timerid" = timerid);
+            let _: Value = client.request("PVR.DeleteTimer", params).await?;
+            let _ = sender.send(()).await;
             Ok(Event::None)
         }
 
@@ -754,22 +2119,73 @@ async fn handle_kodi_command(
 }
 
 async fn handle_notification(
-    client: &Client,
+    client: &KodiClient,
     function: &str,
     data: Result<Value, serde_json::Error>,
-) -> Result<Event, Box<dyn Error + Sync + Send>> {
+    with_chapters: bool,
+) -> Result<Event, KodiError> {
+    let data = data.map_err(|err| KodiError::deserialize(function, "params", err));
     match function {
         "Player.OnPlay" => {
             let info = data?;
-            let player = <ActivePlayer as Deserialize>::deserialize(&info["data"]["player"])?;
+            let player = <ActivePlayer as Deserialize>::deserialize(&info["data"]["player"])
+                .map_err(|err| KodiError::deserialize(function, "player", err))?;
 
             handle_kodi_command(KodiCommand::PlayerGetPlayingItem(player.playerid), client).await
         }
 
-        "Player.OnStop" => {
-            let not_playing = PlayingItem::default();
-            let not_playing = Event::UpdatePlayingItem(not_playing);
-            Ok(not_playing)
+        // Kodi doesn't say which player stopped, and a new player or stream
+        // has more to it than the notification carries
+        "Player.OnStop" | "Player.OnAVStart" | "Player.OnAVChange" => {
+            poll_player_status(client, with_chapters).await
+        }
+
+        "Player.OnPause" | "Player.OnResume" | "Player.OnSpeedChanged" => {
+            let player = notified_player(function, &data?)?;
+            Ok(Event::PlayerChanged(
+                player.playerid,
+                PlayerChange::Speed(player.speed),
+            ))
+        }
+
+        "Player.OnSeek" => {
+            let player = notified_player(function, &data?)?;
+            let Some(time) = player.time else {
+                return Ok(Event::None);
+            };
+            Ok(Event::PlayerChanged(
+                player.playerid,
+                PlayerChange::Seek {
+                    speed: player.speed,
+                    time,
+                },
+            ))
+        }
+
+        "Player.OnPropertyChanged" => {
+            let info = data?;
+            let player = notified_player(function, &info)?;
+            let property = &info["data"]["property"];
+            let parse_err = |err| KodiError::deserialize(function, "property", err);
+            let change = if let Some(shuffled) = property.get("shuffled") {
+                PlayerChange::Shuffled(bool::deserialize(shuffled).map_err(parse_err)?)
+            } else if let Some(repeat) = property.get("repeat") {
+                PlayerChange::Repeat(Repeat::deserialize(repeat).map_err(parse_err)?)
+            } else if let Some(partymode) = property.get("partymode") {
+                PlayerChange::PartyMode(bool::deserialize(partymode).map_err(parse_err)?)
+            } else {
+                // Nothing that's shown, the next poll has it anyway
+                return Ok(Event::None);
+            };
+            Ok(Event::PlayerChanged(player.playerid, change))
+        }
+
+        "Application.OnVolumeChanged" => {
+            let info = data?;
+            Ok(Event::UpdateKodiAppStatus(app_status(
+                function,
+                &info["data"],
+            )?))
         }
 
         "Input.OnInputRequested" => {
@@ -782,6 +2198,31 @@ async fn handle_notification(
             Ok(Event::InputRequested(req.to_string()))
         }
 
+        "Playlist.OnAdd" | "Playlist.OnRemove" | "Playlist.OnClear" => {
+            let info = data?;
+            let playlist_id = <u8 as Deserialize>::deserialize(&info["data"]["playlistid"])
+                .map_err(|err| KodiError::deserialize(function, "playlistid", err))?;
+            Ok(Event::PlaylistChanged(playlist_id))
+        }
+
+        // Music videos etc aren't cached so they're ignored
+        "VideoLibrary.OnUpdate" => {
+            let info = data?;
+            // Playcount changes nest the item, everything else has it at the top
+            let item = match info["data"].get("item") {
+                Some(item) => library_item(item),
+                None => library_item(&info["data"]),
+            };
+            Ok(item.map_or(Event::None, Event::LibraryUpdated))
+        }
+
+        "VideoLibrary.OnRemove" => {
+            let info = data?;
+            Ok(library_item(&info["data"]).map_or(Event::None, Event::LibraryRemoved))
+        }
+
+        "VideoLibrary.OnScanFinished" | "VideoLibrary.OnCleanFinished" => Ok(Event::LibraryScanned),
+
         _ => {
             debug!(function, data = ?data.ok(), "Unhandled notification");
             Ok(Event::None)
@@ -789,24 +2230,84 @@ async fn handle_notification(
     }
 }
 
+// The player part of a Player.On* notification, speed and time depend on which
+#[derive(Deserialize)]
+struct NotifiedPlayer {
+    playerid: u8,
+    #[serde(default)]
+    speed: f64,
+    time: Option<KodiTime>,
+}
+
+fn notified_player(function: &str, info: &Value) -> Result<NotifiedPlayer, KodiError> {
+    NotifiedPlayer::deserialize(&info["data"]["player"])
+        .map_err(|err| KodiError::deserialize(function, "player", err))
+}
+
+fn library_item(value: &Value) -> Option<LibraryItem> {
+    let id = value["id"].as_u64()? as u32;
+    match value["type"].as_str()? {
+        "movie" => Some(LibraryItem::Movie(id)),
+        "tvshow" => Some(LibraryItem::TVShow(id)),
+        "episode" => Some(LibraryItem::Episode(id)),
+        _ => None,
+    }
+}
+
 #[derive(Debug)]
 enum State {
     Disconnected,
-    Connected(Client, Receiver<KodiCommand>),
-    Offline(Receiver<KodiCommand>),
+    // Shared with a chapter seek still stepping
+    Connected(Arc<KodiClient>, Receiver<Queued>),
+    // Retries at the Instant
+    Offline(Receiver<Queued>, Instant),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ConnectionStatus {
+    #[default]
+    Connecting,
+    Connected,
+    Retrying {
+        // Whole seconds, rounded up
+        retry_in: u64,
+        error: String,
+    },
+}
+
+impl ConnectionStatus {
+    fn retrying(retry_in: Duration, error: &str) -> Self {
+        let secs = retry_in.as_secs() + u64::from(retry_in.subsec_nanos() > 0);
+        ConnectionStatus::Retrying {
+            retry_in: secs,
+            error: error.to_string(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum Event {
     Connected(Connection),
     Disconnected(Connection),
+    ConnectionStatus(ConnectionStatus),
+    // Kodi rejected a command, the connection is still fine
+    CommandFailed(KodiError),
     None,
     // UpdateSources(Vec<Sources>),
     // UpdateDirList(Vec<DirList>, String),
-    UpdatePlayerProps(Option<PlayerProps>),
+    // Every active player, empty when nothing is playing
+    UpdatePlayers(Vec<PlayerProps>),
     UpdateKodiAppStatus(KodiAppStatus),
-    UpdatePlayingItem(PlayingItem), // Might change to Option
+    UpdatePlayingItem(u8, Box<PlayingItem>),
+    // A player notification, applied over the last poll
+    PlayerChanged(u8, PlayerChange),
     InputRequested(String),
+    PlaylistChanged(u8),
+    // VideoLibrary notifications
+    LibraryUpdated(LibraryItem),
+    LibraryRemoved(LibraryItem),
+    // Scan or clean finished, anything could have changed
+    LibraryScanned,
     // UpdateMovieList(Vec<MovieListItem>),
     // UpdateTVList(
     //     Vec<TVShowListItem>,
@@ -814,3 +2315,1075 @@ pub enum Event {
     //     Vec<TVEpisodeListItem>,
     // ),
 }
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayerChange {
+    Speed(f64),
+    Seek { speed: f64, time: KodiTime },
+    Shuffled(bool),
+    Repeat(Repeat),
+    PartyMode(bool),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mockkodi::{MockKodi, wait_for};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    // Player.GetProperties a second into a video, with `overrides` replacing any of it
+    fn player_props_json(overrides: Value) -> Value {
+        let time = json!({"hours": 0, "minutes": 0, "seconds": 1, "milliseconds": 0});
+        let mut props = json!({
+            "speed": 1,
+            "time": time,
+            "totaltime": time,
+            "currentaudiostream": {},
+            "audiostreams": [],
+            "currentsubtitle": {},
+            "subtitles": [],
+            "playlistid": 1,
+            "position": 0,
+            "subtitleenabled": false,
+            "type": "video",
+        });
+        props
+            .as_object_mut()
+            .unwrap()
+            .extend(overrides.as_object().unwrap().clone());
+        props
+    }
+
+    fn movie(movieid: u32, title: &str, playcount: i16) -> Value {
+        json!({
+            "movieid": movieid,
+            "label": title,
+            "title": title,
+            "year": 2001,
+            "file": format!("/movies/{}.mkv", title),
+            "dateadded": "2024-01-01 10:00:00",
+            "genre": ["Drama", "Comedy"],
+            "rating": 7.25,
+            "premiered": "2001-05-04",
+            "playcount": playcount,
+            "art": {"poster": format!("image://{}.jpg/", title)},
+            "resume": {"position": 60.0, "total": 600.0},
+            "userrating": 8,
+            "lastplayed": "2024-02-02 20:00:00",
+        })
+    }
+
+    #[tokio::test]
+    async fn connects_and_reconnects() {
+        let mock = MockKodi::start().await;
+        let (_connection, mut events) = mock.connect_client().await;
+        assert_eq!(mock.connections(), 1);
+
+        // Polling starts straight away
+        wait_for(&mut events, |e| matches!(e, Event::UpdatePlayers(_))).await;
+
+        mock.disconnect();
+        wait_for(&mut events, |e| matches!(e, Event::Disconnected(_))).await;
+        wait_for(&mut events, |e| matches!(e, Event::Connected(_))).await;
+        assert_eq!(mock.connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn player_on_play_fetches_playing_item() {
+        let mock = MockKodi::start().await;
+        mock.respond(
+            "Player.GetItem",
+            json!({"item": {
+                "label": "Pilot",
+                "title": "Pilot",
+                "file": "/tv/show/s01e01.mkv",
+                "showtitle": "Show",
+                "season": 1,
+                "episode": 1,
+                "type": "episode",
+                "plot": "It begins.",
+                "rating": 8.2,
+                "art": {
+                    "thumb": "image://thumb.jpg/",
+                    "tvshow.poster": "image://poster.jpg/",
+                    "tvshow.fanart": "image://fanart.jpg/",
+                },
+                "cast": [{"name": "Someone", "order": 0, "role": "Lead"}],
+                "streamdetails": {"audio": [], "subtitle": [], "video": []},
+            }}),
+        );
+        let (_connection, mut events) = mock.connect_client().await;
+
+        mock.notify(
+            "Player.OnPlay",
+            json!({"item": {"type": "episode", "id": 1}, "player": {"playerid": 1, "speed": 1}}),
+        );
+        let event = wait_for(&mut events, |e| matches!(e, Event::UpdatePlayingItem(..))).await;
+        let Event::UpdatePlayingItem(player_id, item) = event else {
+            unreachable!()
+        };
+        assert_eq!(player_id, 1);
+        assert_eq!(item.showtitle.as_deref(), Some("Show"));
+        assert_eq!(item.make_title(), "Show - S01E01 - Pilot");
+        assert_eq!(item.plot, "It begins.");
+        assert_eq!(item.cast[0].role, "Lead");
+        // The show's art when the episode has none of its own
+        assert_eq!(item.poster(), Some("image://poster.jpg/"));
+        assert_eq!(item.fanart(), Some("image://fanart.jpg/"));
+        assert_eq!(mock.calls("Player.GetItem")[0]["playerid"], 1);
+    }
+
+    #[tokio::test]
+    async fn tracks_every_active_player() {
+        let mock = MockKodi::start().await;
+        mock.respond(
+            "Player.GetActivePlayers",
+            json!([
+                {"playerid": 0, "playertype": "internal", "type": "audio"},
+                {"playerid": 2, "playertype": "internal", "type": "picture"},
+            ]),
+        );
+        mock.handle("Player.GetProperties", move |params| {
+            let player_id = params["playerid"].as_u64().unwrap();
+            Ok(player_props_json(json!({
+                "speed": player_id,
+                "playlistid": -1,
+                "position": -1,
+                "type": if player_id == 0 { "audio" } else { "picture" },
+            })))
+        });
+        let (_connection, mut events) = mock.connect_client().await;
+
+        let event = wait_for(
+            &mut events,
+            |e| matches!(e, Event::UpdatePlayers(players) if !players.is_empty()),
+        )
+        .await;
+        let Event::UpdatePlayers(players) = event else {
+            unreachable!()
+        };
+        let players: Vec<_> = players
+            .iter()
+            .map(|p| (p.player_id, p.player_type, p.speed))
+            .collect();
+        assert_eq!(
+            players,
+            vec![
+                (Some(0), PlayerType::Audio, 0.0),
+                (Some(2), PlayerType::Picture, 2.0)
+            ]
+        );
+
+        // A stop doesn't say which player, all of them get looked at again
+        mock.respond(
+            "Player.GetActivePlayers",
+            json!([{"playerid": 0, "playertype": "internal", "type": "audio"}]),
+        );
+        mock.notify(
+            "Player.OnStop",
+            json!({"item": {"type": "picture"}, "end": true}),
+        );
+        wait_for(
+            &mut events,
+            |e| matches!(e, Event::UpdatePlayers(players) if players.len() == 1),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn volume_changes_are_pushed() {
+        let mock = MockKodi::start().await;
+        let (mut connection, mut events) = mock.connect_client().await;
+
+        let event = wait_for(&mut events, |e| matches!(e, Event::UpdateKodiAppStatus(_))).await;
+        let Event::UpdateKodiAppStatus(status) = event else {
+            unreachable!()
+        };
+        assert_eq!((status.volume, status.muted), (100, false));
+
+        // The notification's volume is a float
+        mock.notify(
+            "Application.OnVolumeChanged",
+            json!({"muted": true, "volume": 41.6}),
+        );
+        let event = wait_for(&mut events, |e| matches!(e, Event::UpdateKodiAppStatus(_))).await;
+        let Event::UpdateKodiAppStatus(status) = event else {
+            unreachable!()
+        };
+        assert_eq!((status.volume, status.muted), (42, true));
+
+        mock.respond("Application.SetVolume", json!(30));
+        connection
+            .request(KodiCommand::SetVolume(30))
+            .await
+            .unwrap();
+        assert_eq!(mock.calls("Application.SetVolume")[0]["volume"], 30);
+
+        // Only asked for once, the websocket isn't polled for it
+        mock.notify("Player.OnStop", json!({"item": {}, "end": false}));
+        wait_for(&mut events, |e| matches!(e, Event::UpdatePlayers(_))).await;
+        assert_eq!(mock.calls("Application.GetProperties").len(), 1);
+    }
+
+    #[tokio::test]
+    async fn player_notifications_update_players() {
+        let mock = MockKodi::start().await;
+        let (_connection, mut events) = mock.connect_client().await;
+        wait_for(&mut events, |e| matches!(e, Event::UpdatePlayers(_))).await;
+        let polls = mock.calls("Player.GetActivePlayers").len();
+
+        mock.notify(
+            "Player.OnPause",
+            json!({"item": {"type": "movie", "id": 1}, "player": {"playerid": 1, "speed": 0}}),
+        );
+        wait_for(
+            &mut events,
+            |e| matches!(e, Event::PlayerChanged(1, PlayerChange::Speed(speed)) if *speed == 0.0),
+        )
+        .await;
+
+        let time = json!({"hours": 0, "minutes": 10, "seconds": 0, "milliseconds": 0});
+        mock.notify(
+            "Player.OnSeek",
+            json!({"item": {"type": "movie", "id": 1}, "player": {"playerid": 1, "speed": 1, "time": time}}),
+        );
+        wait_for(&mut events, |e| {
+            matches!(e, Event::PlayerChanged(1, PlayerChange::Seek { time, .. }) if time.minutes == 10)
+        })
+        .await;
+
+        mock.notify(
+            "Player.OnPropertyChanged",
+            json!({"player": {"playerid": 1}, "property": {"shuffled": true}}),
+        );
+        wait_for(&mut events, |e| {
+            matches!(e, Event::PlayerChanged(1, PlayerChange::Shuffled(true)))
+        })
+        .await;
+
+        // The notifications carry what changed, nothing had to be polled
+        assert_eq!(mock.calls("Player.GetActivePlayers").len(), polls);
+    }
+
+    #[tokio::test]
+    async fn play_modes_come_from_player_props() {
+        let mock = MockKodi::start().await;
+        mock.respond(
+            "Player.GetActivePlayers",
+            json!([{"playerid": 0, "playertype": "internal", "type": "audio"}]),
+        );
+        mock.respond(
+            "Player.GetProperties",
+            player_props_json(json!({
+                "playlistid": 0,
+                "position": 3,
+                "repeat": "all",
+                "shuffled": true,
+                "partymode": true,
+                "type": "audio",
+            })),
+        );
+        let (mut connection, mut events) = mock.connect_client().await;
+
+        let event = wait_for(&mut events, |e| matches!(e, Event::UpdatePlayers(_))).await;
+        let Event::UpdatePlayers(players) = event else {
+            unreachable!()
+        };
+        let props = &players[0];
+        assert_eq!(props.repeat, Repeat::All);
+        assert!(props.shuffled && props.partymode);
+
+        mock.respond("Player.SetRepeat", json!("OK"));
+        connection
+            .request(KodiCommand::PlayerSetRepeat {
+                player_id: 0,
+                repeat: props.repeat.next(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(mock.calls("Player.SetRepeat")[0]["repeat"], "one");
+    }
+
+    #[tokio::test]
+    async fn video_streams_can_be_picked() {
+        let mock = MockKodi::start().await;
+        mock.respond(
+            "Player.GetActivePlayers",
+            json!([{"playerid": 1, "playertype": "internal", "type": "video"}]),
+        );
+        let angle = |index: u8| {
+            json!({
+                "codec": "hevc",
+                "height": 2160,
+                "index": index,
+                "language": "eng",
+                "name": format!("Angle {}", index + 1),
+                "width": 3840,
+                "hdrtype": "dolbyvision",
+            })
+        };
+        mock.respond(
+            "Player.GetProperties",
+            player_props_json(json!({
+                "currentaudiostream": {
+                    "bitrate": 768000,
+                    "channels": 6,
+                    "codec": "eac3",
+                    "index": 0,
+                    "isdefault": true,
+                    "isimpaired": false,
+                    "isoriginal": true,
+                    "language": "eng",
+                    "name": "Surround",
+                    "samplerate": 48000,
+                },
+                "currentvideostream": angle(0),
+                "videostreams": [angle(0), angle(1)],
+            })),
+        );
+        let (mut connection, mut events) = mock.connect_client().await;
+
+        let event = wait_for(&mut events, |e| matches!(e, Event::UpdatePlayers(_))).await;
+        let Event::UpdatePlayers(players) = event else {
+            unreachable!()
+        };
+        let props = &players[0];
+        assert_eq!(props.videostreams.len(), 2);
+        assert_eq!(props.tech_info(), "3840x2160 HEVC Dolby Vision · EAC3 5.1");
+
+        mock.respond("Player.SetVideoStream", json!("OK"));
+        connection
+            .request(KodiCommand::PlayerSetVideoStream {
+                player_id: 1,
+                video_index: props.videostreams[1].index,
+            })
+            .await
+            .unwrap();
+        assert_eq!(mock.calls("Player.SetVideoStream")[0]["stream"], 1);
+    }
+
+    #[tokio::test]
+    async fn edit_senders_hear_why_kodi_refused() {
+        let mock = MockKodi::start().await;
+        mock.handle("VideoLibrary.SetMovieDetails", |params| {
+            if params["movieid"] == 1 {
+                Ok(json!("OK"))
+            } else {
+                Err("Invalid params.".to_string())
+            }
+        });
+        let (mut connection, mut events) = mock.connect_client().await;
+
+        let mut edit = |movieid| {
+            let (tx, rx) = channel(1);
+            connection.send(KodiCommand::VideoLibrarySetDetails {
+                sender: tx,
+                edit: LibraryEdit {
+                    item: LibraryItem::Movie(movieid),
+                    change: EditChange::PlayCount(1),
+                },
+            });
+            rx
+        };
+        let mut accepted = edit(1);
+        let mut rejected = edit(2);
+        assert_eq!(accepted.next().await, Some(Ok(())));
+        assert!(matches!(
+            rejected.next().await,
+            Some(Err(KodiError::Rpc { code: -32602, .. }))
+        ));
+
+        // Undoing or queueing the edit is up to the sender, no toast
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        while let Ok(Some(event)) = events.try_next() {
+            assert!(!matches!(event, Event::CommandFailed(_)), "{:?}", event);
+        }
+    }
+
+    // One video playing
+    fn play_video(mock: &MockKodi) {
+        mock.respond(
+            "Player.GetActivePlayers",
+            json!([{"playerid": 1, "playertype": "internal", "type": "video"}]),
+        );
+        mock.respond("Player.GetProperties", player_props_json(json!({})));
+    }
+
+    #[tokio::test]
+    async fn players_are_polled_without_chapters() {
+        // No infolabels scripted, so the chapters fail
+        let mock = MockKodi::start().await;
+        play_video(&mock);
+        let (mut connection, mut events) = mock.connect_client().await;
+        wait_for(&mut events, |e| matches!(e, Event::UpdatePlayers(_))).await;
+
+        connection.send(KodiCommand::SetNowPlayingOpen(true));
+        let event = wait_for(&mut events, |e| matches!(e, Event::UpdatePlayers(_))).await;
+        let Event::UpdatePlayers(players) = event else {
+            unreachable!()
+        };
+        assert!(!mock.calls("XBMC.GetInfoLabels").is_empty());
+        assert_eq!(players.len(), 1);
+        assert_eq!(players[0].chapters, Chapters::default());
+    }
+
+    #[tokio::test]
+    async fn steps_through_chapters() {
+        let mock = MockKodi::start().await;
+        play_video(&mock);
+        // Kodi's side of it, one chapter per action
+        let chapter = Arc::new(AtomicU32::new(1));
+        let current = chapter.clone();
+        mock.handle("XBMC.GetInfoLabels", move |_| {
+            let chapter = current.load(Ordering::SeqCst);
+            Ok(json!({
+                "Player.Chapter": format!("{:02}", chapter),
+                "Player.ChapterCount": "05",
+                "Player.ChapterName": format!("Part {}", chapter),
+            }))
+        });
+        let current = chapter.clone();
+        mock.handle("Input.ExecuteAction", move |params| {
+            let step = match params[0].as_str() {
+                Some("chapterorbigstepforward") => 1,
+                Some("chapterorbigstepback") => -1,
+                _ => 0,
+            };
+            let chapter = current.load(Ordering::SeqCst) as i32 + step;
+            current.store(chapter.clamp(1, 5) as u32, Ordering::SeqCst);
+            Ok(json!("OK"))
+        });
+        let (mut connection, mut events) = mock.connect_client().await;
+
+        // Left alone until the now playing panel shows them
+        let event = wait_for(&mut events, |e| matches!(e, Event::UpdatePlayers(_))).await;
+        let Event::UpdatePlayers(players) = event else {
+            unreachable!()
+        };
+        assert_eq!(players[0].chapters, Chapters::default());
+        assert!(mock.calls("XBMC.GetInfoLabels").is_empty());
+
+        connection.send(KodiCommand::SetNowPlayingOpen(true));
+        let event = wait_for(
+            &mut events,
+            |e| matches!(e, Event::UpdatePlayers(players) if players[0].chapters.count > 0),
+        )
+        .await;
+        let Event::UpdatePlayers(players) = event else {
+            unreachable!()
+        };
+        assert_eq!(
+            players[0].chapters,
+            Chapters {
+                current: 1,
+                count: 5,
+                name: "Part 1".to_string(),
+            }
+        );
+
+        connection
+            .request(KodiCommand::PlayerGoToChapter(4))
+            .await
+            .unwrap();
+        assert_eq!(chapter.load(Ordering::SeqCst), 4);
+        assert_eq!(mock.calls("Input.ExecuteAction").len(), 3);
+
+        connection
+            .request(KodiCommand::PlayerGoToChapter(2))
+            .await
+            .unwrap();
+        assert_eq!(chapter.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            mock.calls("Input.ExecuteAction")[4][0],
+            "chapterorbigstepback"
+        );
+
+        // Out of range is refused without stepping
+        let err = connection
+            .request(KodiCommand::PlayerGoToChapter(9))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err,
+            KodiError::NoChapter {
+                chapter: 9,
+                count: 5
+            }
+        );
+        assert_eq!(mock.calls("Input.ExecuteAction").len(), 5);
+    }
+
+    #[tokio::test]
+    async fn chapter_steps_do_not_hold_up_commands() {
+        let mock = MockKodi::start().await;
+        play_video(&mock);
+        mock.respond(
+            "XBMC.GetInfoLabels",
+            json!({
+                "Player.Chapter": "01",
+                "Player.ChapterCount": "05",
+                "Player.ChapterName": "Part 1",
+            }),
+        );
+        // Kodi never finishes the first step
+        mock.stall("Input.ExecuteAction");
+        mock.respond("Input.SendText", json!("OK"));
+        let (mut connection, mut events) = mock.connect_client().await;
+        wait_for(&mut events, |e| matches!(e, Event::UpdatePlayers(_))).await;
+
+        let seek = connection.request(KodiCommand::PlayerGoToChapter(4));
+        while mock.calls("Input.ExecuteAction").is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        tokio::time::timeout(
+            Duration::from_secs(2),
+            connection.request(KodiCommand::InputSendText("text".into())),
+        )
+        .await
+        .expect("Command waited on the chapter steps")
+        .unwrap();
+        drop(seek);
+    }
+
+    #[tokio::test]
+    async fn bad_poll_responses_keep_the_connection() {
+        let mock = MockKodi::start().await;
+        mock.respond("Player.GetActivePlayers", json!([{"playerid": "one"}]));
+        let (_connection, _events) = mock.connect_client().await;
+        while mock.calls("Player.GetActivePlayers").is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(mock.connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn subtitle_changes_refresh_players() {
+        let mock = MockKodi::start().await;
+        let (mut connection, mut events) = mock.connect_client().await;
+        wait_for(&mut events, |e| matches!(e, Event::UpdatePlayers(_))).await;
+        let polls = mock.calls("Player.GetActivePlayers").len();
+
+        mock.respond("Player.SetSubtitle", json!("OK"));
+        connection
+            .request(KodiCommand::PlayerNextSubtitle(1))
+            .await
+            .unwrap();
+        let call = &mock.calls("Player.SetSubtitle")[0];
+        assert_eq!(call["subtitle"], "next");
+        assert_eq!(call["enable"], true);
+
+        // Kodi has no notification for it, the client asks straight away
+        wait_for(&mut events, |e| matches!(e, Event::UpdatePlayers(_))).await;
+        assert_eq!(mock.calls("Player.GetActivePlayers").len(), polls + 1);
+    }
+
+    #[tokio::test]
+    async fn polling_stops_while_minimized_and_idle() {
+        let mock = MockKodi::start().await;
+        let server = KodiServer {
+            transport: Transport::Http,
+            ..mock.server.as_ref().clone()
+        };
+        let (mut connection, mut events) = mock.connect_client_to(server).await;
+        wait_for(&mut events, |e| matches!(e, Event::UpdatePlayers(_))).await;
+
+        connection.send(KodiCommand::SetMinimized(true));
+        // Let a poll that's already going finish
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let polls = mock.calls("Player.GetActivePlayers").len();
+        tokio::time::sleep(HTTP_POLL * 3).await;
+        assert_eq!(mock.calls("Player.GetActivePlayers").len(), polls);
+
+        // Restoring polls straight away
+        connection.send(KodiCommand::SetMinimized(false));
+        tokio::time::timeout(
+            HTTP_POLL / 2,
+            wait_for(&mut events, |e| matches!(e, Event::UpdatePlayers(_))),
+        )
+        .await
+        .unwrap();
+    }
+
+    // A port nothing is listening on
+    async fn closed_port() -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    #[tokio::test]
+    async fn auto_transport_falls_back_to_http() {
+        let mock = MockKodi::start().await;
+        let server = KodiServer {
+            websocket_port: closed_port().await,
+            ..mock.server.as_ref().clone()
+        };
+        let (_connection, mut events) = mock.connect_client_to(server).await;
+
+        assert_eq!(mock.connections(), 0);
+        assert!(!mock.calls("JSONRPC.Ping").is_empty());
+        wait_for(&mut events, |e| matches!(e, Event::UpdateKodiAppStatus(_))).await;
+    }
+
+    #[tokio::test]
+    async fn websocket_transport_does_not_fall_back() {
+        let mock = MockKodi::start().await;
+        let server = KodiServer {
+            websocket_port: closed_port().await,
+            transport: Transport::WebSocket,
+            ..mock.server.as_ref().clone()
+        };
+        let (tx, mut events) = channel(100);
+        tokio::spawn(connect(Arc::new(server), tx, Backoff::default()));
+
+        wait_for(&mut events, |e| matches!(e, Event::Disconnected(_))).await;
+        let connected = tokio::time::timeout(
+            Duration::from_secs(1),
+            wait_for(&mut events, |e| matches!(e, Event::Connected(_))),
+        )
+        .await;
+        assert!(connected.is_err());
+        assert!(mock.calls("JSONRPC.Ping").is_empty());
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        let backoff = Backoff {
+            min: Duration::from_secs(2),
+            max: Duration::from_secs(30),
+            jitter: 0.25,
+        };
+        for (failures, base) in [(0, 2.0), (1, 4.0), (2, 8.0), (3, 16.0)] {
+            let delay = backoff.delay(failures).as_secs_f64();
+            assert!((base * 0.75..=base * 1.25).contains(&delay), "{}", delay);
+        }
+        for failures in [4, 10, 1000] {
+            let delay = backoff.delay(failures).as_secs_f64();
+            assert!((22.5..=30.0).contains(&delay), "{}", delay);
+        }
+    }
+
+    #[tokio::test]
+    async fn offline_reports_retry_countdown() {
+        let server = KodiServer {
+            ip: "127.0.0.1".to_string(),
+            websocket_port: closed_port().await,
+            transport: Transport::WebSocket,
+            ..Default::default()
+        };
+        let backoff = Backoff {
+            min: Duration::from_secs(2),
+            max: Duration::from_secs(2),
+            jitter: 0.0,
+        };
+        let (tx, mut events) = channel(100);
+        tokio::spawn(connect(Arc::new(server), tx, backoff));
+        // Kept like the data layer does, commands can still be sent while offline
+        let _offline = wait_for(&mut events, |e| matches!(e, Event::Disconnected(_))).await;
+
+        let is_status = |e: &Event| matches!(e, Event::ConnectionStatus(_));
+        let mut statuses = Vec::new();
+        for _ in 0..4 {
+            let Event::ConnectionStatus(status) = wait_for(&mut events, is_status).await else {
+                unreachable!()
+            };
+            statuses.push(status);
+        }
+        assert_eq!(statuses[0], ConnectionStatus::Connecting);
+        let ConnectionStatus::Retrying { retry_in, error } = &statuses[1] else {
+            panic!("Expected a retry, got {:?}", statuses[1]);
+        };
+        assert_eq!(*retry_in, 2);
+        assert!(!error.is_empty());
+        assert!(
+            matches!(statuses[2], ConnectionStatus::Retrying { retry_in: 1, .. }),
+            "{:?}",
+            statuses
+        );
+        assert_eq!(statuses[3], ConnectionStatus::Connecting);
+    }
+
+    #[tokio::test]
+    async fn http_transport_polls_for_changes() {
+        let mock = MockKodi::start().await;
+        let server = KodiServer {
+            transport: Transport::Http,
+            ..mock.server.as_ref().clone()
+        };
+        let (mut connection, mut events) = mock.connect_client_to(server).await;
+        assert_eq!(mock.connections(), 0);
+        wait_for(&mut events, |e| matches!(e, Event::UpdatePlayers(_))).await;
+
+        // Commands go over HTTP too
+        mock.respond("VideoLibrary.GetMovies", json!({"limits": {"total": 0}}));
+        let (tx, mut rx) = channel(1);
+        connection.send(KodiCommand::VideoLibraryGetMovieFingerprints { sender: tx });
+        assert!(rx.next().await.unwrap().is_empty());
+
+        mock.respond("Playlist.GetProperties", json!({"size": 3}));
+        wait_for(&mut events, |e| matches!(e, Event::PlaylistChanged(_))).await;
+
+        mock.respond(
+            "XBMC.GetInfoBooleans",
+            json!({"Library.IsScanningVideo": true}),
+        );
+        // Seen scanning at least once
+        let checks = mock.calls("XBMC.GetInfoBooleans").len();
+        while mock.calls("XBMC.GetInfoBooleans").len() == checks {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        mock.respond(
+            "XBMC.GetInfoBooleans",
+            json!({"Library.IsScanningVideo": false}),
+        );
+        wait_for(&mut events, |e| matches!(e, Event::LibraryScanned)).await;
+
+        mock.respond(
+            "GUI.GetProperties",
+            json!({"currentwindow": {"id": 10103, "label": "Keyboard"}}),
+        );
+        wait_for(&mut events, |e| matches!(e, Event::InputRequested(_))).await;
+
+        mock.respond("Player.GetProperties", player_props_json(json!({})));
+        mock.respond(
+            "Player.GetItem",
+            json!({"item": {"label": "Movie", "title": "Movie", "file": "/movies/movie.mkv", "type": "movie"}}),
+        );
+        mock.respond(
+            "Player.GetActivePlayers",
+            json!([{"playerid": 1, "playertype": "internal", "type": "video"}]),
+        );
+        let event = wait_for(&mut events, |e| matches!(e, Event::UpdatePlayingItem(..))).await;
+        let Event::UpdatePlayingItem(player_id, item) = event else {
+            unreachable!()
+        };
+        assert_eq!(player_id, 1);
+        assert_eq!(item.title, "Movie");
+
+        mock.respond("Player.GetActivePlayers", json!([]));
+        wait_for(
+            &mut events,
+            |e| matches!(e, Event::UpdatePlayers(players) if players.is_empty()),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn http_polling_asks_little_while_idle() {
+        let mock = MockKodi::start().await;
+        let server = KodiServer {
+            transport: Transport::Http,
+            ..mock.server.as_ref().clone()
+        };
+        let (_connection, mut events) = mock.connect_client_to(server).await;
+        wait_for(&mut events, |e| matches!(e, Event::UpdatePlayers(_))).await;
+        tokio::time::sleep(HTTP_POLL * 3).await;
+
+        // One players list per tick (the volume is also asked once on connecting),
+        // the rest only every HTTP_WATCH
+        let ticks = mock.calls("Player.GetActivePlayers").len();
+        assert!(ticks >= 3);
+        assert!(ticks <= mock.calls("Application.GetProperties").len());
+        assert!(mock.calls("GUI.GetProperties").len() <= 2);
+        assert!(mock.calls("XBMC.GetInfoBooleans").len() <= 2);
+    }
+
+    #[tokio::test]
+    async fn http_transport_sends_credentials() {
+        let mock = MockKodi::start().await;
+        mock.require_auth("kodi", "secret");
+        let server = KodiServer {
+            transport: Transport::Http,
+            username: "kodi".to_string(),
+            password: "secret".to_string(),
+            ..mock.server.as_ref().clone()
+        };
+        let (_connection, mut events) = mock.connect_client_to(server.clone()).await;
+        wait_for(&mut events, |e| matches!(e, Event::UpdateKodiAppStatus(_))).await;
+
+        // Nor do they end up in the logs
+        let client = open_http(&server).await.unwrap();
+        assert!(!format!("{:?}", client).contains("secret"));
+    }
+
+    #[tokio::test]
+    async fn library_notifications_become_events() {
+        let mock = MockKodi::start().await;
+        let (_connection, mut events) = mock.connect_client().await;
+
+        mock.notify(
+            "VideoLibrary.OnUpdate",
+            json!({"item": {"id": 5, "type": "movie"}, "playcount": 1}),
+        );
+        let event = wait_for(&mut events, |e| matches!(e, Event::LibraryUpdated(_))).await;
+        assert!(matches!(
+            event,
+            Event::LibraryUpdated(LibraryItem::Movie(5))
+        ));
+
+        mock.notify("VideoLibrary.OnRemove", json!({"id": 3, "type": "episode"}));
+        let event = wait_for(&mut events, |e| matches!(e, Event::LibraryRemoved(_))).await;
+        assert!(matches!(
+            event,
+            Event::LibraryRemoved(LibraryItem::Episode(3))
+        ));
+
+        mock.notify("VideoLibrary.OnScanFinished", Value::Null);
+        wait_for(&mut events, |e| matches!(e, Event::LibraryScanned)).await;
+    }
+
+    #[tokio::test]
+    async fn movies_deserialize_from_kodi_responses() {
+        let mock = MockKodi::start().await;
+        mock.handle(
+            "VideoLibrary.GetMovieDetails",
+            |params| match params["movieid"].as_u64() {
+                Some(2) => Ok(json!({"moviedetails": movie(2, "Two", 3)})),
+                _ => Err("Invalid params.".to_string()),
+            },
+        );
+        let (mut connection, _events) = mock.connect_client().await;
+
+        // The missing movie is skipped rather than failing the batch
+        let (tx, mut rx) = channel(1);
+        connection.send(KodiCommand::VideoLibraryGetMoviesByIDs {
+            sender: tx,
+            ids: vec![2, 9],
+        });
+        let movies = rx.next().await.expect("Should get a reply");
+        assert_eq!(movies.len(), 1);
+        let movie = &movies[0];
+        assert_eq!(movie.movieid, 2);
+        assert_eq!(movie.title, "Two");
+        assert_eq!(movie.genre, vec!["Drama", "Comedy"]);
+        assert_eq!(movie.playcount, 3);
+        assert_eq!(movie.userrating, 8);
+        assert_eq!(movie.art.poster.as_deref(), Some("image://Two.jpg/"));
+        assert_eq!(movie.resume.progress(), Some(0.1));
+    }
+
+    #[tokio::test]
+    async fn fingerprints_handle_empty_libraries() {
+        let mock = MockKodi::start().await;
+        mock.respond(
+            "VideoLibrary.GetMovies",
+            json!({"limits": {"start": 0, "end": 0, "total": 0}}),
+        );
+        let (mut connection, _events) = mock.connect_client().await;
+
+        let (tx, mut rx) = channel(1);
+        connection.send(KodiCommand::VideoLibraryGetMovieFingerprints { sender: tx });
+        assert_eq!(rx.next().await, Some(Vec::new()));
+        assert_eq!(
+            mock.calls("VideoLibrary.GetMovies")[0]["properties"],
+            json!(FINGERPRINT_PROPS)
+        );
+    }
+
+    #[tokio::test]
+    async fn rejected_commands_are_reported() {
+        let mock = MockKodi::start().await;
+        mock.handle("Player.Open", |_| Err("Invalid params.".to_string()));
+        let (mut connection, mut events) = mock.connect_client().await;
+
+        connection.send(KodiCommand::PlayerOpen("/missing.mkv".to_string()));
+        let event = wait_for(&mut events, |e| matches!(e, Event::CommandFailed(_))).await;
+        let Event::CommandFailed(err) = event else {
+            unreachable!()
+        };
+        assert!(!err.needs_reconnect());
+        assert_eq!(
+            err,
+            KodiError::Rpc {
+                method: "Player.Open".to_string(),
+                code: -32602,
+                message: "Invalid params.".to_string(),
+                data: None,
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Player.Open failed: Invalid params. (-32602)"
+        );
+
+        // Kodi saying no doesn't cost the connection
+        mock.notify(
+            "Player.OnPause",
+            json!({"item": {}, "player": {"playerid": 1}}),
+        );
+        wait_for(&mut events, |e| matches!(e, Event::PlayerChanged(..))).await;
+        assert_eq!(mock.connections(), 1);
+    }
+
+    #[tokio::test]
+    async fn bad_responses_name_the_field() {
+        let mock = MockKodi::start().await;
+        mock.respond(
+            "VideoLibrary.GetMovieDetails",
+            json!({"moviedetails": "not a movie"}),
+        );
+        let (mut connection, mut events) = mock.connect_client().await;
+
+        let (tx, _rx) = channel(1);
+        connection.send(KodiCommand::VideoLibraryGetMovieDetails {
+            sender: tx,
+            movieid: 1,
+        });
+        let event = wait_for(&mut events, |e| matches!(e, Event::CommandFailed(_))).await;
+        let Event::CommandFailed(KodiError::Deserialize { method, field, .. }) = event else {
+            panic!("Expected a deserialize error, got {:?}", event);
+        };
+        assert_eq!(method, "VideoLibrary.GetMovieDetails");
+        assert_eq!(field, "moviedetails");
+    }
+
+    #[tokio::test]
+    async fn bad_list_items_are_not_an_empty_list() {
+        let mock = MockKodi::start().await;
+        // No timers leaves the list out
+        mock.respond(
+            "PVR.GetTimers",
+            json!({"limits": {"start": 0, "end": 0, "total": 0}}),
+        );
+        let (mut connection, mut events) = mock.connect_client().await;
+
+        let (tx, mut rx) = channel(1);
+        connection.send(KodiCommand::PVRGetTimers { sender: tx });
+        assert_eq!(rx.next().await.map(|timers| timers.len()), Some(0));
+
+        mock.respond("PVR.GetTimers", json!({"timers": [{"timerid": "one"}]}));
+        let (tx, mut rx) = channel(1);
+        connection.send(KodiCommand::PVRGetTimers { sender: tx });
+        assert!(rx.next().await.is_none());
+        let event = wait_for(&mut events, |e| matches!(e, Event::CommandFailed(_))).await;
+        let Event::CommandFailed(KodiError::Deserialize { method, field, .. }) = event else {
+            panic!("Expected a deserialize error, got {:?}", event);
+        };
+        assert_eq!(method, "PVR.GetTimers");
+        assert_eq!(field, "timers");
+    }
+
+    #[tokio::test]
+    async fn one_bad_playlist_item_fails_the_playlist() {
+        let mock = MockKodi::start().await;
+        mock.respond(
+            "Playlist.GetItems",
+            json!({"items": [
+                {"label": "a.mkv", "title": "A", "file": "/a.mkv"},
+                {"label": "b.mkv"},
+            ]}),
+        );
+        let (mut connection, _events) = mock.connect_client().await;
+
+        let (tx, mut rx) = channel(1);
+        connection.send(KodiCommand::PlaylistGetItems {
+            sender: tx,
+            playlist_id: VIDEO_PLAYLIST,
+        });
+        assert!(rx.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn requests_report_how_they_went() {
+        let mock = MockKodi::start().await;
+        mock.respond("Player.Open", json!("OK"));
+        let (mut connection, _events) = mock.connect_client().await;
+
+        let opened = connection.request(KodiCommand::PlayerOpen("/a.mkv".to_string()));
+        assert_eq!(opened.await, Ok(()));
+
+        mock.handle("Player.Open", |_| Err("Invalid params.".to_string()));
+        let opened = connection.request(KodiCommand::PlayerOpen("/b.mkv".to_string()));
+        assert!(matches!(
+            opened.await,
+            Err(KodiError::Rpc { code: -32602, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn offline_requests_are_not_sent() {
+        let server = KodiServer {
+            ip: "127.0.0.1".to_string(),
+            websocket_port: closed_port().await,
+            transport: Transport::WebSocket,
+            ..Default::default()
+        };
+        let (tx, mut events) = channel(100);
+        tokio::spawn(connect(Arc::new(server), tx, Backoff::default()));
+        let Event::Disconnected(mut offline) =
+            wait_for(&mut events, |e| matches!(e, Event::Disconnected(_))).await
+        else {
+            unreachable!()
+        };
+
+        let opened = offline.request(KodiCommand::PlayerOpen("/a.mkv".to_string()));
+        assert_eq!(opened.await, Err(KodiError::NotConnected));
+    }
+
+    #[tokio::test]
+    async fn stalled_requests_time_out() {
+        let mock = MockKodi::start().await;
+        mock.stall("Files.GetDirectory");
+        let client = open_client(&mock.server).await.unwrap();
+
+        let result: Result<Value, _> = client
+            .request_within(
+                "Files.GetDirectory",
+                rpc_params!(),
+                Duration::from_millis(200),
+            )
+            .await;
+        let err = result.unwrap_err();
+        assert_eq!(
+            err,
+            KodiError::Timeout {
+                method: "Files.GetDirectory".to_string()
+            }
+        );
+        assert!(!err.needs_reconnect());
+        assert_eq!(timeout_for("Files.GetDirectory"), REQUEST_TIMEOUT);
+        assert_eq!(timeout_for("VideoLibrary.GetMovies"), LIBRARY_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn cancelled_directory_loads_free_the_client() {
+        let mock = MockKodi::start().await;
+        mock.stall("Files.GetDirectory");
+        mock.respond("Playlist.GetItems", json!({"items": []}));
+        let (mut connection, _events) = mock.connect_client().await;
+
+        let (tx, rx) = channel(1);
+        connection.send(KodiCommand::GetDirectory {
+            sender: tx,
+            path: "smb://nas/movies/".to_string(),
+            media_type: MediaType::Video,
+        });
+        while mock.calls("Files.GetDirectory").is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        // Navigated away
+        drop(rx);
+
+        let (tx, mut rx) = channel(1);
+        connection.send(KodiCommand::PlaylistGetItems {
+            sender: tx,
+            playlist_id: VIDEO_PLAYLIST,
+        });
+        let items = tokio::time::timeout(Duration::from_secs(2), rx.next()).await;
+        assert!(items.unwrap().is_some_and(|items| items.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn failed_commands_drop_the_reply_sender() {
+        let mock = MockKodi::start().await;
+        let (mut connection, _events) = mock.connect_client().await;
+
+        let (tx, mut rx) = channel(1);
+        connection.send(KodiCommand::PVRAddTimer {
+            sender: tx,
+            broadcastid: 1,
+        });
+        assert!(rx.next().await.is_none());
+    }
+}
@@ -23,9 +23,12 @@ use iced::futures::channel::mpsc::{Receiver, Sender, channel};
 use iced::futures::channel::oneshot;
 use iced::futures::{SinkExt, Stream, StreamExt};
 use iced::stream;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
+use std::time::Instant;
 use tokio::select;
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
 use tracing::{debug, error};
 
 // input messages from UI
@@ -33,13 +36,65 @@ use tracing::{debug, error};
 pub enum Get {
     KodiServers,
     AddOrEditServer(KodiServer),
+    SelectServer(u8),
+    DeleteServer(u8),
+    ContinueWatching,
     Movies(bool),
+    MovieDetails(u32),
     TVShows(bool),
+    TVShowDetails(u32),
     TVSeasons(u32),
     TVEpisodes(u32, i16, bool),
-    Directory { path: String, media_type: MediaType },
+    EpisodeDetails(u32),
+    Artists(bool),
+    Albums(u32, bool),
+    Songs(u32, bool),
+    // PVR is online only, there's nothing worth caching
+    LiveTV,
+    PVRChannels {
+        channelgroupid: u32,
+        label: String,
+    },
+    PVRGuide {
+        channelid: u32,
+        label: String,
+    },
+    PVRRecordings,
+    PVRTimers,
+    PVRAddTimer {
+        broadcastid: u32,
+        channelid: u32,
+        channel: String,
+    },
+    PVRDeleteTimer(u32),
+    // Watched/rating changes, see edit_item
+    EditItem(LibraryEdit),
+    ReplayEdits,
+    // Wake-on-LAN the current server
+    Wake,
+    // Reconnect delay range, jitter stays the default
+    SetBackoff {
+        min: Duration,
+        max: Duration,
+    },
+    Playlist(u8),
+    Directory {
+        path: String,
+        media_type: MediaType,
+    },
     Sources,
+    // The UI stopped waiting on a Directory
+    CancelLoad,
+    // Which player the transport controls act on
+    SelectPlayer(u8),
+    Minimized(bool),
+    NowPlayingOpen(bool),
     SyncDone(Box<Get>),
+    // A library item changed, reloads the shown view if it's part of it
+    LibraryRefresh {
+        item: LibraryItem,
+        tvshowid: Option<u32>,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -63,11 +118,93 @@ pub enum DataEvent {
         title: String,
         data: Vec<Box<dyn IntoListData + Send + 'static>>,
     },
-    // technically Servers is redundant
+    ItemDetails {
+        request: Get,
+        details: ItemDetails,
+    },
+    // technically `active` is redundant
     // can just udpate KodiStatus instead
-    Servers(Vec<KodiServer>),
-    KodiStatus(crate::KodiStatus),
+    Servers {
+        servers: Vec<KodiServer>,
+        active: Option<Arc<KodiServer>>,
+    },
+    // Sent after switching servers, anything the UI got from the old library is stale.
+    ServerChanged(Option<Arc<KodiServer>>),
+    KodiStatus(Box<crate::KodiStatus>),
     InputRequested(String),
+    Playlist {
+        playlist_id: u8,
+        items: Vec<String>,
+    },
+    PlaylistChanged(u8),
+    // Kodi disagreed with an edit the UI already applied
+    ItemEdited(LibraryEdit),
+    Backoff(Backoff),
+    // A view request that got nothing back, kodi's error comes separately
+    LoadFailed(Get),
+    // Shown to the user, kodi rejected something they asked for
+    CommandFailed(client::KodiError),
+    // Shown to the user, something went wrong on this side
+    Error(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum ItemDetails {
+    Movie(Box<MovieDetails>),
+    TVShow(Box<TVShowDetails>),
+    Episode(Box<EpisodeDetails>),
+}
+
+impl ItemDetails {
+    pub fn title(&self) -> &str {
+        match self {
+            ItemDetails::Movie(movie) => &movie.item.title,
+            ItemDetails::TVShow(show) => &show.item.title,
+            ItemDetails::Episode(episode) => &episode.item.title,
+        }
+    }
+
+    // Episodes only have a (landscape) thumb, see poster_size
+    pub fn poster(&self) -> Option<&str> {
+        match self {
+            ItemDetails::Movie(movie) => movie.item.art.poster.as_deref(),
+            ItemDetails::TVShow(show) => show.item.art.poster.as_deref(),
+            ItemDetails::Episode(episode) => episode.item.art.thumb.as_deref(),
+        }
+    }
+
+    pub fn poster_size(&self) -> (u32, u32) {
+        match self {
+            ItemDetails::Episode(_) => (DETAILS_THUMB_W, DETAILS_THUMB_H),
+            _ => (DETAILS_POSTER_W, DETAILS_POSTER_H),
+        }
+    }
+
+    pub fn fanart(&self) -> Option<&str> {
+        match self {
+            ItemDetails::Movie(movie) => movie.item.art.fanart.as_deref(),
+            ItemDetails::TVShow(show) => show.item.art.fanart.as_deref(),
+            ItemDetails::Episode(_) => None,
+        }
+    }
+}
+
+// Where sync_items_by_ids_bg gets one kind of library item's ids (or
+// fingerprints) and items from, and how they're stored
+struct SyncSource<K, B, D, I, G> {
+    get_kodi_keys: K,
+    get_batch: B,
+    db_delete_ids: D,
+    db_insert: I,
+    get_db_keys: G,
+}
+
+// How kodi took a watched/rating edit
+enum EditOutcome {
+    Accepted,
+    Rejected,
+    // Not connected, timed out or the connection dropped, worth trying again
+    Unreachable,
 }
 
 pub struct Data {
@@ -77,13 +214,26 @@ pub struct Data {
     // biggest hurdle to that is actually the slider grab thing
     // I'd need to decouple that part of the UI during grab.
     kodi_status: crate::KodiStatus,
+    settings_db: db::SqlConnection,
+    // library db of the active server, see `switch_server`
     db: db::SqlConnection,
+    library_id: u8,
     kodi_connected: bool,
     client: client::Connection,
     clientrx: Receiver<client::Event>,
-    syncing: HashSet<Get>,
+    backoff: Backoff,
+    syncing: HashMap<Get, JoinHandle<()>>,
+    // Last view the UI asked for, so library notifications know what to refresh
+    shown: Option<Get>,
+    // Directory listing in progress, dropped when the UI moves on
+    loading: Option<JoinHandle<()>>,
 }
 
+const ACTIVE_SERVER: &str = "active_server";
+// Seconds
+const RECONNECT_MIN: &str = "reconnect_min";
+const RECONNECT_MAX: &str = "reconnect_max";
+
 pub fn connect() -> impl Stream<Item = DataEvent> {
     stream::channel(100, |output| async move {
         let mut data = Data::new().await;
@@ -91,6 +241,89 @@ pub fn connect() -> impl Stream<Item = DataEvent> {
     })
 }
 
+// What the sync helper diffs on. Plain ids only catch added/removed items,
+// a Fingerprint also catches ones that changed in kodi.
+trait SyncKey: Send + 'static {
+    fn id(&self) -> u32;
+    fn changed(&self, _cached: &Self) -> bool {
+        false
+    }
+}
+
+impl SyncKey for u32 {
+    fn id(&self) -> u32 {
+        *self
+    }
+}
+
+impl SyncKey for Fingerprint {
+    fn id(&self) -> u32 {
+        self.id
+    }
+    fn changed(&self, cached: &Self) -> bool {
+        self != cached
+    }
+}
+
+impl Get {
+    // Requests that replace what's on screen, as opposed to background refreshes
+    fn is_view(&self) -> bool {
+        match self {
+            Get::Movies(sync)
+            | Get::TVShows(sync)
+            | Get::TVEpisodes(_, _, sync)
+            | Get::Artists(sync)
+            | Get::Albums(_, sync)
+            | Get::Songs(_, sync) => *sync,
+            Get::ContinueWatching
+            | Get::MovieDetails(_)
+            | Get::TVShowDetails(_)
+            | Get::TVSeasons(_)
+            | Get::EpisodeDetails(_)
+            | Get::LiveTV
+            | Get::PVRChannels { .. }
+            | Get::PVRGuide { .. }
+            | Get::PVRRecordings
+            | Get::PVRTimers
+            | Get::Directory { .. }
+            | Get::Sources => true,
+            _ => false,
+        }
+    }
+}
+
+// The (non syncing) request to reload the shown view if `item` is part of it.
+// An episode's show is only known once it's been fetched, without it any
+// episode list is assumed to be affected.
+fn library_refresh(shown: &Option<Get>, item: LibraryItem, tvshowid: Option<u32>) -> Option<Get> {
+    let refresh = match (shown.as_ref()?, item) {
+        (Get::ContinueWatching, LibraryItem::Movie(_) | LibraryItem::Episode(_)) => {
+            Get::ContinueWatching
+        }
+        (Get::Movies(_), LibraryItem::Movie(_)) => Get::Movies(false),
+        (Get::MovieDetails(id), LibraryItem::Movie(movieid)) if *id == movieid => {
+            Get::MovieDetails(movieid)
+        }
+        (Get::TVShows(_), LibraryItem::TVShow(_)) => Get::TVShows(false),
+        (Get::TVShowDetails(id), LibraryItem::TVShow(showid))
+        | (Get::TVSeasons(id), LibraryItem::TVShow(showid))
+            if *id == showid =>
+        {
+            shown.clone()?
+        }
+        (Get::TVEpisodes(id, season, _), LibraryItem::Episode(_))
+            if tvshowid.is_none_or(|showid| showid == *id) =>
+        {
+            Get::TVEpisodes(*id, *season, false)
+        }
+        (Get::EpisodeDetails(id), LibraryItem::Episode(episodeid)) if *id == episodeid => {
+            Get::EpisodeDetails(episodeid)
+        }
+        _ => return None,
+    };
+    Some(refresh)
+}
+
 impl Data {
     pub async fn new() -> Self {
         match Self::initialize_data().await {
@@ -107,22 +340,32 @@ impl Data {
         tokio::spawn(async move {
             db::connect(dbtx).await;
         });
-        let mut conn = dbrx.await?;
+        let mut settings_db = dbrx.await?;
+
+        let servers = Self::get_servers(&mut settings_db).await?;
 
         let (tx, rx) = oneshot::channel();
-        let _ = conn.send(db::SqlCommand::GetServers { sender: tx });
+        settings_db.send(db::SqlCommand::GetSetting {
+            sender: tx,
+            key: ACTIVE_SERVER,
+        });
+        let active_id = rx.await?.and_then(|id| id.parse::<u8>().ok());
+        let backoff = Self::get_backoff(&mut settings_db).await?;
 
-        let kodiserver = rx.await?;
-        let kodiserver = if kodiserver.is_empty() {
-            Arc::new(KodiServer::default())
-        } else {
-            Arc::new(kodiserver[0].clone())
-        };
-        let kodiserver2 = Arc::clone(&kodiserver);
+        let server = servers
+            .iter()
+            .find(|s| Some(s.id) == active_id)
+            .or(servers.first())
+            .cloned()
+            .map(Arc::new);
+
+        let library_id = server.as_ref().map_or(0, |s| s.db_id);
+        let conn = Self::open_library(library_id).await?;
 
+        let kodiserver = server.clone().unwrap_or_default();
         let (koditx, mut kodirx) = channel(100);
         tokio::spawn(async move {
-            client::connect(kodiserver2, koditx).await;
+            client::connect(kodiserver, koditx, backoff).await;
         });
 
         let svr = kodirx.select_next_some().await;
@@ -133,20 +376,120 @@ impl Data {
         };
 
         let kodi_status = crate::KodiStatus {
-            server: Some(kodiserver),
+            server,
             ..Default::default()
         };
 
         Ok(Data {
             kodi_status,
+            settings_db,
             db: conn,
+            library_id,
             kodi_connected,
             client,
             clientrx: kodirx,
-            syncing: HashSet::new(),
+            backoff,
+            syncing: HashMap::new(),
+            shown: None,
+            loading: None,
         })
     }
 
+    // Saved overrides of the default reconnect delays
+    async fn get_backoff(
+        settings_db: &mut db::SqlConnection,
+    ) -> Result<Backoff, Box<dyn Error + Send + Sync>> {
+        let mut backoff = Backoff::default();
+        for (key, value) in [
+            (RECONNECT_MIN, &mut backoff.min),
+            (RECONNECT_MAX, &mut backoff.max),
+        ] {
+            let (tx, rx) = oneshot::channel();
+            settings_db.send(db::SqlCommand::GetSetting { sender: tx, key });
+            if let Some(secs) = rx.await?.and_then(|secs| secs.parse::<u64>().ok()) {
+                *value = Duration::from_secs(secs);
+            }
+        }
+        Ok(backoff)
+    }
+
+    async fn open_library(db_id: u8) -> Result<db::SqlConnection, Box<dyn Error + Send + Sync>> {
+        let (dbtx, dbrx) = oneshot::channel();
+        tokio::spawn(async move {
+            db::connect_library(db_id, dbtx).await;
+        });
+        Ok(dbrx.await?)
+    }
+
+    async fn get_servers(
+        settings_db: &mut db::SqlConnection,
+    ) -> Result<Vec<KodiServer>, Box<dyn Error + Send + Sync>> {
+        let (tx, rx) = oneshot::channel();
+        settings_db.send(db::SqlCommand::GetServers { sender: tx });
+        Ok(rx.await?)
+    }
+
+    async fn send_servers(
+        &mut self,
+        output: &mut Sender<DataEvent>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let servers = Self::get_servers(&mut self.settings_db).await?;
+        for server in servers.iter().filter(|s| s.locked_password.is_some()) {
+            let _ = output
+                .send(DataEvent::Error(format!(
+                    "The saved password for {} can't be read, enter it again",
+                    server.name
+                )))
+                .await;
+        }
+        let _ = output
+            .send(DataEvent::Servers {
+                servers,
+                active: self.kodi_status.server.clone(),
+            })
+            .await;
+        Ok(())
+    }
+
+    // Swaps the kodi client and the library db together
+    // so the offline lists always belong to the server we're talking to.
+    async fn switch_server(
+        &mut self,
+        output: &mut Sender<DataEvent>,
+        server: Option<KodiServer>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        // Anything still syncing would mix the old server's items in to the new library
+        for (_, task) in self.syncing.drain() {
+            task.abort();
+        }
+        self.cancel_load();
+
+        let db_id = server.as_ref().map_or(0, |s| s.db_id);
+        if db_id != self.library_id {
+            self.db = Self::open_library(db_id).await?;
+            self.library_id = db_id;
+        }
+
+        if let Some(srv) = &server {
+            self.settings_db.send(db::SqlCommand::SetSetting {
+                key: ACTIVE_SERVER,
+                value: srv.id.to_string(),
+            });
+        }
+
+        let server = server.map(Arc::new);
+        self.client.send(KodiCommand::ChangeServer(
+            server.clone().unwrap_or_default(),
+        ));
+        self.kodi_status = crate::KodiStatus {
+            server: server.clone(),
+            ..Default::default()
+        };
+
+        let _ = output.send(DataEvent::ServerChanged(server)).await;
+        Ok(())
+    }
+
     async fn handle_connection(&mut self, mut output: Sender<DataEvent>) -> ! {
         let (sender, mut reciever) = channel(100);
         let item = if self.kodi_connected {
@@ -155,6 +498,10 @@ impl Data {
             DataEvent::Offline(Connection(sender.clone()))
         };
         let _ = output.send(item).await;
+        let _ = output.send(DataEvent::Backoff(self.backoff)).await;
+        if self.kodi_connected {
+            let _ = sender.clone().send(Get::ReplayEdits).await;
+        }
         loop {
             select! {
                 kodi_msg = self.clientrx.select_next_some() => {
@@ -166,11 +513,13 @@ impl Data {
                                 DataEvent::Online(Connection(sender.clone()),
                                 kodi.clone())
                             ).await;
+                            let _ = sender.clone().send(Get::ReplayEdits).await;
                         }
                         Event::Disconnected(kodi) => {
                             self.client = kodi.clone();
                             self.kodi_connected = false;
-                            self.kodi_status.active_player_id = None;
+                            self.kodi_status.players.clear();
+                            self.kodi_status.select_player(None);
                             let _ = output.send(
                                 DataEvent::Offline(Connection(sender.clone()))
                             ).await;
@@ -178,7 +527,7 @@ impl Data {
                         _ => {}
                     }
 
-                    let res = self.handle_kodi(&mut output, kodi_msg).await;
+                    let res = self.handle_kodi(&mut output, kodi_msg, sender.clone()).await;
                     if res.is_err() {
                         error!("Kodi handler error: {:?}", res.err());
                     }
@@ -199,39 +548,119 @@ impl Data {
         &mut self,
         output: &mut Sender<DataEvent>,
         msg: client::Event,
+        sender: Sender<Get>,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
         match msg {
-            Event::UpdatePlayerProps(player_props) => match player_props {
-                None => {
-                    self.kodi_status.active_player_id = None;
-                    self.kodi_status.playing_title = "".into();
-                }
-                Some(props) => {
-                    if self.kodi_status.active_player_id.is_none() {
-                        self.client.send(KodiCommand::PlayerGetPlayingItem(
-                            props.player_id.expect("player_id should exist"),
-                        ));
-                    }
-                    self.kodi_status.active_player_id = props.player_id;
-                    self.kodi_status.player_props = props;
+            Event::UpdatePlayers(all_props) => {
+                let mut players = BTreeMap::new();
+                for props in all_props {
+                    let player_id = props.player_id.expect("player_id should exist");
+                    // Items only come with Player.GetItem, new players need one
+                    let (item, title) = match self.kodi_status.players.remove(&player_id) {
+                        Some(player) => (player.item, player.title),
+                        None => {
+                            self.client
+                                .send(KodiCommand::PlayerGetPlayingItem(player_id));
+                            Default::default()
+                        }
+                    };
+                    let player = crate::PlayerStatus {
+                        props,
+                        item,
+                        title,
+                        synced_at: Some(Instant::now()),
+                    };
+                    players.insert(player_id, player);
                 }
-            },
+                self.kodi_status.players = players;
+                self.kodi_status.select_player(None);
+            }
             Event::UpdateKodiAppStatus(status) => {
                 self.kodi_status.muted = status.muted;
+                self.kodi_status.volume = status.volume;
+            }
+            Event::ConnectionStatus(status) => {
+                self.kodi_status.connection = status;
+            }
+            Event::UpdatePlayingItem(player_id, item) => {
+                // Player.OnPlay comes before the player can be polled, its
+                // props follow with Player.OnAVStart
+                let player = self.kodi_status.players.entry(player_id).or_default();
+                player.title = item.make_title();
+                player.item = *item;
+                self.kodi_status.select_player(None);
             }
-            Event::UpdatePlayingItem(item) => {
-                self.kodi_status.playing_title = item.make_title();
+            Event::PlayerChanged(player_id, change) => {
+                // Time so far went at the old speed
+                let now = Instant::now();
+                self.kodi_status.advance(now);
+                if let Some(player) = self.kodi_status.players.get_mut(&player_id) {
+                    let props = &mut player.props;
+                    match change {
+                        client::PlayerChange::Speed(speed) => props.speed = speed,
+                        client::PlayerChange::Seek { speed, time } => {
+                            props.speed = speed;
+                            props.time = time;
+                        }
+                        client::PlayerChange::Shuffled(shuffled) => props.shuffled = shuffled,
+                        client::PlayerChange::Repeat(repeat) => props.repeat = repeat,
+                        client::PlayerChange::PartyMode(partymode) => props.partymode = partymode,
+                    }
+                    player.synced_at = Some(now);
+                }
+                self.kodi_status.select_player(None);
             }
             Event::InputRequested(input) => {
                 let _ = output.send(DataEvent::InputRequested(input)).await;
             }
+            Event::PlaylistChanged(playlist_id) => {
+                let _ = output.send(DataEvent::PlaylistChanged(playlist_id)).await;
+            }
+            Event::CommandFailed(err) => {
+                let _ = output.send(DataEvent::CommandFailed(err)).await;
+            }
+            // Fetching waits on the client, which may itself be waiting to
+            // hand us the next notification, so it's done in the background.
+            Event::LibraryUpdated(item) => {
+                let client = self.client.clone();
+                let db = self.db.clone();
+                tokio::spawn(async move {
+                    Self::update_library_item_bg(client, db, sender, item).await;
+                });
+            }
+            Event::LibraryRemoved(item) => {
+                self.db.send(db::SqlCommand::DeleteLibraryItem(item));
+                let refresh = Get::LibraryRefresh {
+                    item,
+                    tvshowid: None,
+                };
+                let _ = sender.clone().send(refresh).await;
+            }
+            Event::LibraryScanned => {
+                // Same tasks (and keys) as opening the lists with sync on
+                for key in [Get::Movies(true), Get::TVShows(true)] {
+                    if self.syncing.contains_key(&key) {
+                        continue;
+                    }
+                    let client = self.client.clone();
+                    let db = self.db.clone();
+                    let ui_tx = sender.clone();
+                    let task = match key {
+                        Get::Movies(_) => {
+                            tokio::spawn(Self::sync_movies_bg(client, db, ui_tx, key.clone()))
+                        }
+                        _ => tokio::spawn(Self::sync_tvshows_bg(client, db, ui_tx, key.clone())),
+                    };
+                    self.syncing.insert(key, task);
+                }
+            }
 
             _ => {}
         }
         // cloning this thing each time seems bad
         // might cchange kodi_status to rwlock
         let _ = output
-            .send(DataEvent::KodiStatus(self.kodi_status.clone()))
+            .send(DataEvent::KodiStatus(Box::new(self.kodi_status.clone())))
             .await;
         Ok(())
     }
@@ -243,95 +672,590 @@ impl Data {
         output: &mut Sender<DataEvent>,
         msg: Get,
         sender: Sender<Get>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let msg = match msg {
+            // Checked against what's shown now, the view may have changed since the
+            // item did. Reloading isn't a new view, so it can't cancel a load.
+            Get::LibraryRefresh { item, tvshowid } => {
+                let Some(refresh) = library_refresh(&self.shown, item, tvshowid) else {
+                    return Ok(());
+                };
+                refresh
+            }
+            msg => {
+                if msg.is_view() {
+                    self.shown = Some(msg.clone());
+                    self.cancel_load();
+                }
+                msg
+            }
+        };
+
+        // Whatever goes wrong, a view that's loading has to hear back
+        let view = msg.is_view().then(|| msg.clone());
+        let result = self.handle_request(output, msg, sender).await;
+        if let (Err(_), Some(view)) = (&result, view) {
+            let _ = output.send(DataEvent::LoadFailed(view)).await;
+        }
+        result
+    }
+
+    async fn handle_request(
+        &mut self,
+        output: &mut Sender<DataEvent>,
+        msg: Get,
+        sender: Sender<Get>,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
         match msg {
+            // Shares can take a while to wake up, so this doesn't hold up everything else
             Get::Directory { path, media_type } => {
+                let client = self.client.clone();
+                let output = output.clone();
+                self.loading = Some(tokio::spawn(Self::load_directory_bg(
+                    client, output, path, media_type,
+                )));
+                Ok(())
+            }
+
+            Get::CancelLoad => {
+                self.cancel_load();
+                Ok(())
+            }
+
+            Get::Minimized(minimized) => {
+                self.client.send(KodiCommand::SetMinimized(minimized));
+                Ok(())
+            }
+
+            Get::NowPlayingOpen(open) => {
+                self.client.send(KodiCommand::SetNowPlayingOpen(open));
+                Ok(())
+            }
+
+            Get::SelectPlayer(player_id) => {
+                self.kodi_status.select_player(Some(player_id));
+                let _ = output
+                    .send(DataEvent::KodiStatus(Box::new(self.kodi_status.clone())))
+                    .await;
+                Ok(())
+            }
+
+            Get::Sources => {
+                let mut data = Vec::new();
+                for media_type in [MediaType::Video, MediaType::Music] {
+                    let (tx, mut rx) = channel(1);
+                    self.client.send(KodiCommand::GetSources {
+                        sender: tx,
+                        media_type,
+                    });
+                    match rx.next().await {
+                        Some(sources) => data.extend(sources),
+                        None => {
+                            let _ = output.send(DataEvent::LoadFailed(Get::Sources)).await;
+                            return Ok(());
+                        }
+                    }
+                }
+
+                let _ = output
+                    .send(DataEvent::ListData {
+                        request: Get::Sources,
+                        title: "Sources".into(),
+                        data,
+                    })
+                    .await;
+                Ok(())
+            }
+
+            Get::LiveTV => {
                 let (tx, mut rx) = channel(1);
-                self.client.send(KodiCommand::GetDirectory {
+                self.client
+                    .send(KodiCommand::PVRGetChannelGroups { sender: tx });
+                let Some(groups) = rx.next().await else {
+                    let _ = output.send(DataEvent::LoadFailed(Get::LiveTV)).await;
+                    return Ok(());
+                };
+
+                let mut data: Vec<Box<dyn IntoListData + Send>> = vec![
+                    Box::new(MenuLink {
+                        label: "- Recordings".into(),
+                        on_click: crate::Message::GetData(Get::PVRRecordings),
+                    }),
+                    Box::new(MenuLink {
+                        label: "- Timers".into(),
+                        on_click: crate::Message::GetData(Get::PVRTimers),
+                    }),
+                ];
+                data.extend(groups.into_iter().map(|v| Box::new(v) as _));
+
+                let _ = output
+                    .send(DataEvent::ListData {
+                        request: Get::LiveTV,
+                        title: "Live TV".into(),
+                        data,
+                    })
+                    .await;
+                Ok(())
+            }
+
+            Get::PVRChannels {
+                channelgroupid,
+                label,
+            } => {
+                let (tx, mut rx) = channel(1);
+                self.client.send(KodiCommand::PVRGetChannels {
                     sender: tx,
-                    path: path.clone(),
-                    media_type: media_type.clone(),
+                    channelgroupid,
                 });
+                let Some(channels) = rx.next().await else {
+                    let _ = output
+                        .send(DataEvent::LoadFailed(Get::PVRChannels {
+                            channelgroupid,
+                            label,
+                        }))
+                        .await;
+                    return Ok(());
+                };
+                let data = channels.into_iter().map(|v| Box::new(v) as _).collect();
 
-                let data = rx.select_next_some().await;
-                let title = path.clone();
                 let _ = output
                     .send(DataEvent::ListData {
-                        request: Get::Directory { path, media_type },
-                        title,
+                        request: Get::PVRChannels {
+                            channelgroupid,
+                            label: label.clone(),
+                        },
+                        title: label,
                         data,
                     })
                     .await;
                 Ok(())
             }
 
-            Get::Sources => {
+            Get::PVRGuide { channelid, label } => {
                 let (tx, mut rx) = channel(1);
-                self.client.send(KodiCommand::GetSources {
+                self.client.send(KodiCommand::PVRGetBroadcasts {
                     sender: tx,
-                    media_type: MediaType::Video,
+                    channelid,
                 });
+                let Some(broadcasts) = rx.next().await else {
+                    let _ = output
+                        .send(DataEvent::LoadFailed(Get::PVRGuide { channelid, label }))
+                        .await;
+                    return Ok(());
+                };
+
+                // The epg goes back a few days, only keep what's still to come
+                let now = chrono::Local::now();
+                let upcoming = broadcasts
+                    .into_iter()
+                    .filter(|b| pvr_time(&b.endtime).is_some_and(|end| end > now))
+                    .map(|mut b| {
+                        b.channelid = channelid;
+                        b.channel = label.clone();
+                        Box::new(b) as _
+                    });
+
+                let mut data: Vec<Box<dyn IntoListData + Send>> = vec![Box::new(MenuLink {
+                    label: format!("- Watch {}", label),
+                    on_click: crate::Message::KodiReq(KodiCommand::PlayerOpenChannel(channelid)),
+                })];
+                data.extend(upcoming);
 
-                let data = rx.select_next_some().await;
                 let _ = output
                     .send(DataEvent::ListData {
-                        request: Get::Sources,
-                        title: "Sources".into(),
+                        request: Get::PVRGuide {
+                            channelid,
+                            label: label.clone(),
+                        },
+                        title: label,
+                        data,
+                    })
+                    .await;
+                Ok(())
+            }
+
+            Get::PVRRecordings => {
+                let (tx, mut rx) = channel(1);
+                self.client
+                    .send(KodiCommand::PVRGetRecordings { sender: tx });
+                let Some(recordings) = rx.next().await else {
+                    let _ = output.send(DataEvent::LoadFailed(Get::PVRRecordings)).await;
+                    return Ok(());
+                };
+                let data = recordings.into_iter().map(|v| Box::new(v) as _).collect();
+
+                let _ = output
+                    .send(DataEvent::ListData {
+                        request: Get::PVRRecordings,
+                        title: "Recordings".into(),
+                        data,
+                    })
+                    .await;
+                Ok(())
+            }
+
+            Get::PVRTimers => {
+                let (tx, mut rx) = channel(1);
+                self.client.send(KodiCommand::PVRGetTimers { sender: tx });
+                let Some(timers) = rx.next().await else {
+                    let _ = output.send(DataEvent::LoadFailed(Get::PVRTimers)).await;
+                    return Ok(());
+                };
+                let data = timers.into_iter().map(|v| Box::new(v) as _).collect();
+
+                let _ = output
+                    .send(DataEvent::ListData {
+                        request: Get::PVRTimers,
+                        title: "Timers".into(),
                         data,
                     })
                     .await;
                 Ok(())
             }
 
-            Get::KodiServers => {
+            // These two reload the list they were clicked from so it shows the change
+            Get::PVRAddTimer {
+                broadcastid,
+                channelid,
+                channel: label,
+            } => {
+                let (tx, mut rx) = channel(1);
+                self.client.send(KodiCommand::PVRAddTimer {
+                    sender: tx,
+                    broadcastid,
+                });
+                if rx.next().await.is_some() {
+                    let _ = sender
+                        .clone()
+                        .send(Get::PVRGuide { channelid, label })
+                        .await;
+                }
+                Ok(())
+            }
+
+            Get::PVRDeleteTimer(timerid) => {
+                let (tx, mut rx) = channel(1);
+                self.client.send(KodiCommand::PVRDeleteTimer {
+                    sender: tx,
+                    timerid,
+                });
+                if rx.next().await.is_some() {
+                    let _ = sender.clone().send(Get::PVRTimers).await;
+                }
+                Ok(())
+            }
+
+            Get::EditItem(edit) => self.edit_item(output, edit).await,
+
+            Get::ReplayEdits => {
                 let (tx, rx) = oneshot::channel();
-                let _ = self.db.send(db::SqlCommand::GetServers { sender: tx });
-                let svrs = rx.await?;
-                // dbg!(&svrs);
-                let _ = output.send(DataEvent::Servers(svrs)).await;
+                self.db.send(db::SqlCommand::GetPendingEdits { sender: tx });
+                // Stop once kodi can't be reached so later edits can't be undone by
+                // earlier ones. One kodi rejects would be rejected every time, so it's dropped.
+                for (id, edit) in rx.await? {
+                    match self.send_edit(edit).await {
+                        EditOutcome::Accepted => {}
+                        EditOutcome::Rejected => self.undo_edit(output, edit).await,
+                        EditOutcome::Unreachable => break,
+                    }
+                    self.db.send(db::SqlCommand::DeletePendingEdit(id));
+                }
+                Ok(())
+            }
+
+            Get::Wake => {
+                self.client.send(KodiCommand::Wake);
+                Ok(())
+            }
+
+            Get::SetBackoff { min, max } => {
+                self.backoff = Backoff {
+                    min,
+                    max,
+                    ..self.backoff
+                };
+                for (key, value) in [(RECONNECT_MIN, min), (RECONNECT_MAX, max)] {
+                    self.settings_db.send(db::SqlCommand::SetSetting {
+                        key,
+                        value: value.as_secs().to_string(),
+                    });
+                }
+                self.client.send(KodiCommand::SetBackoff(self.backoff));
+                let _ = output.send(DataEvent::Backoff(self.backoff)).await;
                 Ok(())
             }
 
+            Get::Playlist(playlist_id) => {
+                let (tx, mut rx) = channel(1);
+                self.client.send(KodiCommand::PlaylistGetItems {
+                    sender: tx,
+                    playlist_id,
+                });
+                // The client has already reported why
+                let Some(items) = rx.next().await else {
+                    return Ok(());
+                };
+                let items = items.iter().map(PlayingItem::make_title).collect();
+
+                let _ = output
+                    .send(DataEvent::Playlist { playlist_id, items })
+                    .await;
+                Ok(())
+            }
+
+            Get::KodiServers => self.send_servers(output).await,
+
             Get::AddOrEditServer(srv) => {
                 // this command is the only one that's not really "Get"
                 // it's mostly just routing front end to db/kodiclient though
-                let _ = self.db.send(db::SqlCommand::AddOrEditServer(srv.clone()));
-                let _ = self
-                    .client
-                    .send(KodiCommand::ChangeServer(Arc::new(srv.clone())));
-                self.kodi_status.server = Some(Arc::new(srv.clone()));
-                // Might change this to send KodiStatus instead.
-
-                // should send out DataEvent::Connected here too?
-                let _ = output.send(DataEvent::Servers(vec![srv])).await;
+                let (tx, rx) = oneshot::channel();
+                self.settings_db.send(db::SqlCommand::AddOrEditServer {
+                    server: srv.clone(),
+                    sender: tx,
+                });
+                if let Err(err) = rx.await? {
+                    error!("Failed to save server {}: {:?}", srv.name, err);
+                    let _ = output
+                        .send(DataEvent::Error(format!(
+                            "{} wasn't saved: {:#}",
+                            srv.name, err
+                        )))
+                        .await;
+                    return self.send_servers(output).await;
+                }
+
+                // Editing the active server (or adding the first one) reconnects to it
+                let is_active = self
+                    .kodi_status
+                    .server
+                    .as_ref()
+                    .is_none_or(|s| s.id == srv.id);
+                if is_active {
+                    self.switch_server(output, Some(srv)).await?;
+                }
+                self.send_servers(output).await
+            }
+
+            Get::SelectServer(id) => {
+                let servers = Self::get_servers(&mut self.settings_db).await?;
+                if let Some(srv) = servers.into_iter().find(|s| s.id == id) {
+                    self.switch_server(output, Some(srv)).await?;
+                }
+                self.send_servers(output).await
+            }
+
+            Get::DeleteServer(id) => {
+                let mut servers = Self::get_servers(&mut self.settings_db).await?;
+                let Some(pos) = servers.iter().position(|s| s.id == id) else {
+                    return Ok(());
+                };
+                let removed = servers.remove(pos);
+                self.settings_db.send(db::SqlCommand::DeleteServer(id));
+
+                if self.kodi_status.server.as_ref().is_some_and(|s| s.id == id) {
+                    self.switch_server(output, servers.first().cloned()).await?;
+                }
+
+                // Nothing else shares this library so it has to go,
+                // otherwise a future server with the same db_id would inherit it.
+                if !servers.iter().any(|s| s.db_id == removed.db_id) {
+                    if self.library_id == removed.db_id {
+                        self.db.send(db::SqlCommand::ClearLibrary);
+                    } else if let Err(err) = db::remove_library(removed.db_id).await {
+                        error!("Failed to remove library {}: {:?}", removed.db_id, err);
+                    }
+                }
+                self.send_servers(output).await
+            }
+
+            Get::SyncDone(request) => {
+                self.syncing.remove(&request);
+                Ok(())
+            }
+
+            // Turned into the view's own request above
+            Get::LibraryRefresh { .. } => Ok(()),
+
+            Get::Movies(sync) => {
+                if sync && self.kodi_connected && !self.syncing.contains_key(&msg) {
+                    let client = self.client.clone();
+                    let db = self.db.clone();
+                    let ui_tx = sender.clone();
+                    let original_msg = msg.clone();
+                    let task = tokio::spawn(async move {
+                        Self::sync_movies_bg(client, db, ui_tx, original_msg).await;
+                    });
+                    self.syncing.insert(msg.clone(), task);
+                }
+
+                let (tx, rx) = oneshot::channel();
+                let _ = self.db.send(db::SqlCommand::GetMovieList { sender: tx });
+                let data = rx.await?;
+
+                let _ = output
+                    .send(DataEvent::ListData {
+                        request: Get::Movies(sync),
+                        title: "Movies".into(),
+                        data,
+                    })
+                    .await;
+
+                Ok(())
+            }
+
+            Get::ContinueWatching => {
+                if self.kodi_connected {
+                    self.sync_continue_watching().await;
+                }
+
+                let (tx, rx) = oneshot::channel();
+                self.db
+                    .send(db::SqlCommand::GetContinueWatching { sender: tx });
+                let data = rx.await?;
+
+                let _ = output
+                    .send(DataEvent::ListData {
+                        request: Get::ContinueWatching,
+                        title: "Continue watching".into(),
+                        data,
+                    })
+                    .await;
+                Ok(())
+            }
+
+            Get::MovieDetails(movieid) => {
+                let mut details = None;
+                if self.kodi_connected {
+                    let (tx, mut rx) = channel(1);
+                    self.client.send(KodiCommand::VideoLibraryGetMovieDetails {
+                        sender: tx,
+                        movieid,
+                    });
+                    // None here means the request failed, the db copy will have to do
+                    if let Some(movie) = rx.next().await {
+                        self.db
+                            .send(db::SqlCommand::InsertMovies(vec![movie.item.clone()]));
+                        self.db
+                            .send(db::SqlCommand::InsertMovieDetails(Box::new(movie.clone())));
+                        details = Some(movie);
+                    }
+                }
+
+                let details = match details {
+                    Some(details) => details,
+                    None => {
+                        let (tx, rx) = oneshot::channel();
+                        self.db.send(db::SqlCommand::GetMovieDetails {
+                            sender: tx,
+                            movieid,
+                        });
+                        rx.await?
+                    }
+                };
+
+                let _ = output
+                    .send(DataEvent::ItemDetails {
+                        request: Get::MovieDetails(movieid),
+                        details: ItemDetails::Movie(Box::new(details)),
+                    })
+                    .await;
+                Ok(())
+            }
+
+            Get::Artists(sync) => {
+                if sync && self.kodi_connected && !self.syncing.contains_key(&msg) {
+                    let client = self.client.clone();
+                    let db = self.db.clone();
+                    let ui_tx = sender.clone();
+                    let original_msg = msg.clone();
+                    let task = tokio::spawn(async move {
+                        Self::sync_artists_bg(client, db, ui_tx, original_msg).await;
+                    });
+                    self.syncing.insert(msg.clone(), task);
+                }
+
+                let (tx, rx) = oneshot::channel();
+                self.db.send(db::SqlCommand::GetArtistList { sender: tx });
+                let data = rx.await?;
+
+                let _ = output
+                    .send(DataEvent::ListData {
+                        request: Get::Artists(sync),
+                        title: "Artists".into(),
+                        data,
+                    })
+                    .await;
+
                 Ok(())
             }
 
-            Get::SyncDone(request) => {
-                self.syncing.remove(&request);
+            Get::Albums(artistid, sync) => {
+                if sync && self.kodi_connected && !self.syncing.contains_key(&msg) {
+                    let client = self.client.clone();
+                    let db = self.db.clone();
+                    let ui_tx = sender.clone();
+                    let original_msg = msg.clone();
+                    let task = tokio::spawn(async move {
+                        Self::sync_albums_bg(client, db, ui_tx, artistid, original_msg).await;
+                    });
+                    self.syncing.insert(msg.clone(), task);
+                }
+
+                let (tx, rx) = oneshot::channel();
+                self.db.send(db::SqlCommand::GetArtistItem {
+                    sender: tx,
+                    artistid,
+                });
+                let artist = rx.await?;
+
+                let (tx, rx) = oneshot::channel();
+                self.db.send(db::SqlCommand::GetAlbumList {
+                    sender: tx,
+                    artistid,
+                });
+                let data = rx.await?;
+
+                let _ = output
+                    .send(DataEvent::ListData {
+                        request: Get::Albums(artistid, sync),
+                        title: artist.artist,
+                        data,
+                    })
+                    .await;
+
                 Ok(())
             }
 
-            Get::Movies(sync) => {
-                if sync && self.kodi_connected && !self.syncing.contains(&msg) {
-                    self.syncing.insert(msg.clone());
+            Get::Songs(albumid, sync) => {
+                if sync && self.kodi_connected && !self.syncing.contains_key(&msg) {
                     let client = self.client.clone();
                     let db = self.db.clone();
                     let ui_tx = sender.clone();
                     let original_msg = msg.clone();
-                    tokio::spawn(async move {
-                        Self::sync_movies_bg(client, db, ui_tx, original_msg).await;
+                    let task = tokio::spawn(async move {
+                        Self::sync_songs_bg(client, db, ui_tx, albumid, original_msg).await;
                     });
+                    self.syncing.insert(msg.clone(), task);
                 }
 
                 let (tx, rx) = oneshot::channel();
-                let _ = self.db.send(db::SqlCommand::GetMovieList { sender: tx });
+                self.db.send(db::SqlCommand::GetAlbumItem {
+                    sender: tx,
+                    albumid,
+                });
+                let album = rx.await?;
+
+                let (tx, rx) = oneshot::channel();
+                self.db.send(db::SqlCommand::GetSongList {
+                    sender: tx,
+                    albumid,
+                });
                 let data = rx.await?;
 
                 let _ = output
                     .send(DataEvent::ListData {
-                        request: Get::Movies(sync),
-                        title: "Movies".into(),
+                        request: Get::Songs(albumid, sync),
+                        title: format!("{} > {}", album.displayartist, album.title),
                         data,
                     })
                     .await;
@@ -340,15 +1264,15 @@ impl Data {
             }
 
             Get::TVShows(sync) => {
-                if sync && self.kodi_connected && !self.syncing.contains(&msg) {
-                    self.syncing.insert(msg.clone());
+                if sync && self.kodi_connected && !self.syncing.contains_key(&msg) {
                     let client = self.client.clone();
                     let db = self.db.clone();
                     let ui_tx = sender.clone();
                     let original_msg = msg.clone();
-                    tokio::spawn(async move {
+                    let task = tokio::spawn(async move {
                         Self::sync_tvshows_bg(client, db, ui_tx, original_msg).await;
                     });
+                    self.syncing.insert(msg.clone(), task);
                 }
 
                 let (tx, rx) = oneshot::channel();
@@ -366,25 +1290,112 @@ impl Data {
                 Ok(())
             }
 
-            Get::TVSeasons(tvshowid) => {
-                let item = if self.kodi_connected {
+            Get::TVShowDetails(tvshowid) => {
+                let mut details = None;
+                if self.kodi_connected {
                     let (tx, mut rx) = channel(1);
-                    let _ = self.client.send(KodiCommand::VideoLibraryGetTVShowDetails {
+                    self.client.send(KodiCommand::VideoLibraryGetTVShowDetails {
                         sender: tx,
                         tvshowid,
                     });
-                    let show = rx.next().await.expect("Should work if kodi online..");
-                    // update the show in db since we loaded it anyway.
-                    self.db
-                        .send(db::SqlCommand::InsertTVShows(vec![show.clone()]));
-                    show
-                } else {
-                    let (tx, rx) = oneshot::channel();
-                    let _ = self.db.send(db::SqlCommand::GetTVShowItem {
+                    if let Some(show) = rx.next().await {
+                        self.db
+                            .send(db::SqlCommand::InsertTVShows(vec![show.item.clone()]));
+                        self.db
+                            .send(db::SqlCommand::InsertTVShowDetails(Box::new(show.clone())));
+                        details = Some(show);
+                    }
+                }
+
+                let details = match details {
+                    Some(details) => details,
+                    None => {
+                        let (tx, rx) = oneshot::channel();
+                        self.db.send(db::SqlCommand::GetTVShowDetails {
+                            sender: tx,
+                            tvshowid,
+                        });
+                        rx.await?
+                    }
+                };
+
+                let _ = output
+                    .send(DataEvent::ItemDetails {
+                        request: Get::TVShowDetails(tvshowid),
+                        details: ItemDetails::TVShow(Box::new(details)),
+                    })
+                    .await;
+                Ok(())
+            }
+
+            Get::EpisodeDetails(episodeid) => {
+                let mut details = None;
+                if self.kodi_connected {
+                    let (tx, mut rx) = channel(1);
+                    self.client
+                        .send(KodiCommand::VideoLibraryGetEpisodeDetails {
+                            sender: tx,
+                            episodeid,
+                        });
+                    if let Some(episode) = rx.next().await {
+                        self.db
+                            .send(db::SqlCommand::InsertTVEpisodes(vec![episode.item.clone()]));
+                        self.db.send(db::SqlCommand::InsertEpisodeDetails(Box::new(
+                            episode.clone(),
+                        )));
+                        details = Some(episode);
+                    }
+                }
+
+                let details = match details {
+                    Some(details) => details,
+                    None => {
+                        let (tx, rx) = oneshot::channel();
+                        self.db.send(db::SqlCommand::GetEpisodeDetails {
+                            sender: tx,
+                            episodeid,
+                        });
+                        rx.await?
+                    }
+                };
+
+                let _ = output
+                    .send(DataEvent::ItemDetails {
+                        request: Get::EpisodeDetails(episodeid),
+                        details: ItemDetails::Episode(Box::new(details)),
+                    })
+                    .await;
+                Ok(())
+            }
+
+            Get::TVSeasons(tvshowid) => {
+                let mut item = None;
+                if self.kodi_connected {
+                    let (tx, mut rx) = channel(1);
+                    self.client.send(KodiCommand::VideoLibraryGetTVShowDetails {
                         sender: tx,
                         tvshowid,
                     });
-                    rx.await?
+                    if let Some(show) = rx.next().await {
+                        // update the show in db since we loaded it anyway.
+                        self.db
+                            .send(db::SqlCommand::InsertTVShows(vec![show.item.clone()]));
+                        self.db
+                            .send(db::SqlCommand::InsertTVShowDetails(Box::new(show.clone())));
+                        item = Some(show.item);
+                    }
+                }
+
+                let item = match item {
+                    Some(item) => item,
+                    None => {
+                        let (tx, rx) = oneshot::channel();
+                        self.db.send(db::SqlCommand::GetTVShowItem {
+                            sender: tx,
+                            tvshowid,
+                        });
+                        rx.await?
+                    }
                 };
 
                 let (tx, rx) = oneshot::channel();
@@ -401,13 +1412,15 @@ impl Data {
                         sender: tx,
                         tvshowid: tvshowid as i32,
                     });
-                    let newseasons = rx.select_next_some().await;
-                    let _ = self.db.send(db::SqlCommand::InsertTVSeasons(
-                        newseasons.clone(),
-                        tvshowid,
-                    ));
+                    // The cached seasons will do if kodi fails
+                    if let Some(newseasons) = rx.next().await {
+                        self.db.send(db::SqlCommand::InsertTVSeasons(
+                            newseasons.clone(),
+                            tvshowid,
+                        ));
 
-                    data = newseasons.into_iter().map(|v| Box::new(v) as _).collect();
+                        data = newseasons.into_iter().map(|v| Box::new(v) as _).collect();
+                    }
                 };
 
                 if !data.is_empty() {
@@ -433,15 +1446,15 @@ impl Data {
             }
 
             Get::TVEpisodes(tvshowid, season, sync) => {
-                if sync && self.kodi_connected && !self.syncing.contains(&msg) {
-                    self.syncing.insert(msg.clone());
+                if sync && self.kodi_connected && !self.syncing.contains_key(&msg) {
                     let client = self.client.clone();
                     let db = self.db.clone();
                     let ui_tx = sender.clone();
                     let original_msg = msg.clone();
-                    tokio::spawn(async move {
+                    let task = tokio::spawn(async move {
                         Self::sync_tvepisodes_bg(client, db, ui_tx, tvshowid, original_msg).await;
                     });
+                    self.syncing.insert(msg.clone(), task);
                 }
 
                 // pull show item from kodi if online and update db?
@@ -481,76 +1494,112 @@ impl Data {
         }
     }
 
-    async fn sync_items_by_ids_bg<T, K, B, D, I, G>(
+    async fn sync_items_by_ids_bg<T, S, K, B, D, I, G>(
         mut client: client::Connection,
         mut db: db::SqlConnection,
         mut ui_tx: Sender<Get>,
         refresh_msg: Get,
-        get_kodi_ids: K,
-        get_batch: B,
-        db_delete_ids: D,
-        db_insert: I,
-        get_db_ids: G,
+        source: SyncSource<K, B, D, I, G>,
         batch_size: i32,
     ) where
         T: Clone + Send + 'static,
-        K: Fn(Sender<Vec<u32>>) -> KodiCommand,
+        S: SyncKey,
+        K: Fn(Sender<Vec<S>>) -> KodiCommand,
         B: Fn(Sender<Vec<T>>, Vec<u32>) -> KodiCommand,
         D: Fn(Vec<u32>) -> db::SqlCommand,
         I: Fn(Vec<T>) -> db::SqlCommand,
-        G: Fn(oneshot::Sender<Vec<u32>>) -> db::SqlCommand,
+        G: Fn(oneshot::Sender<Vec<S>>) -> db::SqlCommand,
     {
-        // Step 1: Get all IDs from Kodi
+        // Step 1: Get all IDs (or fingerprints) from Kodi
         let (kodi_tx, mut kodi_rx) = channel(1);
-        client.send(get_kodi_ids(kodi_tx));
-        let kodi_ids = match kodi_rx.next().await {
-            Some(ids) => ids,
+        client.send((source.get_kodi_keys)(kodi_tx));
+        let kodi_keys = match kodi_rx.next().await {
+            Some(keys) => keys,
             None => return,
         };
 
         // Step 2: Get all IDs from DB
         let (db_tx, db_rx) = oneshot::channel();
-        let _ = db.send(get_db_ids(db_tx));
-        let db_ids = match db_rx.await {
-            Ok(ids) => ids,
+        db.send((source.get_db_keys)(db_tx));
+        let db_keys: HashMap<u32, S> = match db_rx.await {
+            Ok(keys) => keys.into_iter().map(|key| (key.id(), key)).collect(),
             Err(_) => return,
         };
 
-        // Step 3: Compute new, deleted, and existing IDs
-        let kodi_ids_set: std::collections::HashSet<u32> = kodi_ids.iter().cloned().collect();
-        let db_ids_set: std::collections::HashSet<u32> = db_ids.iter().cloned().collect();
-
-        let new_ids: Vec<u32> = kodi_ids
-            .iter()
-            .filter(|id| !db_ids_set.contains(id))
-            .cloned()
-            .collect();
-        let deleted_ids: Vec<u32> = db_ids
-            .iter()
+        // Step 3: Compute new, deleted, and changed IDs
+        let kodi_ids_set: std::collections::HashSet<u32> =
+            kodi_keys.iter().map(SyncKey::id).collect();
+
+        let mut new_ids = Vec::new();
+        let mut changed_ids = Vec::new();
+        for key in &kodi_keys {
+            match db_keys.get(&key.id()) {
+                None => new_ids.push(key.id()),
+                Some(cached) if key.changed(cached) => changed_ids.push(key.id()),
+                Some(_) => {}
+            }
+        }
+        let deleted_ids: Vec<u32> = db_keys
+            .keys()
             .filter(|id| !kodi_ids_set.contains(id))
             .cloned()
             .collect();
 
         // Step 4: Delete items no longer in Kodi
         if !deleted_ids.is_empty() {
-            let _ = db.send(db_delete_ids(deleted_ids));
+            db.send((source.db_delete_ids)(deleted_ids));
             let _ = ui_tx.send(refresh_msg.clone()).await;
         }
 
-        // Step 5: Fetch new items in batches
-        if !new_ids.is_empty() {
-            for _batch_ids in new_ids.chunks(batch_size as usize) {
+        // Step 5: Fetch new items in batches, then re-fetch the changed ones
+        for ids in [new_ids, changed_ids] {
+            for _batch_ids in ids.chunks(batch_size as usize) {
                 let (tx, mut rx) = channel(1);
-                client.send(get_batch(tx, _batch_ids.to_vec()));
+                client.send((source.get_batch)(tx, _batch_ids.to_vec()));
 
                 if let Some(items) = rx.next().await {
-                    let _ = db.send(db_insert(items));
+                    db.send((source.db_insert)(items));
                     let _ = ui_tx.send(refresh_msg.clone()).await;
                 }
             }
         }
     }
 
+    fn cancel_load(&mut self) {
+        if let Some(task) = self.loading.take() {
+            // Drops the reply channel, which the client takes as a cancel
+            task.abort();
+        }
+    }
+
+    async fn load_directory_bg(
+        mut client: client::Connection,
+        mut output: Sender<DataEvent>,
+        path: String,
+        media_type: MediaType,
+    ) {
+        let (tx, mut rx) = channel(1);
+        client.send(KodiCommand::GetDirectory {
+            sender: tx,
+            path: path.clone(),
+            media_type: media_type.clone(),
+        });
+
+        let request = Get::Directory {
+            path: path.clone(),
+            media_type,
+        };
+        let event = match rx.next().await {
+            Some(data) => DataEvent::ListData {
+                request,
+                title: path,
+                data,
+            },
+            None => DataEvent::LoadFailed(request),
+        };
+        let _ = output.send(event).await;
+    }
+
     async fn sync_movies_bg(
         client: client::Connection,
         db: db::SqlConnection,
@@ -562,17 +1611,168 @@ impl Data {
             db,
             ui_tx.clone(),
             Get::Movies(false),
-            |sender| KodiCommand::VideoLibraryGetMovieIDs { sender },
-            |sender, ids| KodiCommand::VideoLibraryGetMoviesByIDs { sender, ids },
-            |ids| db::SqlCommand::DeleteMoviesByIDs(ids),
-            |movies| db::SqlCommand::InsertMovies(movies),
-            |sender| db::SqlCommand::GetMovieIDs { sender },
+            SyncSource {
+                get_kodi_keys: |sender| KodiCommand::VideoLibraryGetMovieFingerprints { sender },
+                get_batch: |sender, ids| KodiCommand::VideoLibraryGetMoviesByIDs { sender, ids },
+                db_delete_ids: |ids| db::SqlCommand::DeleteMoviesByIDs(ids),
+                db_insert: |movies| db::SqlCommand::InsertMovies(movies),
+                get_db_keys: |sender| db::SqlCommand::GetMovieFingerprints { sender },
+            },
             50, // Smaller batch size for dynamic feel
         )
         .await;
         let _ = ui_tx.send(Get::SyncDone(Box::new(original_msg))).await;
     }
 
+    // Re-fetches one item after a VideoLibrary.OnUpdate, then reloads
+    // whatever's on screen by then if the item is part of it.
+    async fn update_library_item_bg(
+        mut client: client::Connection,
+        mut db: db::SqlConnection,
+        mut ui_tx: Sender<Get>,
+        item: LibraryItem,
+    ) {
+        let ids = vec![item.id()];
+        let mut tvshowid = None;
+        match item {
+            LibraryItem::Movie(_) => {
+                let (tx, mut rx) = channel(1);
+                client.send(KodiCommand::VideoLibraryGetMoviesByIDs { sender: tx, ids });
+                match rx.next().await {
+                    Some(movies) if !movies.is_empty() => {
+                        db.send(db::SqlCommand::InsertMovies(movies))
+                    }
+                    _ => return,
+                }
+            }
+            LibraryItem::TVShow(_) => {
+                let (tx, mut rx) = channel(1);
+                client.send(KodiCommand::VideoLibraryGetTVShowsByIDs { sender: tx, ids });
+                match rx.next().await {
+                    Some(shows) if !shows.is_empty() => {
+                        db.send(db::SqlCommand::InsertTVShows(shows))
+                    }
+                    _ => return,
+                }
+            }
+            LibraryItem::Episode(_) => {
+                let (tx, mut rx) = channel(1);
+                client.send(KodiCommand::VideoLibraryGetTVEpisodesByIDs { sender: tx, ids });
+                match rx.next().await {
+                    Some(episodes) if !episodes.is_empty() => {
+                        tvshowid = Some(episodes[0].tvshowid);
+                        db.send(db::SqlCommand::InsertTVEpisodes(episodes));
+                    }
+                    _ => return,
+                }
+            }
+        }
+
+        let _ = ui_tx.send(Get::LibraryRefresh { item, tvshowid }).await;
+    }
+
+    // The UI and db get the edit straight away. If kodi rejects it the real
+    // state is fetched back and applied over it, if kodi can't be reached
+    // at all it's kept and replayed on the next connect.
+    async fn edit_item(
+        &mut self,
+        output: &mut Sender<DataEvent>,
+        edit: LibraryEdit,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.db.send(db::SqlCommand::ApplyEdit(edit));
+        if !self.kodi_connected {
+            self.db.send(db::SqlCommand::AddPendingEdit(edit));
+            return Ok(());
+        }
+        match self.send_edit(edit).await {
+            EditOutcome::Accepted => {}
+            EditOutcome::Rejected => self.undo_edit(output, edit).await,
+            EditOutcome::Unreachable => self.db.send(db::SqlCommand::AddPendingEdit(edit)),
+        }
+        Ok(())
+    }
+
+    // Puts kodi's own playcount/userrating back over a rejected edit.
+    // If the item is gone there's nothing to put back.
+    async fn undo_edit(&mut self, output: &mut Sender<DataEvent>, edit: LibraryEdit) {
+        let (tx, mut rx) = channel(1);
+        self.client.send(KodiCommand::VideoLibraryGetUserState {
+            sender: tx,
+            item: edit.item,
+        });
+        for correction in rx.next().await.unwrap_or_default() {
+            self.db.send(db::SqlCommand::ApplyEdit(correction));
+            let _ = output.send(DataEvent::ItemEdited(correction)).await;
+        }
+    }
+
+    async fn send_edit(&mut self, edit: LibraryEdit) -> EditOutcome {
+        let (tx, mut rx) = channel(1);
+        self.client
+            .send(KodiCommand::VideoLibrarySetDetails { sender: tx, edit });
+        match rx.next().await {
+            Some(Ok(())) => EditOutcome::Accepted,
+            // Kodi's own error, e.g. the item was deleted, it won't change its mind
+            Some(Err(client::KodiError::Rpc { .. })) => EditOutcome::Rejected,
+            _ => EditOutcome::Unreachable,
+        }
+    }
+
+    // Part watched movies/episodes, then the next episode of any show that's
+    // been started. If kodi doesn't answer the cached list is left alone.
+    async fn sync_continue_watching(&mut self) {
+        let (tx, mut rx) = channel(1);
+        self.client
+            .send(KodiCommand::VideoLibraryGetInProgressMovies { sender: tx });
+        let Some(movies) = rx.next().await else {
+            return;
+        };
+
+        let (tx, mut rx) = channel(1);
+        self.client
+            .send(KodiCommand::VideoLibraryGetInProgressEpisodes { sender: tx });
+        let Some(mut episodes) = rx.next().await else {
+            return;
+        };
+
+        let (tx, mut rx) = channel(1);
+        self.client
+            .send(KodiCommand::VideoLibraryGetInProgressTVShows { sender: tx });
+        let Some(tvshowids) = rx.next().await else {
+            return;
+        };
+
+        for tvshowid in tvshowids {
+            if episodes.iter().any(|ep| ep.item.tvshowid == tvshowid) {
+                continue;
+            }
+            let (tx, mut rx) = channel(1);
+            self.client.send(KodiCommand::VideoLibraryGetNextEpisode {
+                sender: tx,
+                tvshowid,
+            });
+            let Some(next) = rx.next().await else {
+                return;
+            };
+            episodes.extend(next);
+        }
+
+        let movieids = movies.iter().map(|m| m.movieid).collect();
+        let episode_ids = episodes
+            .iter()
+            .map(|ep| (ep.item.episodeid, ep.showtitle.clone()))
+            .collect();
+
+        self.db.send(db::SqlCommand::InsertMovies(movies));
+        self.db.send(db::SqlCommand::InsertTVEpisodes(
+            episodes.into_iter().map(|ep| ep.item).collect(),
+        ));
+        self.db.send(db::SqlCommand::SetContinueWatching {
+            movieids,
+            episodes: episode_ids,
+        });
+    }
+
     async fn sync_tvshows_bg(
         client: client::Connection,
         db: db::SqlConnection,
@@ -584,87 +1784,458 @@ impl Data {
             db,
             ui_tx.clone(),
             Get::TVShows(false),
-            |sender| KodiCommand::VideoLibraryGetTVShowIDs { sender },
-            |sender, ids| KodiCommand::VideoLibraryGetTVShowsByIDs { sender, ids },
-            |ids| db::SqlCommand::DeleteTVShowsByIDs(ids),
-            |tvshows| db::SqlCommand::InsertTVShows(tvshows),
-            |sender| db::SqlCommand::GetTVShowIDs { sender },
+            SyncSource {
+                get_kodi_keys: |sender| KodiCommand::VideoLibraryGetTVShowFingerprints { sender },
+                get_batch: |sender, ids| KodiCommand::VideoLibraryGetTVShowsByIDs { sender, ids },
+                db_delete_ids: |ids| db::SqlCommand::DeleteTVShowsByIDs(ids),
+                db_insert: |tvshows| db::SqlCommand::InsertTVShows(tvshows),
+                get_db_keys: |sender| db::SqlCommand::GetTVShowFingerprints { sender },
+            },
             50, // Smaller batch size
         )
         .await;
         let _ = ui_tx.send(Get::SyncDone(Box::new(original_msg))).await;
     }
 
+    async fn sync_artists_bg(
+        client: client::Connection,
+        db: db::SqlConnection,
+        mut ui_tx: Sender<Get>,
+        original_msg: Get,
+    ) {
+        Self::sync_items_by_ids_bg(
+            client,
+            db,
+            ui_tx.clone(),
+            Get::Artists(false),
+            SyncSource {
+                get_kodi_keys: |sender| KodiCommand::AudioLibraryGetArtistIDs { sender },
+                get_batch: |sender, ids| KodiCommand::AudioLibraryGetArtistsByIDs { sender, ids },
+                db_delete_ids: db::SqlCommand::DeleteArtistsByIDs,
+                db_insert: db::SqlCommand::InsertArtists,
+                get_db_keys: |sender| db::SqlCommand::GetArtistIDs { sender },
+            },
+            50,
+        )
+        .await;
+        let _ = ui_tx.send(Get::SyncDone(Box::new(original_msg))).await;
+    }
+
+    // Albums and songs are synced per artist/album as they're opened, like episodes
+    async fn sync_albums_bg(
+        client: client::Connection,
+        db: db::SqlConnection,
+        mut ui_tx: Sender<Get>,
+        artistid: u32,
+        original_msg: Get,
+    ) {
+        Self::sync_items_by_ids_bg(
+            client,
+            db,
+            ui_tx.clone(),
+            Get::Albums(artistid, false),
+            SyncSource {
+                get_kodi_keys: move |sender| KodiCommand::AudioLibraryGetAlbumIDs {
+                    sender,
+                    artistid,
+                },
+                get_batch: |sender, ids| KodiCommand::AudioLibraryGetAlbumsByIDs { sender, ids },
+                db_delete_ids: move |ids| db::SqlCommand::DeleteAlbumsByIDs { ids, artistid },
+                db_insert: move |albums| db::SqlCommand::InsertAlbums { albums, artistid },
+                get_db_keys: move |sender| db::SqlCommand::GetAlbumIDs { sender, artistid },
+            },
+            50,
+        )
+        .await;
+        let _ = ui_tx.send(Get::SyncDone(Box::new(original_msg))).await;
+    }
+
+    async fn sync_songs_bg(
+        client: client::Connection,
+        db: db::SqlConnection,
+        mut ui_tx: Sender<Get>,
+        albumid: u32,
+        original_msg: Get,
+    ) {
+        Self::sync_items_by_ids_bg(
+            client,
+            db,
+            ui_tx.clone(),
+            Get::Songs(albumid, false),
+            SyncSource {
+                get_kodi_keys: move |sender| KodiCommand::AudioLibraryGetSongIDs {
+                    sender,
+                    albumid,
+                },
+                get_batch: |sender, ids| KodiCommand::AudioLibraryGetSongsByIDs { sender, ids },
+                db_delete_ids: move |ids| db::SqlCommand::DeleteSongsByIDs { ids, albumid },
+                db_insert: db::SqlCommand::InsertSongs,
+                get_db_keys: move |sender| db::SqlCommand::GetSongIDs { sender, albumid },
+            },
+            50,
+        )
+        .await;
+        let _ = ui_tx.send(Get::SyncDone(Box::new(original_msg))).await;
+    }
+
     async fn sync_tvepisodes_bg(
-        mut client: client::Connection,
-        mut db: db::SqlConnection,
+        client: client::Connection,
+        db: db::SqlConnection,
         mut ui_tx: Sender<Get>,
         tvshowid: u32,
         original_msg: Get,
     ) {
-        // Get episode IDs from Kodi
-        let (kodi_tx, mut kodi_rx) = channel(1);
-        client.send(KodiCommand::VideoLibraryGetTVEpisodeIDs {
-            sender: kodi_tx,
+        Self::sync_items_by_ids_bg(
+            client,
+            db,
+            ui_tx.clone(),
+            Get::TVEpisodes(tvshowid, -1, false),
+            SyncSource {
+                get_kodi_keys: move |sender| KodiCommand::VideoLibraryGetTVEpisodeFingerprints {
+                    sender,
+                    tvshowid,
+                },
+                get_batch: |sender, ids| KodiCommand::VideoLibraryGetTVEpisodesByIDs {
+                    sender,
+                    ids,
+                },
+                db_delete_ids: move |ids| db::SqlCommand::DeleteTVEpisodesByIDs { ids, tvshowid },
+                db_insert: db::SqlCommand::InsertTVEpisodes,
+                get_db_keys: move |sender| db::SqlCommand::GetTVEpisodeFingerprints {
+                    sender,
+                    tvshowid,
+                },
+            },
+            50,
+        )
+        .await;
+        let _ = ui_tx.send(Get::SyncDone(Box::new(original_msg))).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mockkodi::MockKodi;
+    use serde_json::{Value, json};
+
+    async fn memory_library() -> db::SqlConnection {
+        let (tx, rx) = oneshot::channel();
+        tokio::spawn(db::open(":memory:".into(), tx, true));
+        rx.await.unwrap()
+    }
+
+    fn fingerprint(id_field: &str, id: u32, playcount: i16) -> Value {
+        json!({
+            id_field: id,
+            "label": format!("Item {}", id),
+            "title": format!("Item {}", id),
+            "art": {},
+            "playcount": playcount,
+            "lastplayed": if playcount > 0 { "2024-02-02 20:00:00" } else { "" },
+            "dateadded": "2024-01-01 10:00:00",
+            "userrating": 0,
+        })
+    }
+
+    fn movie(movieid: u32, playcount: i16) -> Value {
+        let mut movie = fingerprint("movieid", movieid, playcount);
+        movie.as_object_mut().unwrap().extend(
+            json!({
+                "year": 2001,
+                "file": format!("/movies/{}.mkv", movieid),
+                "genre": ["Drama"],
+                "rating": 7.0,
+                "premiered": "2001-05-04",
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        );
+        movie
+    }
+
+    fn episode(episodeid: u32, tvshowid: u32, playcount: i16) -> Value {
+        let mut episode = fingerprint("episodeid", episodeid, playcount);
+        episode.as_object_mut().unwrap().extend(
+            json!({
+                "tvshowid": tvshowid,
+                "season": 1,
+                "episode": episodeid,
+                "file": format!("/tv/{}.mkv", episodeid),
+                "rating": 7.0,
+                "firstaired": "2001-05-04",
+                "specialsortseason": -1,
+                "specialsortepisode": -1,
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        );
+        episode
+    }
+
+    async fn data_for(mock: &MockKodi, db: db::SqlConnection) -> Data {
+        let (client, clientrx) = mock.connect_client().await;
+        Data {
+            kodi_status: Default::default(),
+            settings_db: memory_library().await,
+            db,
+            library_id: 0,
+            kodi_connected: true,
+            client,
+            clientrx,
+            backoff: Backoff::default(),
+            syncing: HashMap::new(),
+            shown: None,
+            loading: None,
+        }
+    }
+
+    // Requested ids, sorted
+    fn requested(mock: &MockKodi, method: &str, id_field: &str) -> Vec<u64> {
+        let mut ids: Vec<u64> = mock
+            .calls(method)
+            .iter()
+            .filter_map(|params| params[id_field].as_u64())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    async fn movie_fingerprints(db: &mut db::SqlConnection) -> Vec<Fingerprint> {
+        let (tx, rx) = oneshot::channel();
+        db.send(db::SqlCommand::GetMovieFingerprints { sender: tx });
+        let mut fingerprints = rx.await.unwrap();
+        fingerprints.sort_by_key(|f| f.id);
+        fingerprints
+    }
+
+    async fn episode_fingerprints(db: &mut db::SqlConnection, tvshowid: u32) -> Vec<Fingerprint> {
+        let (tx, rx) = oneshot::channel();
+        db.send(db::SqlCommand::GetTVEpisodeFingerprints {
+            sender: tx,
             tvshowid,
         });
-        let kodi_episode_ids = match kodi_rx.next().await {
-            Some(ids) => ids,
-            None => return,
-        };
+        let mut fingerprints = rx.await.unwrap();
+        fingerprints.sort_by_key(|f| f.id);
+        fingerprints
+    }
 
-        // Get episode IDs from DB
-        let (db_tx, db_rx) = oneshot::channel();
-        let _ = db.send(db::SqlCommand::GetTVEpisodeIDs {
-            sender: db_tx,
-            tvshowid,
+    #[tokio::test]
+    async fn movie_sync_fetches_only_new_and_changed() {
+        let mock = MockKodi::start().await;
+        // Kodi: 2 has been watched since, 3 is unchanged, 4 is new and 1 is gone
+        let kodi = [(2, 1), (3, 0), (4, 0)];
+        mock.respond(
+            "VideoLibrary.GetMovies",
+            json!({"movies": kodi.map(|(id, plays)| fingerprint("movieid", id, plays))}),
+        );
+        mock.handle("VideoLibrary.GetMovieDetails", move |params| {
+            let id = params["movieid"].as_u64().unwrap() as u32;
+            let (_, plays) = kodi.iter().find(|(kodi_id, _)| *kodi_id == id).unwrap();
+            Ok(json!({"moviedetails": movie(id, *plays)}))
         });
-        let db_episode_ids = match db_rx.await {
-            Ok(ids) => ids,
-            Err(_) => return,
-        };
+        let (client, _events) = mock.connect_client().await;
 
-        // Find new and deleted episodes
-        let kodi_ids_set: std::collections::HashSet<u32> =
-            kodi_episode_ids.iter().cloned().collect();
-        let db_ids_set: std::collections::HashSet<u32> = db_episode_ids.iter().cloned().collect();
+        let mut db = memory_library().await;
+        let cached = [movie(1, 0), movie(2, 0), movie(3, 0)]
+            .into_iter()
+            .map(|m| serde_json::from_value(m).unwrap())
+            .collect();
+        db.send(db::SqlCommand::InsertMovies(cached));
+
+        let (ui_tx, mut ui_rx) = channel(100);
+        Data::sync_movies_bg(client, db.clone(), ui_tx, Get::Movies(true)).await;
 
-        let new_ids: Vec<u32> = kodi_episode_ids
+        assert_eq!(
+            requested(&mock, "VideoLibrary.GetMovieDetails", "movieid"),
+            vec![2, 4]
+        );
+        let ids: Vec<(u32, i16)> = movie_fingerprints(&mut db)
+            .await
             .iter()
-            .filter(|id| !db_ids_set.contains(id))
-            .cloned()
+            .map(|f| (f.id, f.playcount))
+            .collect();
+        assert_eq!(ids, kodi.to_vec());
+
+        // Refreshes for the delete, the new batch and the changed batch
+        let mut refreshes = 0;
+        while let Ok(Some(msg)) = ui_rx.try_next() {
+            match msg {
+                Get::Movies(false) => refreshes += 1,
+                Get::SyncDone(request) => assert_eq!(*request, Get::Movies(true)),
+                other => panic!("Unexpected {:?}", other),
+            }
+        }
+        assert_eq!(refreshes, 3);
+    }
+
+    #[tokio::test]
+    async fn unchanged_movies_are_not_refetched() {
+        let mock = MockKodi::start().await;
+        mock.respond(
+            "VideoLibrary.GetMovies",
+            json!({"movies": [fingerprint("movieid", 1, 2)]}),
+        );
+        let (client, _events) = mock.connect_client().await;
+
+        let mut db = memory_library().await;
+        db.send(db::SqlCommand::InsertMovies(vec![
+            serde_json::from_value(movie(1, 2)).unwrap(),
+        ]));
+
+        let (ui_tx, _ui_rx) = channel(100);
+        Data::sync_movies_bg(client, db.clone(), ui_tx, Get::Movies(true)).await;
+
+        assert!(mock.calls("VideoLibrary.GetMovieDetails").is_empty());
+        assert_eq!(movie_fingerprints(&mut db).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn renamed_movies_and_new_art_are_refetched() {
+        let mock = MockKodi::start().await;
+        let mut renamed = fingerprint("movieid", 1, 0);
+        renamed["title"] = json!("Renamed");
+        let mut new_art = fingerprint("movieid", 2, 0);
+        new_art["art"] = json!({"poster": "image://new.jpg/"});
+        mock.respond(
+            "VideoLibrary.GetMovies",
+            json!({"movies": [renamed, new_art]}),
+        );
+        mock.handle("VideoLibrary.GetMovieDetails", |params| {
+            let id = params["movieid"].as_u64().unwrap() as u32;
+            Ok(json!({"moviedetails": movie(id, 0)}))
+        });
+        let (client, _events) = mock.connect_client().await;
+
+        let mut db = memory_library().await;
+        db.send(db::SqlCommand::InsertMovies(vec![
+            serde_json::from_value(movie(1, 0)).unwrap(),
+            serde_json::from_value(movie(2, 0)).unwrap(),
+        ]));
+
+        let (ui_tx, _ui_rx) = channel(100);
+        Data::sync_movies_bg(client, db.clone(), ui_tx, Get::Movies(true)).await;
+
+        assert_eq!(
+            requested(&mock, "VideoLibrary.GetMovieDetails", "movieid"),
+            vec![1, 2]
+        );
+    }
+
+    #[tokio::test]
+    async fn episode_sync_only_touches_its_show() {
+        let mock = MockKodi::start().await;
+        mock.handle("VideoLibrary.GetEpisodes", |params| {
+            assert_eq!(params["tvshowid"], 1);
+            Ok(json!({"episodes": [
+                fingerprint("episodeid", 10, 0),
+                fingerprint("episodeid", 12, 0),
+            ]}))
+        });
+        mock.handle("VideoLibrary.GetEpisodeDetails", |params| {
+            let id = params["episodeid"].as_u64().unwrap() as u32;
+            Ok(json!({"episodedetails": episode(id, 1, 0)}))
+        });
+        let (client, _events) = mock.connect_client().await;
+
+        let mut db = memory_library().await;
+        let cached = [episode(10, 1, 0), episode(11, 1, 0), episode(20, 2, 0)]
+            .into_iter()
+            .map(|e| serde_json::from_value(e).unwrap())
             .collect();
-        let deleted_ids: Vec<u32> = db_episode_ids
+        db.send(db::SqlCommand::InsertTVEpisodes(cached));
+
+        let (ui_tx, _ui_rx) = channel(100);
+        let request = Get::TVEpisodes(1, -1, true);
+        Data::sync_tvepisodes_bg(client, db.clone(), ui_tx, 1, request).await;
+
+        assert_eq!(
+            requested(&mock, "VideoLibrary.GetEpisodeDetails", "episodeid"),
+            vec![12]
+        );
+        let show1: Vec<u32> = episode_fingerprints(&mut db, 1)
+            .await
             .iter()
-            .filter(|id| !kodi_ids_set.contains(id))
-            .cloned()
+            .map(|f| f.id)
             .collect();
+        assert_eq!(show1, vec![10, 12]);
+        assert_eq!(episode_fingerprints(&mut db, 2).await.len(), 1);
+    }
 
-        // Delete episodes no longer in Kodi
-        if !deleted_ids.is_empty() {
-            let _ = db.send(db::SqlCommand::DeleteTVEpisodesByIDs {
-                ids: deleted_ids,
-                tvshowid,
-            });
-            let _ = ui_tx.send(Get::TVEpisodes(tvshowid, -1, false)).await;
+    #[tokio::test]
+    async fn seasons_fall_back_to_the_cached_show() {
+        // Nothing scripted, so every kodi request fails
+        let mock = MockKodi::start().await;
+        let mut db = memory_library().await;
+        let mut data = data_for(&mock, db.clone()).await;
+        let (mut output, mut events) = channel(10);
+        let (ui_tx, _ui_rx) = channel(10);
+
+        // No cached show either, the UI still has to hear back
+        let res = data
+            .handle_cmd(&mut output, Get::TVSeasons(1), ui_tx.clone())
+            .await;
+        assert!(res.is_err());
+        match events.try_next() {
+            Ok(Some(DataEvent::LoadFailed(request))) => assert_eq!(request, Get::TVSeasons(1)),
+            other => panic!("Unexpected {:?}", other),
         }
 
-        // Fetch new episodes in batches
-        let batch_size = 50;
-        if !new_ids.is_empty() {
-            for _batch_ids in new_ids.chunks(batch_size) {
-                let (tx, mut rx) = channel(1);
-                client.send(KodiCommand::VideoLibraryGetTVEpisodesByIDs {
-                    sender: tx,
-                    ids: _batch_ids.to_vec(),
-                });
-
-                if let Some(episodes) = rx.next().await {
-                    let _ = db.send(db::SqlCommand::InsertTVEpisodes(episodes));
-                    let _ = ui_tx.send(Get::TVEpisodes(tvshowid, -1, false)).await;
-                }
+        let mut show = fingerprint("tvshowid", 1, 0);
+        show.as_object_mut().unwrap().extend(
+            json!({
+                "year": 2001,
+                "season": 0,
+                "episode": 0,
+                "file": "/tv/1/",
+                "genre": ["Drama"],
+                "rating": 7.0,
+            })
+            .as_object()
+            .unwrap()
+            .clone(),
+        );
+        db.send(db::SqlCommand::InsertTVShows(vec![
+            serde_json::from_value(show).unwrap(),
+        ]));
+
+        data.handle_cmd(&mut output, Get::TVSeasons(1), ui_tx)
+            .await
+            .unwrap();
+        match events.try_next() {
+            Ok(Some(DataEvent::ListData { request, title, .. })) => {
+                assert_eq!(request, Get::TVSeasons(1));
+                assert_eq!(title, "Item 1");
             }
+            other => panic!("Unexpected {:?}", other),
         }
-        let _ = ui_tx.send(Get::SyncDone(Box::new(original_msg))).await;
+        assert_eq!(mock.calls("VideoLibrary.GetTVShowDetails").len(), 2);
+    }
+
+    #[tokio::test]
+    async fn bad_continue_watching_keeps_the_cached_list() {
+        let mock = MockKodi::start().await;
+        mock.respond(
+            "VideoLibrary.GetMovies",
+            json!({"movies": [{"movieid": "one"}]}),
+        );
+        let mut db = memory_library().await;
+        db.send(db::SqlCommand::InsertMovies(vec![
+            serde_json::from_value(movie(1, 0)).unwrap(),
+        ]));
+        db.send(db::SqlCommand::SetContinueWatching {
+            movieids: vec![1],
+            episodes: Vec::new(),
+        });
+        let mut data = data_for(&mock, db.clone()).await;
+
+        data.sync_continue_watching().await;
+
+        let (tx, rx) = oneshot::channel();
+        db.send(db::SqlCommand::GetContinueWatching { sender: tx });
+        assert_eq!(rx.await.unwrap().len(), 1);
+        assert!(mock.calls("VideoLibrary.GetEpisodes").is_empty());
     }
 }
@@ -1,15 +1,17 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use iced::futures::StreamExt;
 use iced::futures::channel::mpsc::{Receiver, Sender, channel};
 use iced::futures::channel::oneshot;
 
 use anyhow::{Context, Result};
+use rusqlite::OptionalExtension;
 use tokio::fs;
 use tokio_rusqlite::Connection;
 use tokio_rusqlite::params;
 use tracing::{debug, error};
 
+use crate::keyfile;
 use crate::koditypes::*;
 
 #[derive(Debug)]
@@ -17,7 +19,21 @@ pub enum SqlCommand {
     GetServers {
         sender: oneshot::Sender<Vec<KodiServer>>,
     },
-    AddOrEditServer(KodiServer),
+    AddOrEditServer {
+        server: KodiServer,
+        sender: oneshot::Sender<Result<()>>,
+    },
+    DeleteServer(u8),
+    GetSetting {
+        sender: oneshot::Sender<Option<String>>,
+        key: &'static str,
+    },
+    SetSetting {
+        key: &'static str,
+        value: String,
+    },
+    // Wipes the library tables, used when no server references this library anymore
+    ClearLibrary,
 
     GetMovieList {
         sender: oneshot::Sender<Vec<Box<dyn IntoListData + Send>>>,
@@ -38,29 +54,111 @@ pub enum SqlCommand {
         sender: oneshot::Sender<TVShowListItem>,
         tvshowid: u32,
     },
+    GetMovieDetails {
+        sender: oneshot::Sender<MovieDetails>,
+        movieid: u32,
+    },
+    GetTVShowDetails {
+        sender: oneshot::Sender<TVShowDetails>,
+        tvshowid: u32,
+    },
+    GetEpisodeDetails {
+        sender: oneshot::Sender<EpisodeDetails>,
+        episodeid: u32,
+    },
+
+    GetArtistList {
+        sender: oneshot::Sender<Vec<Box<dyn IntoListData + Send>>>,
+    },
+    GetAlbumList {
+        sender: oneshot::Sender<Vec<Box<dyn IntoListData + Send>>>,
+        artistid: u32,
+    },
+    GetSongList {
+        sender: oneshot::Sender<Vec<Box<dyn IntoListData + Send>>>,
+        albumid: u32,
+    },
+    GetArtistItem {
+        sender: oneshot::Sender<ArtistListItem>,
+        artistid: u32,
+    },
+    GetAlbumItem {
+        sender: oneshot::Sender<AlbumListItem>,
+        albumid: u32,
+    },
+    GetContinueWatching {
+        sender: oneshot::Sender<Vec<Box<dyn IntoListData + Send>>>,
+    },
 
     InsertMovies(Vec<MovieListItem>), // bool clear_before_insert?
+    InsertMovieDetails(Box<MovieDetails>),
     InsertTVShows(Vec<TVShowListItem>),
+    InsertTVShowDetails(Box<TVShowDetails>),
     InsertTVSeasons(Vec<TVSeasonListItem>, u32),
     InsertTVEpisodes(Vec<TVEpisodeListItem>), // same
+    InsertEpisodeDetails(Box<EpisodeDetails>),
+    InsertArtists(Vec<ArtistListItem>),
+    // artistid is the artist the albums were requested for,
+    // kodi can return albums where they're only on some songs
+    InsertAlbums {
+        albums: Vec<AlbumListItem>,
+        artistid: u32,
+    },
+    InsertSongs(Vec<SongListItem>),
+    // The items themselves go in with InsertMovies/InsertTVEpisodes first,
+    // episodes are (episodeid, showtitle)
+    SetContinueWatching {
+        movieids: Vec<u32>,
+        episodes: Vec<(u32, String)>,
+    },
+
+    // Watched/rating changes, applied locally straight away
+    ApplyEdit(LibraryEdit),
+    AddPendingEdit(LibraryEdit),
+    GetPendingEdits {
+        sender: oneshot::Sender<Vec<(i64, LibraryEdit)>>,
+    },
+    DeletePendingEdit(i64),
 
     // ID-based sync operations
-    GetMovieIDs {
+    GetMovieFingerprints {
+        sender: oneshot::Sender<Vec<Fingerprint>>,
+    },
+    GetTVShowFingerprints {
+        sender: oneshot::Sender<Vec<Fingerprint>>,
+    },
+    GetTVEpisodeFingerprints {
+        sender: oneshot::Sender<Vec<Fingerprint>>,
+        tvshowid: u32,
+    },
+    GetArtistIDs {
         sender: oneshot::Sender<Vec<u32>>,
     },
-    GetTVShowIDs {
+    GetAlbumIDs {
         sender: oneshot::Sender<Vec<u32>>,
+        artistid: u32,
     },
-    GetTVEpisodeIDs {
+    GetSongIDs {
         sender: oneshot::Sender<Vec<u32>>,
-        tvshowid: u32,
+        albumid: u32,
     },
     DeleteMoviesByIDs(Vec<u32>),
     DeleteTVShowsByIDs(Vec<u32>),
+    // From a VideoLibrary.OnRemove, a show takes its seasons and episodes with it
+    DeleteLibraryItem(LibraryItem),
     DeleteTVEpisodesByIDs {
         ids: Vec<u32>,
         tvshowid: u32,
     },
+    DeleteArtistsByIDs(Vec<u32>),
+    DeleteAlbumsByIDs {
+        ids: Vec<u32>,
+        artistid: u32,
+    },
+    DeleteSongsByIDs {
+        ids: Vec<u32>,
+        albumid: u32,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -73,23 +171,53 @@ impl SqlConnection {
     }
 }
 
+// The settings db only holds the servers/settings tables.
+// Each KodiServer.db_id gets its own library db so switching servers
+// never mixes up the cached movie/tv lists.
 pub async fn connect(output: oneshot::Sender<SqlConnection>) {
+    let path = db_path("krustmote.db").await;
+    open(path, output, false).await;
+}
+
+pub async fn connect_library(db_id: u8, output: oneshot::Sender<SqlConnection>) {
+    let path = db_path(&library_filename(db_id)).await;
+    open(path, output, true).await;
+}
+
+/// Removes the library db for `db_id`. Must not be called while it's still open.
+pub async fn remove_library(db_id: u8) -> Result<()> {
+    let path = db_path(&library_filename(db_id)).await;
+    if fs::metadata(&path).await.is_ok() {
+        fs::remove_file(&path)
+            .await
+            .with_context(|| format!("Failed to remove {:?}", path))?;
+    }
+    Ok(())
+}
+
+fn library_filename(db_id: u8) -> String {
+    format!("library{}.db", db_id)
+}
+
+async fn db_path(filename: &str) -> PathBuf {
     let dir = crate::PROJECT_DIRS.config_dir();
-    let db_path = dir.join("krustmote.db");
-    let path = if fs::metadata(&dir).await.is_ok() {
-        db_path
+    if fs::metadata(&dir).await.is_ok() || fs::create_dir_all(&dir).await.is_ok() {
+        dir.join(filename)
     } else {
-        if fs::create_dir_all(&dir).await.is_ok() {
-            db_path
-        } else {
-            Path::new("./krustmote.db").to_path_buf()
-        }
-    };
-    match Connection::open(path).await {
+        Path::new(".").join(filename)
+    }
+}
+
+pub(crate) async fn open(path: PathBuf, output: oneshot::Sender<SqlConnection>, library: bool) {
+    match Connection::open(&path).await {
         Ok(conn) => {
-            let res = create_tables(&conn).await;
+            let res = if library {
+                create_library_tables(&conn).await
+            } else {
+                create_tables(&conn).await
+            };
             if res.is_err() {
-                error!("Sqlite err creating tables: {:?}", res.err());
+                error!("Sqlite err creating tables in {:?}: {:?}", path, res.err());
                 panic!("Sqlite err creating tables");
             }
 
@@ -97,17 +225,18 @@ pub async fn connect(output: oneshot::Sender<SqlConnection>) {
             let _ = output.send(SqlConnection(sender));
 
             handle_connection(conn, reciever).await;
+            debug!(?path, "Database connection closed");
         }
         Err(err) => {
             // let _ = output;
-            error!("Failed to open database: {:?}", err);
+            error!("Failed to open database {:?}: {:?}", path, err);
         }
     }
 }
 
-async fn handle_connection(mut conn: Connection, mut reciever: Receiver<SqlCommand>) -> ! {
-    loop {
-        let cmd = reciever.select_next_some().await;
+// Runs until every SqlConnection for this db has been dropped
+async fn handle_connection(mut conn: Connection, mut reciever: Receiver<SqlCommand>) {
+    while let Some(cmd) = reciever.next().await {
         let res = handle_command(cmd, &mut conn).await;
         if res.is_err() {
             error!("Database command error: {:?}", res.err());
@@ -119,13 +248,26 @@ async fn handle_command(cmd: SqlCommand, conn: &mut Connection) -> Result<()> {
     match cmd {
         SqlCommand::GetServers { sender } => get_server_list(conn, sender).await,
 
-        SqlCommand::AddOrEditServer(server) => {
+        SqlCommand::AddOrEditServer { server, sender } => {
             // NOTE might change this to NOT return the servers
-            debug!(?server, "Add or Edit server");
+            debug!(server.name, server.ip, "Add or Edit server");
+            let password = match &server.locked_password {
+                // Still unreadable, but it may be again once the key file is back
+                Some(stored) if server.password.is_empty() => Ok(stored.clone()),
+                _ => keyfile::encrypt(&server.password),
+            };
+            let password = match password {
+                Ok(password) => password,
+                Err(err) => {
+                    // Saving it without the password would lose it for good
+                    let _ = sender.send(Err(err.context("Failed to encrypt the password")));
+                    return Ok(());
+                }
+            };
             let res = conn
                 .call(move |conn| {
                     let q =
-                        "INSERT OR REPLACE INTO servers VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
+                        "INSERT OR REPLACE INTO servers VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)";
 
                     conn.execute(
                         q,
@@ -136,30 +278,103 @@ async fn handle_command(cmd: SqlCommand, conn: &mut Connection) -> Result<()> {
                             server.webserver_port,
                             server.websocket_port,
                             server.username,
-                            server.password,
-                            server.db_id
+                            password,
+                            server.db_id,
+                            u8::from(server.transport),
+                            server.mac,
+                            server.broadcast
                         ],
                     )?;
                     Ok::<_, tokio_rusqlite::Error>(())
                 })
                 .await;
 
-            if let Err(err) = res {
-                error!("Failed to insert/replace server: {:?}", err);
-            }
+            let _ = sender.send(res.context("Failed to save the server"));
+            Ok(())
+        }
+
+        SqlCommand::DeleteServer(id) => {
+            debug!(id, "Delete server");
+            conn.call(move |conn| {
+                conn.execute("DELETE FROM servers WHERE id = ?1", [id])?;
+                Ok::<_, tokio_rusqlite::Error>(())
+            })
+            .await
+            .context("Failed to delete server")?;
+            Ok(())
+        }
+
+        SqlCommand::GetSetting { sender, key } => {
+            let value = conn
+                .call(move |conn| {
+                    let value = conn
+                        .query_row(
+                            "SELECT value FROM settings WHERE setting = ?1",
+                            [key],
+                            |row| row.get(0),
+                        )
+                        .optional()?;
+                    Ok::<Option<String>, tokio_rusqlite::Error>(value)
+                })
+                .await?;
+            let _ = sender.send(value);
+            Ok(())
+        }
+
+        SqlCommand::SetSetting { key, value } => {
+            conn.call(move |conn| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO settings (setting, value) VALUES (?1, ?2)",
+                    params![key, value],
+                )?;
+                Ok::<_, tokio_rusqlite::Error>(())
+            })
+            .await
+            .context("Failed to save setting")?;
             Ok(())
         }
 
+        SqlCommand::ClearLibrary => clear_library(conn).await,
+
+        SqlCommand::GetContinueWatching { sender } => get_continue_watching(conn, sender).await,
+
+        SqlCommand::SetContinueWatching { movieids, episodes } => {
+            set_continue_watching(conn, movieids, episodes).await
+        }
+
+        SqlCommand::ApplyEdit(edit) => apply_edit(conn, edit).await,
+        SqlCommand::AddPendingEdit(edit) => add_pending_edit(conn, edit).await,
+        SqlCommand::GetPendingEdits { sender } => get_pending_edits(conn, sender).await,
+        SqlCommand::DeletePendingEdit(id) => delete_pending_edit(conn, id).await,
+
         SqlCommand::InsertMovies(movies) => insert_movies(conn, movies).await,
 
+        SqlCommand::InsertMovieDetails(details) => insert_movie_details(conn, *details).await,
+
+        SqlCommand::GetMovieDetails { sender, movieid } => {
+            get_movie_details(conn, sender, movieid).await
+        }
+
         SqlCommand::InsertTVShows(tvshows) => insert_tvshows(conn, tvshows).await,
 
+        SqlCommand::InsertTVShowDetails(details) => insert_tvshow_details(conn, *details).await,
+
+        SqlCommand::GetTVShowDetails { sender, tvshowid } => {
+            get_tvshow_details(conn, sender, tvshowid).await
+        }
+
         SqlCommand::InsertTVSeasons(seasons, tvshowid) => {
             insert_tvseasons(conn, seasons, tvshowid).await
         }
 
         SqlCommand::InsertTVEpisodes(episodes) => insert_tvepisodes(conn, episodes).await,
 
+        SqlCommand::InsertEpisodeDetails(details) => insert_episode_details(conn, *details).await,
+
+        SqlCommand::GetEpisodeDetails { sender, episodeid } => {
+            get_episode_details(conn, sender, episodeid).await
+        }
+
         SqlCommand::GetMovieList { sender } => get_movie_list(conn, sender).await,
 
         SqlCommand::GetTVShowList { sender } => get_tv_show_list(conn, sender).await,
@@ -178,20 +393,126 @@ async fn handle_command(cmd: SqlCommand, conn: &mut Connection) -> Result<()> {
             get_tv_show_item(conn, sender, tvshowid).await
         }
 
-        SqlCommand::GetMovieIDs { sender } => get_movie_ids(conn, sender).await,
-        SqlCommand::GetTVShowIDs { sender } => get_tvshow_ids(conn, sender).await,
-        SqlCommand::GetTVEpisodeIDs { sender, tvshowid } => {
-            get_tvepisode_ids(conn, sender, tvshowid).await
+        SqlCommand::GetMovieFingerprints { sender } => {
+            let q = "SELECT movieid, playcount, lastplayed, dateadded, userrating, title, art
+                FROM movielist";
+            get_fingerprints(conn, sender, q, None).await
+        }
+        SqlCommand::GetTVShowFingerprints { sender } => {
+            let q = "SELECT tvshowid, playcount, lastplayed, dateadded, userrating, title, art
+                FROM tvshowlist";
+            get_fingerprints(conn, sender, q, None).await
+        }
+        SqlCommand::GetTVEpisodeFingerprints { sender, tvshowid } => {
+            let q = "SELECT episodeid, playcount, lastplayed, dateadded, userrating, title, art
+                FROM tvepisodelist WHERE tvshowid = ?1";
+            get_fingerprints(conn, sender, q, Some(tvshowid)).await
         }
 
         SqlCommand::DeleteMoviesByIDs(ids) => delete_movies_by_ids(conn, ids).await,
         SqlCommand::DeleteTVShowsByIDs(ids) => delete_tvshows_by_ids(conn, ids).await,
+        SqlCommand::DeleteLibraryItem(item) => delete_library_item(conn, item).await,
         SqlCommand::DeleteTVEpisodesByIDs { ids, tvshowid } => {
             delete_tvepisodes_by_ids(conn, ids, tvshowid).await
         }
+
+        SqlCommand::GetArtistList { sender } => get_artist_list(conn, sender).await,
+        SqlCommand::GetAlbumList { sender, artistid } => {
+            get_album_list(conn, sender, artistid).await
+        }
+        SqlCommand::GetSongList { sender, albumid } => get_song_list(conn, sender, albumid).await,
+        SqlCommand::GetArtistItem { sender, artistid } => {
+            get_artist_item(conn, sender, artistid).await
+        }
+        SqlCommand::GetAlbumItem { sender, albumid } => get_album_item(conn, sender, albumid).await,
+
+        SqlCommand::InsertArtists(artists) => insert_artists(conn, artists).await,
+        SqlCommand::InsertAlbums { albums, artistid } => {
+            insert_albums(conn, albums, artistid).await
+        }
+        SqlCommand::InsertSongs(songs) => insert_songs(conn, songs).await,
+
+        SqlCommand::GetArtistIDs { sender } => get_artist_ids(conn, sender).await,
+        SqlCommand::GetAlbumIDs { sender, artistid } => get_album_ids(conn, sender, artistid).await,
+        SqlCommand::GetSongIDs { sender, albumid } => get_song_ids(conn, sender, albumid).await,
+
+        SqlCommand::DeleteArtistsByIDs(ids) => delete_artists_by_ids(conn, ids).await,
+        SqlCommand::DeleteAlbumsByIDs { ids, artistid } => {
+            delete_albums_by_ids(conn, ids, artistid).await
+        }
+        SqlCommand::DeleteSongsByIDs { ids, albumid } => {
+            delete_songs_by_ids(conn, ids, albumid).await
+        }
     }
 }
 
+fn tvshow_from_row(row: &rusqlite::Row) -> rusqlite::Result<TVShowListItem> {
+    Ok(TVShowListItem {
+        tvshowid: row.get(0)?,
+        title: row.get(1)?,
+        year: row.get(2)?,
+        season: row.get(3)?,
+        episode: row.get(4)?,
+        file: row.get(5)?,
+        dateadded: row.get(6)?,
+        genre: {
+            let genre_str: String = row.get(7)?;
+            genre_str.split(",").map(String::from).collect()
+        },
+        rating: row.get(8)?,
+        playcount: row.get(9)?,
+        art: Art {
+            poster: {
+                let poster_str: String = row.get(10)?;
+                if poster_str.is_empty() {
+                    None
+                } else {
+                    Some(poster_str)
+                }
+            },
+            thumb: None,
+            fanart: None,
+        },
+        userrating: row.get(11)?,
+        lastplayed: row.get(12)?,
+    })
+}
+
+fn episode_from_row(row: &rusqlite::Row) -> rusqlite::Result<TVEpisodeListItem> {
+    Ok(TVEpisodeListItem {
+        episodeid: row.get(0)?,
+        tvshowid: row.get(1)?,
+        title: row.get(2)?,
+        season: row.get(3)?,
+        episode: row.get(4)?,
+        file: row.get(5)?,
+        dateadded: row.get(6)?,
+        rating: row.get(7)?,
+        firstaired: row.get(8)?,
+        playcount: row.get(9)?,
+        art: Art {
+            poster: None,
+            fanart: None,
+            thumb: {
+                let thumb_str: String = row.get(10)?;
+                if thumb_str.is_empty() {
+                    None
+                } else {
+                    Some(thumb_str)
+                }
+            },
+        },
+        specialsortseason: row.get(11)?,
+        specialsortepisode: row.get(12)?,
+        resume: ResumePoint {
+            position: row.get(13)?,
+            total: row.get(14)?,
+        },
+        userrating: row.get(15)?,
+        lastplayed: row.get(16)?,
+    })
+}
+
 // note I may add a limiter/condition to tv_show_list later instead of this
 async fn get_tv_show_item(
     conn: &Connection,
@@ -201,34 +522,7 @@ async fn get_tv_show_item(
     let item_result = conn
         .call(move |conn| {
             let q = "SELECT * FROM tvshowlist WHERE tvshowid = ?1";
-            let item = conn.query_row(q, [tvshowid], |row| {
-                Ok(TVShowListItem {
-                    tvshowid: row.get(0)?,
-                    title: row.get(1)?,
-                    year: row.get(2)?,
-                    season: row.get(3)?,
-                    episode: row.get(4)?,
-                    file: row.get(5)?,
-                    dateadded: row.get(6)?,
-                    genre: {
-                        let genre_str: String = row.get(7)?;
-                        genre_str.split(",").map(String::from).collect()
-                    },
-                    rating: row.get(8)?,
-                    playcount: row.get(9)?,
-                    art: Art {
-                        poster: {
-                            let poster_str: String = row.get(10)?;
-                            if poster_str.is_empty() {
-                                None
-                            } else {
-                                Some(poster_str)
-                            }
-                        },
-                        thumb: None,
-                    },
-                })
-            })?;
+            let item = conn.query_row(q, [tvshowid], tvshow_from_row)?;
             Ok::<TVShowListItem, tokio_rusqlite::Error>(item)
         })
         .await?;
@@ -248,34 +542,7 @@ async fn get_tv_show_list(
             let q = "SELECT * FROM tvshowlist ORDER BY title COLLATE NOCASE ASC";
             let mut stmt = conn.prepare(q)?;
             let shows = stmt
-                .query_map([], |row| {
-                    Ok(Box::new(TVShowListItem {
-                        tvshowid: row.get(0)?,
-                        title: row.get(1)?,
-                        year: row.get(2)?,
-                        season: row.get(3)?,
-                        episode: row.get(4)?,
-                        file: row.get(5)?,
-                        dateadded: row.get(6)?,
-                        genre: {
-                            let genre_str: String = row.get(7)?;
-                            genre_str.split(",").map(String::from).collect()
-                        },
-                        rating: row.get(8)?,
-                        playcount: row.get(9)?,
-                        art: Art {
-                            poster: {
-                                let poster_str: String = row.get(10)?;
-                                if poster_str.is_empty() {
-                                    None
-                                } else {
-                                    Some(poster_str)
-                                }
-                            },
-                            thumb: None,
-                        },
-                    }) as _)
-                })?
+                .query_map([], |row| Ok(Box::new(tvshow_from_row(row)?) as _))?
                 .collect::<Result<Vec<Box<dyn IntoListData + Send>>, rusqlite::Error>>()?;
             Ok::<_, tokio_rusqlite::Error>(shows)
         })
@@ -345,31 +612,7 @@ async fn get_tv_episode_list(
             let mut stmt = conn.prepare(q)?;
             let episodes = stmt
                 .query_map(params, |row| {
-                    Ok(Box::new(TVEpisodeListItem {
-                        episodeid: row.get(0)?,
-                        tvshowid: row.get(1)?,
-                        title: row.get(2)?,
-                        season: row.get(3)?,
-                        episode: row.get(4)?,
-                        file: row.get(5)?,
-                        dateadded: row.get(6)?,
-                        rating: row.get(7)?,
-                        firstaired: row.get(8)?,
-                        playcount: row.get(9)?,
-                        art: Art {
-                            poster: None,
-                            thumb: {
-                                let thumb_str: String = row.get(10)?;
-                                if thumb_str.is_empty() {
-                                    None
-                                } else {
-                                    Some(thumb_str)
-                                }
-                            },
-                        },
-                        specialsortseason: row.get(11)?,
-                        specialsortepisode: row.get(12)?,
-                    }) as _)
+                    Ok(Box::new(episode_from_row(row)?) as _)
                 })?
                 .collect::<Result<Vec<Box<dyn IntoListData + Send>>, rusqlite::Error>>()?;
             Ok::<_, tokio_rusqlite::Error>(episodes)
@@ -380,6 +623,50 @@ async fn get_tv_episode_list(
     Ok(())
 }
 
+fn movie_from_row(row: &rusqlite::Row) -> rusqlite::Result<MovieListItem> {
+    Ok(MovieListItem {
+        movieid: row.get(0)?,
+        title: row.get(1)?,
+        genre: {
+            let genre_str: String = row.get(2)?;
+            genre_str.split(",").map(String::from).collect()
+        },
+        year: row.get(3)?,
+        rating: row.get(4)?,
+        playcount: row.get(5)?,
+        file: row.get(6)?,
+        dateadded: row.get(7)?,
+        premiered: row.get(8)?,
+        art: Art {
+            poster: {
+                let poster_str: String = row.get(9)?;
+                if poster_str.is_empty() {
+                    None
+                } else {
+                    Some(poster_str)
+                }
+            },
+            thumb: None,
+            fanart: None,
+        },
+        resume: ResumePoint {
+            position: row.get(10)?,
+            total: row.get(11)?,
+        },
+        userrating: row.get(12)?,
+        lastplayed: row.get(13)?,
+    })
+}
+
+// Like genre but without the [""] for an empty list
+fn split_list(list: Option<String>) -> Vec<String> {
+    list.unwrap_or_default()
+        .split(",")
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
 async fn get_movie_list(
     conn: &Connection,
     sender: oneshot::Sender<Vec<Box<dyn IntoListData + Send>>>,
@@ -389,33 +676,7 @@ async fn get_movie_list(
             let q = "SELECT * FROM movielist ORDER BY dateadded DESC";
             let mut stmt = conn.prepare(q)?;
             let movies = stmt
-                .query_map([], |row| {
-                    Ok(Box::new(MovieListItem {
-                        movieid: row.get(0)?,
-                        title: row.get(1)?,
-                        genre: {
-                            let genre_str: String = row.get(2)?;
-                            genre_str.split(",").map(String::from).collect()
-                        },
-                        year: row.get(3)?,
-                        rating: row.get(4)?,
-                        playcount: row.get(5)?,
-                        file: row.get(6)?,
-                        dateadded: row.get(7)?,
-                        premiered: row.get(8)?,
-                        art: Art {
-                            poster: {
-                                let poster_str: String = row.get(9)?;
-                                if poster_str.is_empty() {
-                                    None
-                                } else {
-                                    Some(poster_str)
-                                }
-                            },
-                            thumb: None,
-                        },
-                    }) as _)
-                })?
+                .query_map([], |row| Ok(Box::new(movie_from_row(row)?) as _))?
                 .collect::<Result<Vec<Box<dyn IntoListData + Send>>, rusqlite::Error>>()?;
 
             Ok::<_, tokio_rusqlite::Error>(movies)
@@ -426,6 +687,127 @@ async fn get_movie_list(
     Ok(())
 }
 
+// LEFT JOIN so a movie that was never opened online still gets
+// a (mostly empty) details view from its movielist row.
+async fn get_movie_details(
+    conn: &Connection,
+    sender: oneshot::Sender<MovieDetails>,
+    movieid: u32,
+) -> Result<()> {
+    let details = conn
+        .call(move |conn| {
+            let q = "SELECT movielist.*,
+                    originaltitle, tagline, plot, director, writer, studio, mpaa, country,
+                    runtime, moviecast, streamdetails, fanart
+                FROM movielist LEFT JOIN moviedetails USING (movieid)
+                WHERE movieid = ?1";
+            let details = conn.query_row(q, [movieid], |row| {
+                let mut item = movie_from_row(row)?;
+                item.art.fanart = row.get(25)?;
+                let cast: Option<String> = row.get(23)?;
+                let streamdetails: Option<String> = row.get(24)?;
+                Ok(MovieDetails {
+                    item,
+                    originaltitle: row.get::<_, Option<String>>(14)?.unwrap_or_default(),
+                    tagline: row.get::<_, Option<String>>(15)?.unwrap_or_default(),
+                    plot: row.get::<_, Option<String>>(16)?.unwrap_or_default(),
+                    director: split_list(row.get(17)?),
+                    writer: split_list(row.get(18)?),
+                    studio: split_list(row.get(19)?),
+                    mpaa: row.get::<_, Option<String>>(20)?.unwrap_or_default(),
+                    country: split_list(row.get(21)?),
+                    runtime: row.get::<_, Option<u32>>(22)?.unwrap_or_default(),
+                    cast: cast
+                        .and_then(|c| serde_json::from_str(&c).ok())
+                        .unwrap_or_default(),
+                    streamdetails: streamdetails
+                        .and_then(|s| serde_json::from_str(&s).ok())
+                        .unwrap_or_default(),
+                })
+            })?;
+            Ok::<MovieDetails, tokio_rusqlite::Error>(details)
+        })
+        .await?;
+
+    let _ = sender.send(details);
+    Ok(())
+}
+
+async fn get_tvshow_details(
+    conn: &Connection,
+    sender: oneshot::Sender<TVShowDetails>,
+    tvshowid: u32,
+) -> Result<()> {
+    let details = conn
+        .call(move |conn| {
+            let q = "SELECT tvshowlist.*,
+                    originaltitle, plot, studio, mpaa, status, premiered, tvshowcast, fanart
+                FROM tvshowlist LEFT JOIN tvshowdetails USING (tvshowid)
+                WHERE tvshowid = ?1";
+            let details = conn.query_row(q, [tvshowid], |row| {
+                let mut item = tvshow_from_row(row)?;
+                item.art.fanart = row.get(20)?;
+                let cast: Option<String> = row.get(19)?;
+                Ok(TVShowDetails {
+                    item,
+                    originaltitle: row.get::<_, Option<String>>(13)?.unwrap_or_default(),
+                    plot: row.get::<_, Option<String>>(14)?.unwrap_or_default(),
+                    studio: split_list(row.get(15)?),
+                    mpaa: row.get::<_, Option<String>>(16)?.unwrap_or_default(),
+                    status: row.get::<_, Option<String>>(17)?.unwrap_or_default(),
+                    premiered: row.get::<_, Option<String>>(18)?.unwrap_or_default(),
+                    cast: cast
+                        .and_then(|c| serde_json::from_str(&c).ok())
+                        .unwrap_or_default(),
+                })
+            })?;
+            Ok::<TVShowDetails, tokio_rusqlite::Error>(details)
+        })
+        .await?;
+
+    let _ = sender.send(details);
+    Ok(())
+}
+
+async fn get_episode_details(
+    conn: &Connection,
+    sender: oneshot::Sender<EpisodeDetails>,
+    episodeid: u32,
+) -> Result<()> {
+    let details = conn
+        .call(move |conn| {
+            let q = "SELECT tvepisodelist.*,
+                    showtitle, originaltitle, plot, director, writer, runtime, episodecast,
+                    streamdetails
+                FROM tvepisodelist LEFT JOIN episodedetails USING (episodeid)
+                WHERE episodeid = ?1";
+            let details = conn.query_row(q, [episodeid], |row| {
+                let cast: Option<String> = row.get(23)?;
+                let streamdetails: Option<String> = row.get(24)?;
+                Ok(EpisodeDetails {
+                    item: episode_from_row(row)?,
+                    showtitle: row.get::<_, Option<String>>(17)?.unwrap_or_default(),
+                    originaltitle: row.get::<_, Option<String>>(18)?.unwrap_or_default(),
+                    plot: row.get::<_, Option<String>>(19)?.unwrap_or_default(),
+                    director: split_list(row.get(20)?),
+                    writer: split_list(row.get(21)?),
+                    runtime: row.get::<_, Option<u32>>(22)?.unwrap_or_default(),
+                    cast: cast
+                        .and_then(|c| serde_json::from_str(&c).ok())
+                        .unwrap_or_default(),
+                    streamdetails: streamdetails
+                        .and_then(|s| serde_json::from_str(&s).ok())
+                        .unwrap_or_default(),
+                })
+            })?;
+            Ok::<EpisodeDetails, tokio_rusqlite::Error>(details)
+        })
+        .await?;
+
+    let _ = sender.send(details);
+    Ok(())
+}
+
 async fn get_server_list(
     conn: &Connection,
     sender: oneshot::Sender<Vec<KodiServer>>,
@@ -445,12 +827,30 @@ async fn get_server_list(
                         username: row.get(5)?,
                         password: row.get(6)?,
                         db_id: row.get(7)?,
+                        transport: row.get::<_, u8>(8)?.into(),
+                        mac: row.get(9)?,
+                        broadcast: row.get(10)?,
+                        locked_password: None,
                     })
                 })?
                 .collect::<Result<Vec<KodiServer>, rusqlite::Error>>();
             Ok::<_, tokio_rusqlite::Error>(servers)
         })
         .await??;
+
+    let servers = servers
+        .into_iter()
+        .map(|mut server| {
+            match keyfile::decrypt(&server.password) {
+                Ok(password) => server.password = password,
+                Err(err) => {
+                    error!(server.name, "Failed to decrypt password: {:?}", err);
+                    server.locked_password = Some(std::mem::take(&mut server.password));
+                }
+            }
+            server
+        })
+        .collect();
     let _ = sender.send(servers);
     Ok(())
 }
@@ -461,9 +861,10 @@ async fn insert_movies(conn: &Connection, movies: Vec<MovieListItem>) -> Result<
 
         let mut stmt = t.prepare(
             "INSERT OR REPLACE INTO movielist (
-                    movieid, title, genre, year, rating, playcount, file, dateadded, premiered, art
+                    movieid, title, genre, year, rating, playcount, file, dateadded, premiered, art,
+                    resume_position, resume_total, userrating, lastplayed
                 ) VALUES (
-                    ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10
+                    ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14
                 )",
         )?;
 
@@ -479,6 +880,10 @@ async fn insert_movies(conn: &Connection, movies: Vec<MovieListItem>) -> Result<
                 movie.dateadded,
                 movie.premiered,
                 movie.art.poster.unwrap_or("".to_string()),
+                movie.resume.position,
+                movie.resume.total,
+                movie.userrating,
+                movie.lastplayed,
             ])?;
         }
         drop(stmt);
@@ -492,15 +897,49 @@ async fn insert_movies(conn: &Connection, movies: Vec<MovieListItem>) -> Result<
     Ok(())
 }
 
+async fn insert_movie_details(conn: &Connection, details: MovieDetails) -> Result<()> {
+    conn.call(move |conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO moviedetails (
+                movieid, originaltitle, tagline, plot, director, writer, studio, mpaa,
+                country, runtime, moviecast, streamdetails, fanart
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13
+            )",
+            params![
+                details.item.movieid,
+                details.originaltitle,
+                details.tagline,
+                details.plot,
+                details.director.join(","),
+                details.writer.join(","),
+                details.studio.join(","),
+                details.mpaa,
+                details.country.join(","),
+                details.runtime,
+                serde_json::to_string(&details.cast).unwrap_or_default(),
+                serde_json::to_string(&details.streamdetails).unwrap_or_default(),
+                details.item.art.fanart,
+            ],
+        )?;
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await
+    .context("Failed to insert movie details DB")?;
+
+    Ok(())
+}
+
 async fn insert_tvshows(conn: &Connection, tvshows: Vec<TVShowListItem>) -> Result<()> {
     conn.call(move |conn| {
         let t = conn.transaction()?;
 
         let mut stmt = t.prepare(
             "INSERT OR REPLACE INTO tvshowlist (
-                tvshowid, title, year, season, episode, file, dateadded, genre, rating, playcount, art
+                tvshowid, title, year, season, episode, file, dateadded, genre, rating, playcount, art,
+                userrating, lastplayed
             ) VALUES (
-                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13
             )",
         )?;
 
@@ -523,6 +962,8 @@ async fn insert_tvshows(conn: &Connection, tvshows: Vec<TVShowListItem>) -> Resu
                 tv_show.rating,
                 tv_show.playcount,
                 tv_show.art.poster.unwrap_or("".to_string()),
+                tv_show.userrating,
+                tv_show.lastplayed,
             ])?;
         }
         drop(stmt);
@@ -536,6 +977,34 @@ async fn insert_tvshows(conn: &Connection, tvshows: Vec<TVShowListItem>) -> Resu
     Ok(())
 }
 
+async fn insert_tvshow_details(conn: &Connection, details: TVShowDetails) -> Result<()> {
+    conn.call(move |conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO tvshowdetails (
+                tvshowid, originaltitle, plot, studio, mpaa, status, premiered, tvshowcast, fanart
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9
+            )",
+            params![
+                details.item.tvshowid,
+                details.originaltitle,
+                details.plot,
+                details.studio.join(","),
+                details.mpaa,
+                details.status,
+                details.premiered,
+                serde_json::to_string(&details.cast).unwrap_or_default(),
+                details.item.art.fanart,
+            ],
+        )?;
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await
+    .context("Failed to insert tvshow details DB")?;
+
+    Ok(())
+}
+
 async fn insert_tvseasons(
     conn: &Connection,
     seasons: Vec<TVSeasonListItem>,
@@ -588,9 +1057,10 @@ async fn insert_tvepisodes(conn: &Connection, episodes: Vec<TVEpisodeListItem>)
         let mut stmt = t.prepare(
             "INSERT OR REPLACE INTO tvepisodelist (
                     episodeid, tvshowid, title, season, episode, file, dateadded, rating, 
-                    firstaired, playcount, art, specialsortseason, specialsortepisode
+                    firstaired, playcount, art, specialsortseason, specialsortepisode,
+                    resume_position, resume_total, userrating, lastplayed
                 ) VALUES (
-                    ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13
+                    ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17
                 )",
         )?;
 
@@ -609,6 +1079,10 @@ async fn insert_tvepisodes(conn: &Connection, episodes: Vec<TVEpisodeListItem>)
                 episode.art.thumb.unwrap_or("".to_string()),
                 episode.specialsortseason,
                 episode.specialsortepisode,
+                episode.resume.position,
+                episode.resume.total,
+                episode.userrating,
+                episode.lastplayed,
             ])?;
         }
         drop(stmt);
@@ -622,53 +1096,65 @@ async fn insert_tvepisodes(conn: &Connection, episodes: Vec<TVEpisodeListItem>)
     Ok(())
 }
 
-// ID-based sync operations
-async fn get_movie_ids(conn: &Connection, sender: oneshot::Sender<Vec<u32>>) -> Result<()> {
-    let ids = conn
-        .call(|conn| {
-            let q = "SELECT movieid FROM movielist";
-            let mut stmt = conn.prepare(q)?;
-            let ids = stmt
-                .query_map([], |row| row.get(0))?
-                .collect::<Result<Vec<u32>, rusqlite::Error>>()?;
-            Ok::<Vec<u32>, tokio_rusqlite::Error>(ids)
-        })
-        .await?;
-    let _ = sender.send(ids);
-    Ok(())
-}
+async fn insert_episode_details(conn: &Connection, details: EpisodeDetails) -> Result<()> {
+    conn.call(move |conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO episodedetails (
+                episodeid, showtitle, originaltitle, plot, director, writer, runtime,
+                episodecast, streamdetails
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9
+            )",
+            params![
+                details.item.episodeid,
+                details.showtitle,
+                details.originaltitle,
+                details.plot,
+                details.director.join(","),
+                details.writer.join(","),
+                details.runtime,
+                serde_json::to_string(&details.cast).unwrap_or_default(),
+                serde_json::to_string(&details.streamdetails).unwrap_or_default(),
+            ],
+        )?;
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await
+    .context("Failed to insert episode details DB")?;
 
-async fn get_tvshow_ids(conn: &Connection, sender: oneshot::Sender<Vec<u32>>) -> Result<()> {
-    let ids = conn
-        .call(|conn| {
-            let q = "SELECT tvshowid FROM tvshowlist";
-            let mut stmt = conn.prepare(q)?;
-            let ids = stmt
-                .query_map([], |row| row.get(0))?
-                .collect::<Result<Vec<u32>, rusqlite::Error>>()?;
-            Ok::<Vec<u32>, tokio_rusqlite::Error>(ids)
-        })
-        .await?;
-    let _ = sender.send(ids);
     Ok(())
 }
 
-async fn get_tvepisode_ids(
+// ID-based sync operations
+
+// `q` selects the id then the FINGERPRINT_PROPS columns in order
+async fn get_fingerprints(
     conn: &Connection,
-    sender: oneshot::Sender<Vec<u32>>,
-    tvshowid: u32,
+    sender: oneshot::Sender<Vec<Fingerprint>>,
+    q: &'static str,
+    tvshowid: Option<u32>,
 ) -> Result<()> {
-    let ids = conn
+    let fingerprints = conn
         .call(move |conn| {
-            let q = "SELECT episodeid FROM tvepisodelist WHERE tvshowid = ?1";
             let mut stmt = conn.prepare(q)?;
-            let ids = stmt
-                .query_map([tvshowid], |row| row.get(0))?
-                .collect::<Result<Vec<u32>, rusqlite::Error>>()?;
-            Ok::<Vec<u32>, tokio_rusqlite::Error>(ids)
+            let params = rusqlite::params_from_iter(tvshowid);
+            let fingerprints = stmt
+                .query_map(params, |row| {
+                    Ok(Fingerprint {
+                        id: row.get(0)?,
+                        playcount: row.get(1)?,
+                        lastplayed: row.get(2)?,
+                        dateadded: row.get(3)?,
+                        userrating: row.get(4)?,
+                        title: row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+                        art: row.get::<_, Option<String>>(6)?.unwrap_or_default(),
+                    })
+                })?
+                .collect::<Result<Vec<Fingerprint>, rusqlite::Error>>()?;
+            Ok::<_, tokio_rusqlite::Error>(fingerprints)
         })
         .await?;
-    let _ = sender.send(ids);
+    let _ = sender.send(fingerprints);
     Ok(())
 }
 
@@ -679,12 +1165,14 @@ async fn delete_movies_by_ids(conn: &Connection, ids: Vec<u32>) -> Result<()> {
 
     conn.call(move |conn| {
         let placeholders = (0..ids.len()).map(|_| "?").collect::<Vec<_>>().join(",");
-        let q = format!("DELETE FROM movielist WHERE movieid IN ({})", placeholders);
-        let mut stmt = conn.prepare(&q)?;
-
         let params: Vec<&dyn rusqlite::ToSql> =
             ids.iter().map(|id| id as &dyn rusqlite::ToSql).collect();
-        stmt.execute(params.as_slice())?;
+
+        for table in ["movielist", "moviedetails"] {
+            let q = format!("DELETE FROM {} WHERE movieid IN ({})", table, placeholders);
+            let mut stmt = conn.prepare(&q)?;
+            stmt.execute(params.as_slice())?;
+        }
         Ok::<_, tokio_rusqlite::Error>(())
     })
     .await?;
@@ -699,15 +1187,14 @@ async fn delete_tvshows_by_ids(conn: &Connection, ids: Vec<u32>) -> Result<()> {
 
     conn.call(move |conn| {
         let placeholders = (0..ids.len()).map(|_| "?").collect::<Vec<_>>().join(",");
-        let q = format!(
-            "DELETE FROM tvshowlist WHERE tvshowid IN ({})",
-            placeholders
-        );
-        let mut stmt = conn.prepare(&q)?;
-
         let params: Vec<&dyn rusqlite::ToSql> =
             ids.iter().map(|id| id as &dyn rusqlite::ToSql).collect();
-        stmt.execute(params.as_slice())?;
+
+        for table in ["tvshowlist", "tvshowdetails"] {
+            let q = format!("DELETE FROM {} WHERE tvshowid IN ({})", table, placeholders);
+            let mut stmt = conn.prepare(&q)?;
+            stmt.execute(params.as_slice())?;
+        }
         Ok::<_, tokio_rusqlite::Error>(())
     })
     .await?;
@@ -734,6 +1221,14 @@ async fn delete_tvepisodes_by_ids(conn: &Connection, ids: Vec<u32>, tvshowid: u3
         params.extend(id_params);
 
         stmt.execute(params.as_slice())?;
+
+        // episodedetails has no tvshowid, the episodeids are unique anyway
+        let q = format!(
+            "DELETE FROM episodedetails WHERE episodeid IN ({})",
+            placeholders
+        );
+        let mut stmt = conn.prepare(&q)?;
+        stmt.execute(&params[1..])?;
         Ok::<_, tokio_rusqlite::Error>(())
     })
     .await?;
@@ -741,47 +1236,786 @@ async fn delete_tvepisodes_by_ids(conn: &Connection, ids: Vec<u32>, tvshowid: u3
     Ok(())
 }
 
-async fn create_tables(conn: &Connection) -> Result<()> {
-    conn.call(|conn| {conn.execute(
-        "CREATE TABLE IF NOT EXISTS 'servers' (
-            id INTEGER PRIMARY KEY,
-            name TEXT NOT NULL,
-            ip TEXT NOT NULL,
-            webserver_port INTEGER NOT NULL,
-            websocket_port INTEGER NOT NULL,
-            username TEXT,
-            password TEXT,
-            db_id INTEGER
-        )",
-        [],
-    )?;
-    Ok::<_, tokio_rusqlite::Error>}(())
-    ).await?;
-
-    // dbg!(servers.err());
-
-    // Not used yet:
-    // Will eventually be used for selected server and others.
-    // sort options (movie/files/etc)
-    // let settings = conn.call(|conn| {conn.execute(
-    //     "CREATE TABLE IF NOT EXISTS 'settings' (
-    //         setting TEXT PRIMARY KEY ON CONFLICT REPLACE,
-    //         value TEXT,
-    //     )",
-    //     [],
-    // )?;
-    // Ok::<_, rusqlite::Error>}(())
-    // ).await;
-
-    // dbg!(settings.err());
-
-    // TODO - these table names should include db_id ie. movielist0 etc.
-    //        or I can make db0.sqlite etc separate from settings/server db
-
-    conn.call(|conn| {conn.execute(
-        "CREATE TABLE IF NOT EXISTS 'movielist' (
-            movieid INTEGER PRIMARY KEY ON CONFLICT REPLACE,
-            title TEXT,
+async fn delete_library_item(conn: &Connection, item: LibraryItem) -> Result<()> {
+    conn.call(move |conn| {
+        let t = conn.transaction()?;
+        match item {
+            LibraryItem::Movie(movieid) => {
+                for table in ["movielist", "moviedetails", "continuewatching"] {
+                    let q = format!("DELETE FROM {} WHERE movieid = ?1", table);
+                    t.execute(&q, [movieid])?;
+                }
+            }
+            LibraryItem::TVShow(tvshowid) => {
+                t.execute(
+                    "DELETE FROM episodedetails WHERE episodeid IN
+                        (SELECT episodeid FROM tvepisodelist WHERE tvshowid = ?1)",
+                    [tvshowid],
+                )?;
+                t.execute(
+                    "DELETE FROM continuewatching WHERE episodeid IN
+                        (SELECT episodeid FROM tvepisodelist WHERE tvshowid = ?1)",
+                    [tvshowid],
+                )?;
+                for table in [
+                    "tvshowlist",
+                    "tvshowdetails",
+                    "tvseasonlist",
+                    "tvepisodelist",
+                ] {
+                    let q = format!("DELETE FROM {} WHERE tvshowid = ?1", table);
+                    t.execute(&q, [tvshowid])?;
+                }
+            }
+            LibraryItem::Episode(episodeid) => {
+                for table in ["tvepisodelist", "episodedetails", "continuewatching"] {
+                    let q = format!("DELETE FROM {} WHERE episodeid = ?1", table);
+                    t.execute(&q, [episodeid])?;
+                }
+            }
+        }
+        t.commit()?;
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await
+    .context("Failed to delete library item DB")?;
+
+    Ok(())
+}
+
+fn artist_from_row(row: &rusqlite::Row) -> rusqlite::Result<ArtistListItem> {
+    Ok(ArtistListItem {
+        artistid: row.get(0)?,
+        artist: row.get(1)?,
+        genre: split_list(row.get(2)?),
+        thumbnail: row.get(3)?,
+        dateadded: row.get(4)?,
+    })
+}
+
+// artistid isn't a column, it comes from the albumartist GROUP_CONCAT
+fn album_from_row(row: &rusqlite::Row) -> rusqlite::Result<AlbumListItem> {
+    Ok(AlbumListItem {
+        albumid: row.get(0)?,
+        title: row.get(1)?,
+        displayartist: row.get(2)?,
+        year: row.get(3)?,
+        genre: split_list(row.get(4)?),
+        thumbnail: row.get(5)?,
+        playcount: row.get(6)?,
+        dateadded: row.get(7)?,
+        artistid: split_list(row.get(8)?)
+            .iter()
+            .filter_map(|id| id.parse().ok())
+            .collect(),
+    })
+}
+
+fn song_from_row(row: &rusqlite::Row) -> rusqlite::Result<SongListItem> {
+    Ok(SongListItem {
+        songid: row.get(0)?,
+        albumid: row.get(1)?,
+        title: row.get(2)?,
+        displayartist: row.get(3)?,
+        track: row.get(4)?,
+        disc: row.get(5)?,
+        duration: row.get(6)?,
+        file: row.get(7)?,
+        thumbnail: row.get(8)?,
+        playcount: row.get(9)?,
+    })
+}
+
+const ALBUM_SELECT: &str = "SELECT albumlist.*, GROUP_CONCAT(albumartist.artistid)
+    FROM albumlist JOIN albumartist USING (albumid)";
+
+async fn get_artist_list(
+    conn: &Connection,
+    sender: oneshot::Sender<Vec<Box<dyn IntoListData + Send>>>,
+) -> Result<()> {
+    let artists = conn
+        .call(|conn| {
+            let q = "SELECT * FROM artistlist ORDER BY artist COLLATE NOCASE ASC";
+            let mut stmt = conn.prepare(q)?;
+            let artists = stmt
+                .query_map([], |row| Ok(Box::new(artist_from_row(row)?) as _))?
+                .collect::<Result<Vec<Box<dyn IntoListData + Send>>, rusqlite::Error>>()?;
+            Ok::<_, tokio_rusqlite::Error>(artists)
+        })
+        .await?;
+
+    let _ = sender.send(artists);
+    Ok(())
+}
+
+async fn get_album_list(
+    conn: &Connection,
+    sender: oneshot::Sender<Vec<Box<dyn IntoListData + Send>>>,
+    artistid: u32,
+) -> Result<()> {
+    let albums = conn
+        .call(move |conn| {
+            let q = format!(
+                "{ALBUM_SELECT}
+                WHERE albumid IN (SELECT albumid FROM albumartist WHERE artistid = ?1)
+                GROUP BY albumid
+                ORDER BY year ASC, title COLLATE NOCASE ASC"
+            );
+            let mut stmt = conn.prepare(&q)?;
+            let albums = stmt
+                .query_map([artistid], |row| Ok(Box::new(album_from_row(row)?) as _))?
+                .collect::<Result<Vec<Box<dyn IntoListData + Send>>, rusqlite::Error>>()?;
+            Ok::<_, tokio_rusqlite::Error>(albums)
+        })
+        .await?;
+
+    let _ = sender.send(albums);
+    Ok(())
+}
+
+async fn get_song_list(
+    conn: &Connection,
+    sender: oneshot::Sender<Vec<Box<dyn IntoListData + Send>>>,
+    albumid: u32,
+) -> Result<()> {
+    let songs = conn
+        .call(move |conn| {
+            let q = "SELECT * FROM songlist WHERE albumid = ?1 ORDER BY disc ASC, track ASC";
+            let mut stmt = conn.prepare(q)?;
+            let songs = stmt
+                .query_map([albumid], |row| Ok(Box::new(song_from_row(row)?) as _))?
+                .collect::<Result<Vec<Box<dyn IntoListData + Send>>, rusqlite::Error>>()?;
+            Ok::<_, tokio_rusqlite::Error>(songs)
+        })
+        .await?;
+
+    let _ = sender.send(songs);
+    Ok(())
+}
+
+async fn get_artist_item(
+    conn: &Connection,
+    sender: oneshot::Sender<ArtistListItem>,
+    artistid: u32,
+) -> Result<()> {
+    let artist = conn
+        .call(move |conn| {
+            let q = "SELECT * FROM artistlist WHERE artistid = ?1";
+            let artist = conn.query_row(q, [artistid], artist_from_row)?;
+            Ok::<_, tokio_rusqlite::Error>(artist)
+        })
+        .await?;
+
+    let _ = sender.send(artist);
+    Ok(())
+}
+
+async fn get_album_item(
+    conn: &Connection,
+    sender: oneshot::Sender<AlbumListItem>,
+    albumid: u32,
+) -> Result<()> {
+    let album = conn
+        .call(move |conn| {
+            let q = format!("{ALBUM_SELECT} WHERE albumid = ?1 GROUP BY albumid");
+            let album = conn.query_row(&q, [albumid], album_from_row)?;
+            Ok::<_, tokio_rusqlite::Error>(album)
+        })
+        .await?;
+
+    let _ = sender.send(album);
+    Ok(())
+}
+
+async fn insert_artists(conn: &Connection, artists: Vec<ArtistListItem>) -> Result<()> {
+    conn.call(move |conn| {
+        let t = conn.transaction()?;
+
+        let mut stmt = t.prepare(
+            "INSERT OR REPLACE INTO artistlist (
+                artistid, artist, genre, thumbnail, dateadded
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5
+            )",
+        )?;
+
+        for artist in artists {
+            stmt.execute(params![
+                artist.artistid,
+                artist.artist,
+                artist.genre.join(","),
+                artist.thumbnail,
+                artist.dateadded,
+            ])?;
+        }
+        drop(stmt);
+
+        t.commit()?;
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await
+    .context("Failed to insert artists DB")?;
+
+    Ok(())
+}
+
+async fn insert_albums(conn: &Connection, albums: Vec<AlbumListItem>, artistid: u32) -> Result<()> {
+    conn.call(move |conn| {
+        let t = conn.transaction()?;
+
+        let mut stmt = t.prepare(
+            "INSERT OR REPLACE INTO albumlist (
+                albumid, title, displayartist, year, genre, thumbnail, playcount, dateadded
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8
+            )",
+        )?;
+        let mut link = t.prepare("INSERT OR REPLACE INTO albumartist VALUES (?1, ?2)")?;
+
+        for album in albums {
+            stmt.execute(params![
+                album.albumid,
+                album.title,
+                album.displayartist,
+                album.year,
+                album.genre.join(","),
+                album.thumbnail,
+                album.playcount,
+                album.dateadded,
+            ])?;
+            link.execute([album.albumid, artistid])?;
+            for id in album.artistid {
+                link.execute([album.albumid, id])?;
+            }
+        }
+        drop(stmt);
+        drop(link);
+
+        t.commit()?;
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await
+    .context("Failed to insert albums DB")?;
+
+    Ok(())
+}
+
+async fn insert_songs(conn: &Connection, songs: Vec<SongListItem>) -> Result<()> {
+    conn.call(move |conn| {
+        let t = conn.transaction()?;
+
+        let mut stmt = t.prepare(
+            "INSERT OR REPLACE INTO songlist (
+                songid, albumid, title, displayartist, track, disc, duration, file,
+                thumbnail, playcount
+            ) VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10
+            )",
+        )?;
+
+        for song in songs {
+            stmt.execute(params![
+                song.songid,
+                song.albumid,
+                song.title,
+                song.displayartist,
+                song.track,
+                song.disc,
+                song.duration,
+                song.file,
+                song.thumbnail,
+                song.playcount,
+            ])?;
+        }
+        drop(stmt);
+
+        t.commit()?;
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await
+    .context("Failed to insert songs DB")?;
+
+    Ok(())
+}
+
+async fn get_artist_ids(conn: &Connection, sender: oneshot::Sender<Vec<u32>>) -> Result<()> {
+    let ids = conn
+        .call(|conn| {
+            let q = "SELECT artistid FROM artistlist";
+            let mut stmt = conn.prepare(q)?;
+            let ids = stmt
+                .query_map([], |row| row.get(0))?
+                .collect::<Result<Vec<u32>, rusqlite::Error>>()?;
+            Ok::<Vec<u32>, tokio_rusqlite::Error>(ids)
+        })
+        .await?;
+    let _ = sender.send(ids);
+    Ok(())
+}
+
+async fn get_album_ids(
+    conn: &Connection,
+    sender: oneshot::Sender<Vec<u32>>,
+    artistid: u32,
+) -> Result<()> {
+    let ids = conn
+        .call(move |conn| {
+            let q = "SELECT albumid FROM albumartist WHERE artistid = ?1";
+            let mut stmt = conn.prepare(q)?;
+            let ids = stmt
+                .query_map([artistid], |row| row.get(0))?
+                .collect::<Result<Vec<u32>, rusqlite::Error>>()?;
+            Ok::<Vec<u32>, tokio_rusqlite::Error>(ids)
+        })
+        .await?;
+    let _ = sender.send(ids);
+    Ok(())
+}
+
+async fn get_song_ids(
+    conn: &Connection,
+    sender: oneshot::Sender<Vec<u32>>,
+    albumid: u32,
+) -> Result<()> {
+    let ids = conn
+        .call(move |conn| {
+            let q = "SELECT songid FROM songlist WHERE albumid = ?1";
+            let mut stmt = conn.prepare(q)?;
+            let ids = stmt
+                .query_map([albumid], |row| row.get(0))?
+                .collect::<Result<Vec<u32>, rusqlite::Error>>()?;
+            Ok::<Vec<u32>, tokio_rusqlite::Error>(ids)
+        })
+        .await?;
+    let _ = sender.send(ids);
+    Ok(())
+}
+
+// Albums/songs only exist through an albumartist link,
+// anything that lost its last link goes too.
+fn delete_orphaned_albums(conn: &rusqlite::Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM albumlist WHERE albumid NOT IN (SELECT albumid FROM albumartist)",
+        [],
+    )?;
+    conn.execute(
+        "DELETE FROM songlist WHERE albumid NOT IN (SELECT albumid FROM albumlist)",
+        [],
+    )?;
+    Ok(())
+}
+
+async fn delete_artists_by_ids(conn: &Connection, ids: Vec<u32>) -> Result<()> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    conn.call(move |conn| {
+        let placeholders = (0..ids.len()).map(|_| "?").collect::<Vec<_>>().join(",");
+        let params: Vec<&dyn rusqlite::ToSql> =
+            ids.iter().map(|id| id as &dyn rusqlite::ToSql).collect();
+
+        let t = conn.transaction()?;
+        for table in ["artistlist", "albumartist"] {
+            let q = format!("DELETE FROM {} WHERE artistid IN ({})", table, placeholders);
+            t.execute(&q, params.as_slice())?;
+        }
+        delete_orphaned_albums(&t)?;
+        t.commit()?;
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await?;
+
+    Ok(())
+}
+
+async fn delete_albums_by_ids(conn: &Connection, ids: Vec<u32>, artistid: u32) -> Result<()> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    conn.call(move |conn| {
+        let placeholders = (0..ids.len()).map(|_| "?").collect::<Vec<_>>().join(",");
+        let q = format!(
+            "DELETE FROM albumartist WHERE artistid = ? AND albumid IN ({})",
+            placeholders
+        );
+
+        let mut params: Vec<&dyn rusqlite::ToSql> = vec![&artistid];
+        params.extend(ids.iter().map(|id| id as &dyn rusqlite::ToSql));
+
+        let t = conn.transaction()?;
+        t.execute(&q, params.as_slice())?;
+        delete_orphaned_albums(&t)?;
+        t.commit()?;
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await?;
+
+    Ok(())
+}
+
+async fn delete_songs_by_ids(conn: &Connection, ids: Vec<u32>, albumid: u32) -> Result<()> {
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    conn.call(move |conn| {
+        let placeholders = (0..ids.len()).map(|_| "?").collect::<Vec<_>>().join(",");
+        let q = format!(
+            "DELETE FROM songlist WHERE albumid = ? AND songid IN ({})",
+            placeholders
+        );
+
+        let mut params: Vec<&dyn rusqlite::ToSql> = vec![&albumid];
+        params.extend(ids.iter().map(|id| id as &dyn rusqlite::ToSql));
+
+        conn.execute(&q, params.as_slice())?;
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await?;
+
+    Ok(())
+}
+
+async fn get_continue_watching(
+    conn: &Connection,
+    sender: oneshot::Sender<Vec<Box<dyn IntoListData + Send>>>,
+) -> Result<()> {
+    let items = conn
+        .call(|conn| {
+            let q = "SELECT movielist.* FROM continuewatching
+                JOIN movielist USING (movieid) ORDER BY position";
+            let mut stmt = conn.prepare(q)?;
+            let mut items = stmt
+                .query_map([], |row| Ok(Box::new(movie_from_row(row)?) as _))?
+                .collect::<Result<Vec<Box<dyn IntoListData + Send>>, rusqlite::Error>>()?;
+
+            let q = "SELECT tvepisodelist.*, showtitle FROM continuewatching
+                JOIN tvepisodelist USING (episodeid) ORDER BY position";
+            let mut stmt = conn.prepare(q)?;
+            let episodes = stmt.query_map([], |row| {
+                Ok(Box::new(ContinueEpisode {
+                    item: episode_from_row(row)?,
+                    showtitle: row.get::<_, Option<String>>(17)?.unwrap_or_default(),
+                }) as _)
+            })?;
+            for episode in episodes {
+                items.push(episode?);
+            }
+
+            Ok::<_, tokio_rusqlite::Error>(items)
+        })
+        .await?;
+
+    let _ = sender.send(items);
+    Ok(())
+}
+
+async fn set_continue_watching(
+    conn: &Connection,
+    movieids: Vec<u32>,
+    episodes: Vec<(u32, String)>,
+) -> Result<()> {
+    conn.call(move |conn| {
+        let t = conn.transaction()?;
+        t.execute("DELETE FROM continuewatching", [])?;
+
+        let mut stmt = t.prepare(
+            "INSERT INTO continuewatching (movieid, episodeid, showtitle) VALUES (?1, ?2, ?3)",
+        )?;
+        for movieid in movieids {
+            stmt.execute(params![movieid, None::<u32>, None::<String>])?;
+        }
+        for (episodeid, showtitle) in episodes {
+            stmt.execute(params![None::<u32>, episodeid, showtitle])?;
+        }
+        drop(stmt);
+
+        // Anything kodi didn't list as in progress has been finished (or reset) since
+        t.execute(
+            "UPDATE movielist SET resume_position = 0 WHERE resume_position > 0
+                AND movieid NOT IN (SELECT movieid FROM continuewatching WHERE movieid IS NOT NULL)",
+            [],
+        )?;
+        t.execute(
+            "UPDATE tvepisodelist SET resume_position = 0 WHERE resume_position > 0
+                AND episodeid NOT IN (SELECT episodeid FROM continuewatching WHERE episodeid IS NOT NULL)",
+            [],
+        )?;
+
+        t.commit()?;
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await
+    .context("Failed to set continue watching DB")?;
+
+    Ok(())
+}
+
+async fn apply_edit(conn: &Connection, edit: LibraryEdit) -> Result<()> {
+    conn.call(move |conn| {
+        let t = conn.transaction()?;
+        let (table, id_field) = match edit.item {
+            LibraryItem::Movie(_) => ("movielist", "movieid"),
+            LibraryItem::TVShow(_) => ("tvshowlist", "tvshowid"),
+            LibraryItem::Episode(_) => ("tvepisodelist", "episodeid"),
+        };
+        match edit.change {
+            EditChange::PlayCount(count) => {
+                let q = format!(
+                    "UPDATE {} SET playcount = ?1 WHERE {} = ?2",
+                    table, id_field
+                );
+                t.execute(&q, params![count, edit.item.id()])?;
+                // Marking a show marks all of its episodes
+                if let LibraryItem::TVShow(tvshowid) = edit.item {
+                    t.execute(
+                        "UPDATE tvepisodelist SET playcount = ?1 WHERE tvshowid = ?2",
+                        params![count, tvshowid],
+                    )?;
+                }
+                if count == 0 && table != "tvshowlist" {
+                    let q = format!(
+                        "UPDATE {} SET resume_position = 0 WHERE {} = ?1",
+                        table, id_field
+                    );
+                    t.execute(&q, [edit.item.id()])?;
+                }
+            }
+            EditChange::UserRating(rating) => {
+                let q = format!(
+                    "UPDATE {} SET userrating = ?1 WHERE {} = ?2",
+                    table, id_field
+                );
+                t.execute(&q, params![rating, edit.item.id()])?;
+            }
+        }
+        t.commit()?;
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await
+    .context("Failed to apply edit DB")?;
+
+    Ok(())
+}
+
+async fn add_pending_edit(conn: &Connection, edit: LibraryEdit) -> Result<()> {
+    let edit = serde_json::to_string(&edit)?;
+    conn.call(move |conn| {
+        conn.execute("INSERT INTO pendingedits (edit) VALUES (?1)", [edit])?;
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await
+    .context("Failed to add pending edit DB")?;
+
+    Ok(())
+}
+
+// Oldest first. Unreadable ones are skipped, they'd never replay anyway.
+async fn get_pending_edits(
+    conn: &Connection,
+    sender: oneshot::Sender<Vec<(i64, LibraryEdit)>>,
+) -> Result<()> {
+    let edits = conn
+        .call(|conn| {
+            let mut stmt = conn.prepare("SELECT id, edit FROM pendingedits ORDER BY id")?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get::<_, String>(1)?)))?
+                .collect::<Result<Vec<(i64, String)>, rusqlite::Error>>()?;
+            Ok::<_, tokio_rusqlite::Error>(rows)
+        })
+        .await?;
+
+    let edits = edits
+        .into_iter()
+        .filter_map(|(id, edit)| Some((id, serde_json::from_str(&edit).ok()?)))
+        .collect();
+    let _ = sender.send(edits);
+    Ok(())
+}
+
+async fn delete_pending_edit(conn: &Connection, id: i64) -> Result<()> {
+    conn.call(move |conn| {
+        conn.execute("DELETE FROM pendingedits WHERE id = ?1", [id])?;
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await
+    .context("Failed to delete pending edit DB")?;
+
+    Ok(())
+}
+
+// Everything in the library db that came from kodi, see LIBRARY_VERSION.
+// pendingedits isn't here, it's the user's own edits waiting to be sent.
+const LIBRARY_TABLES: [&str; 12] = [
+    "movielist",
+    "moviedetails",
+    "tvshowlist",
+    "tvshowdetails",
+    "tvseasonlist",
+    "tvepisodelist",
+    "episodedetails",
+    "artistlist",
+    "albumlist",
+    "albumartist",
+    "songlist",
+    "continuewatching",
+];
+
+// Bump this whenever a library table changes. Older library dbs are dropped
+// and rebuilt, everything in them came from kodi and gets synced again.
+const LIBRARY_VERSION: i32 = 3;
+
+async fn clear_library(conn: &Connection) -> Result<()> {
+    conn.call(|conn| {
+        let t = conn.transaction()?;
+        for table in LIBRARY_TABLES {
+            t.execute(&format!("DELETE FROM {}", table), [])?;
+        }
+        t.commit()?;
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await
+    .context("Failed to clear library DB")?;
+
+    Ok(())
+}
+
+async fn create_tables(conn: &Connection) -> Result<()> {
+    conn.call(|conn| {conn.execute(
+        "CREATE TABLE IF NOT EXISTS 'servers' (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            ip TEXT NOT NULL,
+            webserver_port INTEGER NOT NULL,
+            websocket_port INTEGER NOT NULL,
+            username TEXT,
+            password TEXT,
+            db_id INTEGER,
+            transport INTEGER NOT NULL DEFAULT 0,
+            mac TEXT NOT NULL DEFAULT '',
+            broadcast TEXT NOT NULL DEFAULT ''
+        )",
+        [],
+    )?;
+    Ok::<_, tokio_rusqlite::Error>}(())
+    ).await?;
+
+    // Servers saved before these settings existed, in the order they were added
+    for (column, definition) in [
+        ("transport", "INTEGER NOT NULL DEFAULT 0"),
+        ("mac", "TEXT NOT NULL DEFAULT ''"),
+        ("broadcast", "TEXT NOT NULL DEFAULT ''"),
+    ] {
+        add_missing_column(conn, "servers", column, definition).await?;
+    }
+
+    conn.call(|conn| {conn.execute(
+        "CREATE TABLE IF NOT EXISTS 'settings' (
+            setting TEXT PRIMARY KEY ON CONFLICT REPLACE,
+            value TEXT
+        )",
+        [],
+    )?;
+    Ok::<_, tokio_rusqlite::Error>}(())
+    ).await?;
+
+    // The library used to live in here, it's per server now (see connect_library).
+    // Nothing in it is worth moving, the new library dbs have a different schema
+    // and sync again from kodi anyway.
+    conn.call(|conn| {
+        let t = conn.transaction()?;
+        for table in LIBRARY_TABLES {
+            t.execute(&format!("DROP TABLE IF EXISTS {}", table), [])?;
+        }
+        t.commit()?;
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await
+    .context("Failed to drop the old library tables")?;
+
+    encrypt_plaintext_passwords(conn).await?;
+
+    Ok(())
+}
+
+async fn add_missing_column(
+    conn: &Connection,
+    table: &'static str,
+    column: &'static str,
+    definition: &'static str,
+) -> Result<()> {
+    conn.call(move |conn| {
+        let exists = conn
+            .prepare(&format!("SELECT {} FROM {} LIMIT 0", column, table))
+            .is_ok();
+        if !exists {
+            conn.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+                [],
+            )?;
+        }
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await
+    .with_context(|| format!("Failed to add {}.{}", table, column))?;
+    Ok(())
+}
+
+// Passwords used to be stored as plaintext
+async fn encrypt_plaintext_passwords(conn: &Connection) -> Result<()> {
+    conn.call(|conn| {
+        let t = conn.transaction()?;
+        {
+            let mut stmt = t.prepare("SELECT id, password FROM servers WHERE password != ''")?;
+            let plaintext = stmt
+                .query_map([], |row| {
+                    Ok((row.get::<_, u8>(0)?, row.get::<_, String>(1)?))
+                })?
+                .collect::<Result<Vec<_>, rusqlite::Error>>()?
+                .into_iter()
+                .filter(|(_, password)| !keyfile::is_encrypted(password));
+            for (id, password) in plaintext {
+                match keyfile::encrypt(&password) {
+                    Ok(password) => {
+                        t.execute(
+                            "UPDATE servers SET password = ?1 WHERE id = ?2",
+                            params![password, id],
+                        )?;
+                    }
+                    Err(err) => error!(id, "Failed to encrypt stored password: {:?}", err),
+                }
+            }
+        }
+        t.commit()?;
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await
+    .context("Failed to encrypt stored passwords")?;
+    Ok(())
+}
+
+async fn create_library_tables(conn: &Connection) -> Result<()> {
+    conn.call(|conn| {
+        let version: i32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version != LIBRARY_VERSION {
+            debug!(version, LIBRARY_VERSION, "Rebuilding library db");
+            let t = conn.transaction()?;
+            for table in LIBRARY_TABLES {
+                t.execute(&format!("DROP TABLE IF EXISTS {}", table), [])?;
+            }
+            t.pragma_update(None, "user_version", LIBRARY_VERSION)?;
+            t.commit()?;
+        }
+        Ok::<_, tokio_rusqlite::Error>(())
+    })
+    .await?;
+
+    conn.call(|conn| {conn.execute(
+        "CREATE TABLE IF NOT EXISTS 'movielist' (
+            movieid INTEGER PRIMARY KEY ON CONFLICT REPLACE,
+            title TEXT,
             genre TEXT,
             year INTEGER,
             rating REAL,
@@ -789,7 +2023,11 @@ async fn create_tables(conn: &Connection) -> Result<()> {
             file TEXT,
             dateadded TEXT,            
             premiered TEXT,
-            art TEXT
+            art TEXT,
+            resume_position REAL,
+            resume_total REAL,
+            userrating INTEGER,
+            lastplayed TEXT
         )",
         [],
     )?;
@@ -799,7 +2037,28 @@ async fn create_tables(conn: &Connection) -> Result<()> {
     // dbg!(movielist.err());
 
     // Due to websocket response size limits I have to keep the movielist to minimal fields
-    // I can create a moviedetails db with the same `movieid` then use JOIN
+    // so the rest goes in moviedetails with the same `movieid` then use JOIN
+    // cast/streamdetails are stored as json.
+    conn.call(|conn| {conn.execute(
+        "CREATE TABLE IF NOT EXISTS 'moviedetails' (
+            movieid INTEGER PRIMARY KEY ON CONFLICT REPLACE,
+            originaltitle TEXT,
+            tagline TEXT,
+            plot TEXT,
+            director TEXT,
+            writer TEXT,
+            studio TEXT,
+            mpaa TEXT,
+            country TEXT,
+            runtime INTEGER,
+            moviecast TEXT,
+            streamdetails TEXT,
+            fanart TEXT
+        )",
+        [],
+    )?;
+    Ok::<_, tokio_rusqlite::Error>}(())
+    ).await?;
 
     conn.call(|conn| {conn.execute(
         "CREATE TABLE IF NOT EXISTS 'tvshowlist' (
@@ -813,7 +2072,9 @@ async fn create_tables(conn: &Connection) -> Result<()> {
             genre TEXT,
             rating REAL,
             playcount NUMBER,
-            art TEXT
+            art TEXT,
+            userrating INTEGER,
+            lastplayed TEXT
         )",
         [],
     )?;
@@ -822,6 +2083,24 @@ async fn create_tables(conn: &Connection) -> Result<()> {
 
     // dbg!(tvshowlist.err());
 
+    // Same as moviedetails
+    conn.call(|conn| {conn.execute(
+        "CREATE TABLE IF NOT EXISTS 'tvshowdetails' (
+            tvshowid INTEGER PRIMARY KEY ON CONFLICT REPLACE,
+            originaltitle TEXT,
+            plot TEXT,
+            studio TEXT,
+            mpaa TEXT,
+            status TEXT,
+            premiered TEXT,
+            tvshowcast TEXT,
+            fanart TEXT
+        )",
+        [],
+    )?;
+    Ok::<_, tokio_rusqlite::Error>}(())
+    ).await?;
+
     conn.call(|conn| {conn.execute(
         "CREATE TABLE IF NOT EXISTS 'tvseasonlist' (
             seasonid INTEGER PRIMARY KEY ON CONFLICT REPLACE,
@@ -851,7 +2130,11 @@ async fn create_tables(conn: &Connection) -> Result<()> {
             playcount NUMBER,
             art TEXT,
             specialsortseason INTEGER,
-            specialsortepisode INTEGER
+            specialsortepisode INTEGER,
+            resume_position REAL,
+            resume_total REAL,
+            userrating INTEGER,
+            lastplayed TEXT
         )",
         [],
     )?;
@@ -860,5 +2143,106 @@ async fn create_tables(conn: &Connection) -> Result<()> {
 
     // dbg!(tvepisodelist.err());
 
+    conn.call(|conn| {conn.execute(
+        "CREATE TABLE IF NOT EXISTS 'artistlist' (
+            artistid INTEGER PRIMARY KEY ON CONFLICT REPLACE,
+            artist TEXT,
+            genre TEXT,
+            thumbnail TEXT,
+            dateadded TEXT
+        )",
+        [],
+    )?;
+    Ok::<_, tokio_rusqlite::Error>}(())
+    ).await?;
+
+    conn.call(|conn| {conn.execute(
+        "CREATE TABLE IF NOT EXISTS 'albumlist' (
+            albumid INTEGER PRIMARY KEY ON CONFLICT REPLACE,
+            title TEXT,
+            displayartist TEXT,
+            year INTEGER,
+            genre TEXT,
+            thumbnail TEXT,
+            playcount NUMBER,
+            dateadded TEXT
+        )",
+        [],
+    )?;
+    Ok::<_, tokio_rusqlite::Error>}(())
+    ).await?;
+
+    // An album can have several artists (and show up under each of them)
+    conn.call(|conn| {conn.execute(
+        "CREATE TABLE IF NOT EXISTS 'albumartist' (
+            albumid INTEGER,
+            artistid INTEGER,
+            PRIMARY KEY (albumid, artistid) ON CONFLICT REPLACE
+        )",
+        [],
+    )?;
+    Ok::<_, tokio_rusqlite::Error>}(())
+    ).await?;
+
+    conn.call(|conn| {conn.execute(
+        "CREATE TABLE IF NOT EXISTS 'songlist' (
+            songid INTEGER PRIMARY KEY ON CONFLICT REPLACE,
+            albumid INTEGER,
+            title TEXT,
+            displayartist TEXT,
+            track INTEGER,
+            disc INTEGER,
+            duration INTEGER,
+            file TEXT,
+            thumbnail TEXT,
+            playcount NUMBER
+        )",
+        [],
+    )?;
+    Ok::<_, tokio_rusqlite::Error>}(())
+    ).await?;
+
+    conn.call(|conn| {conn.execute(
+        "CREATE TABLE IF NOT EXISTS 'episodedetails' (
+            episodeid INTEGER PRIMARY KEY ON CONFLICT REPLACE,
+            showtitle TEXT,
+            originaltitle TEXT,
+            plot TEXT,
+            director TEXT,
+            writer TEXT,
+            runtime INTEGER,
+            episodecast TEXT,
+            streamdetails TEXT
+        )",
+        [],
+    )?;
+    Ok::<_, tokio_rusqlite::Error>}(())
+    ).await?;
+
+    // The items themselves are in movielist/tvepisodelist
+    conn.call(|conn| {conn.execute(
+        "CREATE TABLE IF NOT EXISTS 'continuewatching' (
+            position INTEGER PRIMARY KEY,
+            movieid INTEGER,
+            episodeid INTEGER,
+            showtitle TEXT
+        )",
+        [],
+    )?;
+    Ok::<_, tokio_rusqlite::Error>}(())
+    ).await?;
+
+    // Edits made while offline, replayed in id order on reconnect.
+    // edit is a LibraryEdit as json.
+    conn.call(|conn| {conn.execute(
+        "CREATE TABLE IF NOT EXISTS 'pendingedits' (
+            id INTEGER PRIMARY KEY,
+            edit TEXT
+        )",
+        [],
+    )?;
+    Ok::<_, tokio_rusqlite::Error>}(())
+    ).await?;
+
     Ok(())
 }
@@ -0,0 +1,351 @@
+// Finds kodi instances on the LAN over mDNS (Zeroconf).
+//
+// Kodi advertises `_xbmc-jsonrpc._tcp` for its tcp/websocket JSON-RPC port
+// and `_xbmc-jsonrpc-h._tcp` for the web server. This sends one query for
+// both from an ephemeral port (a "legacy unicast" query, RFC 6762 6.7) so
+// responders answer straight back to us, then collects answers for a while.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use anyhow::{Context, Result, anyhow};
+use tokio::net::UdpSocket;
+use tokio::time::{Duration, Instant, timeout_at};
+use tracing::debug;
+
+pub const MDNS_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), 5353);
+pub const BROWSE_TIME: Duration = Duration::from_secs(3);
+
+const WS_SERVICE: &str = "_xbmc-jsonrpc._tcp.local";
+const HTTP_SERVICE: &str = "_xbmc-jsonrpc-h._tcp.local";
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discovered {
+    pub name: String,
+    pub ip: IpAddr,
+    pub websocket_port: Option<u16>,
+    pub webserver_port: Option<u16>,
+}
+
+pub async fn discover() -> Result<Vec<Discovered>, String> {
+    discover_on(MDNS_ADDR, BROWSE_TIME)
+        .await
+        .map_err(|err| format!("{:#}", err))
+}
+
+/// Browses for `browse_time`, `target` is only not MDNS_ADDR in tests
+pub async fn discover_on(target: SocketAddr, browse_time: Duration) -> Result<Vec<Discovered>> {
+    let bind: SocketAddr = match target {
+        SocketAddr::V4(_) => "0.0.0.0:0",
+        SocketAddr::V6(_) => "[::]:0",
+    }
+    .parse()
+    .expect("Valid address");
+    let socket = UdpSocket::bind(bind)
+        .await
+        .context("Failed to bind mDNS socket")?;
+    socket
+        .send_to(&query(), target)
+        .await
+        .context("Failed to send mDNS query")?;
+
+    let mut records = Records::default();
+    let deadline = Instant::now() + browse_time;
+    let mut buf = [0; 9000];
+    while let Ok(received) = timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        let (len, from) = received.context("Failed to receive mDNS response")?;
+        if let Err(err) = records.parse(&buf[..len]) {
+            debug!(?from, "Ignoring bad mDNS packet: {:?}", err);
+        }
+    }
+    Ok(records.instances())
+}
+
+fn query() -> Vec<u8> {
+    // id 0, standard query, 2 questions
+    let mut packet = vec![0, 0, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0];
+    for service in [WS_SERVICE, HTTP_SERVICE] {
+        write_name(&mut packet, service);
+        packet.extend(TYPE_PTR.to_be_bytes());
+        packet.extend(CLASS_IN.to_be_bytes());
+    }
+    packet
+}
+
+fn write_name(packet: &mut Vec<u8>, name: &str) {
+    for label in name.split('.') {
+        packet.push(label.len() as u8);
+        packet.extend(label.as_bytes());
+    }
+    packet.push(0);
+}
+
+// Everything useful seen so far, responses can be spread over packets
+#[derive(Debug, Default)]
+struct Records {
+    // service -> instances
+    ptr: Vec<(String, String)>,
+    // instance -> (host, port)
+    srv: HashMap<String, (String, u16)>,
+    // host -> ip
+    a: HashMap<String, IpAddr>,
+}
+
+impl Records {
+    fn parse(&mut self, packet: &[u8]) -> Result<()> {
+        let mut reader = Reader { packet, pos: 0 };
+        let _id = reader.u16()?;
+        let flags = reader.u16()?;
+        if flags & 0x8000 == 0 {
+            // Someone else's query
+            return Ok(());
+        }
+        let questions = reader.u16()?;
+        let records = reader.u16()? as usize + reader.u16()? as usize + reader.u16()? as usize;
+
+        for _ in 0..questions {
+            reader.name()?;
+            reader.skip(4)?;
+        }
+        for _ in 0..records {
+            let name = reader.name()?;
+            let rtype = reader.u16()?;
+            reader.skip(6)?; // class + ttl
+            let len = reader.u16()? as usize;
+            let end = reader.pos + len;
+            match rtype {
+                TYPE_PTR => {
+                    let instance = reader.name()?;
+                    self.ptr.push((name.to_lowercase(), instance));
+                }
+                TYPE_SRV => {
+                    reader.skip(4)?; // priority + weight
+                    let port = reader.u16()?;
+                    let host = reader.name()?;
+                    self.srv.insert(name, (host.to_lowercase(), port));
+                }
+                TYPE_A if len == 4 => {
+                    let ip = reader.bytes(4)?;
+                    let ip = Ipv4Addr::new(ip[0], ip[1], ip[2], ip[3]);
+                    self.a.insert(name.to_lowercase(), IpAddr::V4(ip));
+                }
+                _ => {}
+            }
+            reader.pos = end;
+        }
+        Ok(())
+    }
+
+    // Both services of one kodi share the instance name, merged into one
+    fn instances(&self) -> Vec<Discovered> {
+        let mut found: Vec<Discovered> = Vec::new();
+        for (service, instance) in &self.ptr {
+            let Some((host, port)) = self.srv.get(instance) else {
+                continue;
+            };
+            let Some(ip) = self.a.get(host) else {
+                continue;
+            };
+            let name = instance
+                .strip_suffix(&format!(".{}", service))
+                .unwrap_or(instance)
+                .to_string();
+
+            let entry = match found.iter_mut().find(|d| d.name == name && d.ip == *ip) {
+                Some(entry) => entry,
+                None => {
+                    found.push(Discovered {
+                        name,
+                        ip: *ip,
+                        websocket_port: None,
+                        webserver_port: None,
+                    });
+                    found.last_mut().expect("Just pushed")
+                }
+            };
+            match service.as_str() {
+                WS_SERVICE => entry.websocket_port = Some(*port),
+                HTTP_SERVICE => entry.webserver_port = Some(*port),
+                _ => {}
+            }
+        }
+        found
+    }
+}
+
+struct Reader<'a> {
+    packet: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn bytes(&mut self, len: usize) -> Result<&[u8]> {
+        let bytes = self
+            .packet
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| anyhow!("Truncated packet"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<()> {
+        self.bytes(len).map(|_| ())
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    // Dotted name, following compression pointers
+    fn name(&mut self) -> Result<String> {
+        let mut labels = Vec::new();
+        let mut pos = self.pos;
+        let mut jumped = false;
+        // Pointers could loop, a real name is never this long
+        for _ in 0..128 {
+            let len = *self
+                .packet
+                .get(pos)
+                .ok_or_else(|| anyhow!("Truncated name"))? as usize;
+            if len == 0 {
+                if !jumped {
+                    self.pos = pos + 1;
+                }
+                return Ok(labels.join("."));
+            }
+            if len & 0xC0 == 0xC0 {
+                let low = *self
+                    .packet
+                    .get(pos + 1)
+                    .ok_or_else(|| anyhow!("Truncated pointer"))?
+                    as usize;
+                if !jumped {
+                    self.pos = pos + 2;
+                    jumped = true;
+                }
+                pos = ((len & 0x3F) << 8) | low;
+                continue;
+            }
+            let label = self
+                .packet
+                .get(pos + 1..pos + 1 + len)
+                .ok_or_else(|| anyhow!("Truncated label"))?;
+            labels.push(String::from_utf8_lossy(label).into_owned());
+            pos += 1 + len;
+        }
+        Err(anyhow!("Name compression loop"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(packet: &mut Vec<u8>, name: &str, rtype: u16, rdata: &[u8]) {
+        write_name(packet, name);
+        packet.extend(rtype.to_be_bytes());
+        packet.extend(CLASS_IN.to_be_bytes());
+        packet.extend(120u32.to_be_bytes());
+        packet.extend((rdata.len() as u16).to_be_bytes());
+        packet.extend(rdata);
+    }
+
+    fn name(name: &str) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_name(&mut bytes, name);
+        bytes
+    }
+
+    fn srv(port: u16, host: &str) -> Vec<u8> {
+        let mut rdata = vec![0, 0, 0, 0];
+        rdata.extend(port.to_be_bytes());
+        rdata.extend(name(host));
+        rdata
+    }
+
+    // What kodi (via avahi) answers for one instance
+    fn response(instance: &str, host: &str, ip: [u8; 4]) -> Vec<u8> {
+        let mut packet = vec![0, 0, 0x84, 0, 0, 0, 0, 5, 0, 0, 0, 0];
+        let ws_instance = format!("{}.{}", instance, WS_SERVICE);
+        let http_instance = format!("{}.{}", instance, HTTP_SERVICE);
+        record(&mut packet, WS_SERVICE, TYPE_PTR, &name(&ws_instance));
+        record(&mut packet, HTTP_SERVICE, TYPE_PTR, &name(&http_instance));
+        record(&mut packet, &ws_instance, TYPE_SRV, &srv(9090, host));
+        record(&mut packet, &http_instance, TYPE_SRV, &srv(8080, host));
+        record(&mut packet, host, TYPE_A, &ip);
+        packet
+    }
+
+    // Stands in for the LAN's mDNS responders, answers one query
+    async fn responder(packets: Vec<Vec<u8>>) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0; 1500];
+            let (len, from) = socket.recv_from(&mut buf).await.unwrap();
+            assert_eq!(&buf[..len], query().as_slice());
+            for packet in packets {
+                socket.send_to(&packet, from).await.unwrap();
+            }
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn finds_instances() {
+        let addr = responder(vec![
+            response("Living Room", "livingroom.local", [192, 168, 1, 20]),
+            response("Bedroom", "bedroom.local", [192, 168, 1, 21]),
+        ])
+        .await;
+
+        let mut found = discover_on(addr, Duration::from_millis(300)).await.unwrap();
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        assert_eq!(
+            found,
+            vec![
+                Discovered {
+                    name: "Bedroom".to_string(),
+                    ip: "192.168.1.21".parse().unwrap(),
+                    websocket_port: Some(9090),
+                    webserver_port: Some(8080),
+                },
+                Discovered {
+                    name: "Living Room".to_string(),
+                    ip: "192.168.1.20".parse().unwrap(),
+                    websocket_port: Some(9090),
+                    webserver_port: Some(8080),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn ignores_garbage() {
+        let addr = responder(vec![vec![0, 0, 0x84, 0, 0, 0, 0, 9], b"not dns".to_vec()]).await;
+        let found = discover_on(addr, Duration::from_millis(300)).await.unwrap();
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn follows_compression_pointers() {
+        // "kodi.local" at 12, then a name that's "host." + pointer to it
+        let mut packet = vec![0; 12];
+        packet.extend(name("kodi.local"));
+        let start = packet.len();
+        packet.extend([4, b'h', b'o', b's', b't', 0xC0, 12, 0xFF]);
+
+        let mut reader = Reader {
+            packet: &packet,
+            pos: start,
+        };
+        assert_eq!(reader.name().unwrap(), "host.kodi.local");
+        assert_eq!(reader.pos, start + 7);
+    }
+}
@@ -11,10 +11,14 @@ const ICONS: Font = Font {
     ..Font::with_name("Material Icons") 
 };
 
+pub fn history() -> Text<'static> {icon('\u{e889}')}
 pub fn movie() -> Text<'static> {icon('\u{e02c}')}
 pub fn tv() -> Text<'static> {icon('\u{e333}')}
+pub fn live_tv() -> Text<'static> {icon('\u{e639}')}
+pub fn library_music() -> Text<'static> {icon('\u{e030}')}
 pub fn folder() -> Text<'static> {icon('\u{e2c7}')}
 pub fn settings() -> Text<'static> {icon('\u{e8b8}')}
+pub fn power_settings_new() -> Text<'static> {icon('\u{e8ac}')}
 pub fn sync() -> Text<'static> {icon('\u{e627}')}
 pub fn sync_disabled() -> Text<'static> {icon('\u{e628}')}
 pub fn menu() -> Text<'static> {icon('\u{e5d2}')}
@@ -38,6 +42,7 @@ pub fn chevron_right() -> Text<'static> {icon('\u{e5cc}')}
 pub fn expand_more() -> Text<'static> {icon('\u{e5cf}')}
 pub fn circle() -> Text<'static> {icon('\u{ef4a}')}
 pub fn arrow_back() -> Text<'static> {icon('\u{e5c4}')}
+pub fn close() -> Text<'static> {icon('\u{e5cd}')}
 
 pub fn done() -> Text<'static> {icon('\u{e876}')}
 
@@ -48,10 +53,23 @@ pub fn fast_rewind() -> Text<'static> {icon('\u{e020}')}
 pub fn fast_forward() -> Text<'static> {icon('\u{e01f}')}
 pub fn skip_previous() -> Text<'static> {icon('\u{e045}')}
 pub fn skip_next() -> Text<'static> {icon('\u{e044}')}
+pub fn shuffle() -> Text<'static> {icon('\u{e043}')}
+pub fn repeat() -> Text<'static> {icon('\u{e040}')}
+pub fn repeat_one() -> Text<'static> {icon('\u{e041}')}
+pub fn celebration() -> Text<'static> {icon('\u{ea65}')}
+
+pub fn play_arrow() -> Text<'static> {icon('\u{e037}')}
+pub fn playlist_add() -> Text<'static> {icon('\u{e03b}')}
+pub fn playlist_play() -> Text<'static> {icon('\u{e05f}')}
+pub fn arrow_upward() -> Text<'static> {icon('\u{e5d8}')}
+pub fn arrow_downward() -> Text<'static> {icon('\u{e5db}')}
+pub fn delete() -> Text<'static> {icon('\u{e872}')}
 
 pub fn subtitles() -> Text<'static> {icon('\u{e048}')}
 pub fn videocam() -> Text<'static> {icon('\u{e04b}')}
 pub fn hearing() -> Text<'static> {icon('\u{e023}')}
+pub fn music_note() -> Text<'static> {icon('\u{e405}')}
+pub fn photo() -> Text<'static> {icon('\u{e410}')}
 
 fn icon(unicode: char) -> Text<'static> {
     text(unicode.to_string())
@@ -0,0 +1,200 @@
+// Server passwords are stored encrypted with a random key kept in its own
+// file next to the settings db, so the db on its own (backups, copying it
+// to another machine) doesn't give them away.
+// Anyone with both files can still decrypt them.
+
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result, anyhow};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+
+// Marks encrypted values, anything without it is a plaintext password
+// from before encryption.
+const PREFIX: &str = "enc1:";
+const KEY_LEN: usize = 32;
+
+static KEY: OnceLock<LessSafeKey> = OnceLock::new();
+
+pub fn is_encrypted(stored: &str) -> bool {
+    stored.starts_with(PREFIX)
+}
+
+/// Empty passwords are stored as is
+pub fn encrypt(plain: &str) -> Result<String> {
+    if plain.is_empty() {
+        return Ok(String::new());
+    }
+    encrypt_with(key()?, plain)
+}
+
+/// Plaintext (not yet migrated) values are returned unchanged
+pub fn decrypt(stored: &str) -> Result<String> {
+    if !is_encrypted(stored) {
+        return Ok(stored.to_string());
+    }
+    decrypt_with(key()?, stored)
+}
+
+fn encrypt_with(key: &LessSafeKey, plain: &str) -> Result<String> {
+    let mut nonce = [0; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| anyhow!("No randomness for nonce"))?;
+
+    let mut sealed = plain.as_bytes().to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::empty(),
+        &mut sealed,
+    )
+    .map_err(|_| anyhow!("Failed to encrypt"))?;
+
+    let mut out = nonce.to_vec();
+    out.extend(sealed);
+    Ok(format!("{}{}", PREFIX, BASE64.encode(out)))
+}
+
+fn decrypt_with(key: &LessSafeKey, stored: &str) -> Result<String> {
+    let data = BASE64
+        .decode(&stored[PREFIX.len()..])
+        .context("Encrypted value isn't base64")?;
+    if data.len() < NONCE_LEN {
+        return Err(anyhow!("Encrypted value is too short"));
+    }
+    let (nonce, sealed) = data.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("Bad nonce"))?;
+
+    let mut sealed = sealed.to_vec();
+    let plain = key
+        .open_in_place(nonce, Aad::empty(), &mut sealed)
+        .map_err(|_| anyhow!("Failed to decrypt, the key file may have changed"))?;
+    Ok(String::from_utf8(plain.to_vec())?)
+}
+
+fn key() -> Result<&'static LessSafeKey> {
+    if let Some(key) = KEY.get() {
+        return Ok(key);
+    }
+    let bytes = load_or_create(&key_path())?;
+    let key = LessSafeKey::new(
+        UnboundKey::new(&AES_256_GCM, &bytes).map_err(|_| anyhow!("Bad key length"))?,
+    );
+    Ok(KEY.get_or_init(|| key))
+}
+
+// Same fallback as the dbs
+fn key_path() -> PathBuf {
+    let dir = crate::PROJECT_DIRS.config_dir();
+    if dir.is_dir() || fs::create_dir_all(dir).is_ok() {
+        dir.join("krustmote.key")
+    } else {
+        Path::new(".").join("krustmote.key")
+    }
+}
+
+// Only a missing file gets a new key, writing one over a file that just
+// couldn't be read would lose every stored password for good.
+fn load_or_create(path: &Path) -> Result<Vec<u8>> {
+    if let Some(bytes) = read_key(path)? {
+        return Ok(bytes);
+    }
+
+    let mut bytes = vec![0; KEY_LEN];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| anyhow!("No randomness for key"))?;
+
+    // Never readable by others, and if someone else got there first their key wins
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = match options.open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+            return read_key(path)?.ok_or_else(|| anyhow!("Key file {:?} vanished", path));
+        }
+        Err(err) => {
+            return Err(err).with_context(|| format!("Failed to create key file {:?}", path));
+        }
+    };
+    file.write_all(&bytes)
+        .with_context(|| format!("Failed to write key file {:?}", path))?;
+    Ok(bytes)
+}
+
+// None if there's no key file yet
+fn read_key(path: &Path) -> Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(bytes) if bytes.len() == KEY_LEN => Ok(Some(bytes)),
+        Ok(_) => Err(anyhow!("Key file {:?} is corrupt", path)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("Failed to read key file {:?}", path)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_key(byte: u8) -> LessSafeKey {
+        LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &[byte; KEY_LEN]).unwrap())
+    }
+
+    #[test]
+    fn round_trips() {
+        let key = test_key(1);
+        let stored = encrypt_with(&key, "hunter2").unwrap();
+        assert!(is_encrypted(&stored));
+        assert!(!stored.contains("hunter2"));
+        assert_eq!(decrypt_with(&key, &stored).unwrap(), "hunter2");
+
+        // Fresh nonce every time
+        assert_ne!(stored, encrypt_with(&key, "hunter2").unwrap());
+    }
+
+    #[test]
+    fn wrong_key_fails() {
+        let stored = encrypt_with(&test_key(1), "hunter2").unwrap();
+        assert!(decrypt_with(&test_key(2), &stored).is_err());
+    }
+
+    #[test]
+    fn key_file_is_created_once() {
+        let dir = std::env::temp_dir().join(format!("krustmote-key-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.key");
+        let _ = fs::remove_file(&path);
+
+        let key = load_or_create(&path).unwrap();
+        assert_eq!(load_or_create(&path).unwrap(), key);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // Unreadable isn't missing, the old key must survive
+        fs::remove_file(&path).unwrap();
+        fs::create_dir(&path).unwrap();
+        assert!(load_or_create(&path).is_err());
+        assert!(path.is_dir());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn plaintext_passes_through() {
+        assert_eq!(decrypt("old password").unwrap(), "old password");
+        assert_eq!(encrypt("").unwrap(), "");
+    }
+}
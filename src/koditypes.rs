@@ -1,10 +1,13 @@
+use crate::client::KodiError;
 use crate::data;
 use core::fmt::Debug;
 use fxhash;
 use iced::futures::channel::mpsc::Sender;
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 // TODO: Investigate Cow for these Strings
 
@@ -16,20 +19,97 @@ pub const POSTER_H: u32 = 120;
 pub const THUMB_W: u32 = 192;
 pub const THUMB_H: u32 = 108;
 pub const ICON_SIZE: u32 = 80;
+pub const DETAILS_POSTER_W: u32 = 240;
+pub const DETAILS_POSTER_H: u32 = 360;
+pub const ALBUM_ART_SIZE: u32 = 80;
+pub const DETAILS_THUMB_W: u32 = 384;
+pub const DETAILS_THUMB_H: u32 = 216;
+pub const FANART_W: u32 = 640;
+pub const FANART_H: u32 = 360;
+
+// Kodi's fixed playlist ids
+pub const MUSIC_PLAYLIST: u8 = 0;
+pub const VIDEO_PLAYLIST: u8 = 1;
 
 #[derive(Debug, Clone)]
 pub enum KodiCommand {
     ChangeServer(Arc<KodiServer>),
+    // Wake-on-LAN, only does anything while offline
+    Wake,
+    SetBackoff(Backoff),
+    // Polling stops while minimised with nothing playing
+    SetMinimized(bool),
+    // Chapters are only fetched while the now playing panel shows them
+    SetNowPlayingOpen(bool),
     PlayerOpen(String),
+    PlayerOpenResume(String),
+    PlayerOpenChannel(u32),
+    PlayerOpenRecording(u32),
+    PlaylistAdd {
+        playlist_id: u8,
+        item: PlaylistItemRef,
+    },
+    PlaylistGetItems {
+        sender: Sender<Vec<PlayingItem>>,
+        playlist_id: u8,
+    },
+    PlaylistRemove {
+        playlist_id: u8,
+        position: u32,
+    },
+    PlaylistSwap {
+        playlist_id: u8,
+        position1: u32,
+        position2: u32,
+    },
+    PlaylistClear(u8),
+    // GoTo only works on the playlist that's already playing
+    // otherwise the playlist has to be opened at the position
+    PlayerGoTo {
+        player_id: u8,
+        position: u32,
+    },
+    // "next" or "previous" in the player's playlist
+    PlayerSkip {
+        player_id: u8,
+        direction: &'static str,
+    },
+    PlayerPlayPause(u8),
+    PlayerStop(u8),
+    PlayerSetShuffle {
+        player_id: u8,
+        shuffle: bool,
+    },
+    PlayerSetRepeat {
+        player_id: u8,
+        repeat: Repeat,
+    },
+    PlayerSetPartymode {
+        player_id: u8,
+        partymode: bool,
+    },
+    // "increment" or "decrement", same steps as kodi's ff/rewind
+    PlayerSetSpeed {
+        player_id: u8,
+        speed: &'static str,
+    },
+    PlayerOpenPlaylist {
+        playlist_id: u8,
+        position: u32,
+    },
     InputButtonEvent {
         button: &'static str,
         keymap: &'static str,
     },
     InputExecuteAction(&'static str),
     ToggleMute,
+    // 0-100
+    SetVolume(u8),
     GUIActivateWindow(&'static str),
     // change to {} to sync with others that take player_id?
     PlayerSeek(u8, KodiTime),
+    // Chapter number as in Chapters::current, always the playing video
+    PlayerGoToChapter(u32),
     PlayerSetSubtitle {
         player_id: u8,
         subtitle_index: u8,
@@ -39,10 +119,17 @@ pub enum KodiCommand {
         player_id: u8,
         on_off: &'static str,
     },
+    // Cycles through the subtitles, turning them on
+    PlayerNextSubtitle(u8),
     PlayerSetAudioStream {
         player_id: u8,
         audio_index: u8,
     },
+    // Other angles or versions of the same video
+    PlayerSetVideoStream {
+        player_id: u8,
+        video_index: u8,
+    },
     InputSendText(String),
 
     PlayerGetProperties,
@@ -66,10 +153,18 @@ pub enum KodiCommand {
         sender: Sender<Vec<TVShowListItem>>,
         limit: i32,
     },
+    VideoLibraryGetMovieDetails {
+        sender: Sender<MovieDetails>,
+        movieid: u32,
+    },
     VideoLibraryGetTVShowDetails {
-        sender: Sender<TVShowListItem>,
+        sender: Sender<TVShowDetails>,
         tvshowid: u32,
     },
+    VideoLibraryGetEpisodeDetails {
+        sender: Sender<EpisodeDetails>,
+        episodeid: u32,
+    },
     VideoLibraryGetTVSeasons {
         sender: Sender<Vec<TVSeasonListItem>>,
         tvshowid: i32,
@@ -83,16 +178,43 @@ pub enum KodiCommand {
         sender: Sender<Vec<TVEpisodeListItem>>,
         ids: Vec<u32>,
     },
-
-    // ID-only fetches for efficient syncing
-    VideoLibraryGetMovieIDs {
-        sender: Sender<Vec<u32>>,
+    // Continue watching
+    VideoLibraryGetInProgressMovies {
+        sender: Sender<Vec<MovieListItem>>,
     },
-    VideoLibraryGetTVShowIDs {
-        sender: Sender<Vec<u32>>,
+    VideoLibraryGetInProgressEpisodes {
+        sender: Sender<Vec<ContinueEpisode>>,
     },
-    VideoLibraryGetTVEpisodeIDs {
+    VideoLibraryGetInProgressTVShows {
         sender: Sender<Vec<u32>>,
+    },
+    // First unwatched (or part watched) episode, if there is one
+    VideoLibraryGetNextEpisode {
+        sender: Sender<Vec<ContinueEpisode>>,
+        tvshowid: u32,
+    },
+
+    // Watched/rating edits, sender gets kodi's answer or nothing if it was never sent
+    VideoLibrarySetDetails {
+        sender: Sender<Result<(), KodiError>>,
+        edit: LibraryEdit,
+    },
+    // Current playcount/userrating as edits, for undoing a failed edit
+    VideoLibraryGetUserState {
+        sender: Sender<Vec<LibraryEdit>>,
+        item: LibraryItem,
+    },
+
+    // ID-only fetches for efficient syncing,
+    // video items come with a Fingerprint to spot changed ones too
+    VideoLibraryGetMovieFingerprints {
+        sender: Sender<Vec<Fingerprint>>,
+    },
+    VideoLibraryGetTVShowFingerprints {
+        sender: Sender<Vec<Fingerprint>>,
+    },
+    VideoLibraryGetTVEpisodeFingerprints {
+        sender: Sender<Vec<Fingerprint>>,
         tvshowid: u32,
     },
     VideoLibraryGetMoviesByIDs {
@@ -103,12 +225,86 @@ pub enum KodiCommand {
         sender: Sender<Vec<TVShowListItem>>,
         ids: Vec<u32>,
     },
+    AudioLibraryGetArtistIDs {
+        sender: Sender<Vec<u32>>,
+    },
+    AudioLibraryGetAlbumIDs {
+        sender: Sender<Vec<u32>>,
+        artistid: u32,
+    },
+    AudioLibraryGetSongIDs {
+        sender: Sender<Vec<u32>>,
+        albumid: u32,
+    },
+    AudioLibraryGetArtistsByIDs {
+        sender: Sender<Vec<ArtistListItem>>,
+        ids: Vec<u32>,
+    },
+    AudioLibraryGetAlbumsByIDs {
+        sender: Sender<Vec<AlbumListItem>>,
+        ids: Vec<u32>,
+    },
+    AudioLibraryGetSongsByIDs {
+        sender: Sender<Vec<SongListItem>>,
+        ids: Vec<u32>,
+    },
+
+    PVRGetChannelGroups {
+        sender: Sender<Vec<PVRChannelGroup>>,
+    },
+    PVRGetChannels {
+        sender: Sender<Vec<PVRChannel>>,
+        channelgroupid: u32,
+    },
+    PVRGetBroadcasts {
+        sender: Sender<Vec<PVRBroadcast>>,
+        channelid: u32,
+    },
+    PVRGetRecordings {
+        sender: Sender<Vec<PVRRecording>>,
+    },
+    PVRGetTimers {
+        sender: Sender<Vec<PVRTimer>>,
+    },
+    // sender only gets a reply if kodi accepted it
+    PVRAddTimer {
+        sender: Sender<()>,
+        broadcastid: u32,
+    },
+    PVRDeleteTimer {
+        sender: Sender<()>,
+        timerid: u32,
+    },
 
     // only used for testing/debug:
     PlayerGetPlayingItemDebug(u8),
     Test,
 }
 
+impl KodiCommand {
+    // Worth a toast when it works, everything else shows for itself on kodi
+    pub fn done_message(&self) -> Option<&'static str> {
+        match self {
+            KodiCommand::PlaylistAdd { .. } => Some("Added to the queue"),
+            KodiCommand::PlaylistClear(_) => Some("Queue cleared"),
+            KodiCommand::InputSendText(_) => Some("Text sent"),
+            _ => None,
+        }
+    }
+
+    /// Stream changes don't get a notification, the players are polled after
+    pub fn changes_streams(&self) -> bool {
+        matches!(
+            self,
+            KodiCommand::PlayerSetSubtitle { .. }
+                | KodiCommand::PlayerToggleSubtitle { .. }
+                | KodiCommand::PlayerNextSubtitle(_)
+                | KodiCommand::PlayerSetAudioStream { .. }
+                | KodiCommand::PlayerSetVideoStream { .. }
+        )
+    }
+}
+
 fn treat_error_as_none<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
 where
     T: Deserialize<'de>,
@@ -124,6 +320,116 @@ pub trait IntoListData {
     fn into_listdata(&self) -> crate::ListData;
     fn get_art_data(&self, server: &Option<Arc<KodiServer>>) -> Pic;
     fn label_contains(&self, find: &String) -> bool;
+    // Shown on right click / long press
+    fn context_actions(&self) -> Vec<MenuLink> {
+        Vec::new()
+    }
+    // Returns true if the edit was for this item
+    fn apply_edit(&mut self, _edit: &LibraryEdit) -> bool {
+        false
+    }
+}
+
+// The subset of Playlist.Item that's used so far
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaylistItemRef {
+    File(String),
+    AlbumId(u32),
+}
+
+fn queue_action(playlist_id: u8, item: PlaylistItemRef) -> MenuLink {
+    MenuLink {
+        label: "Queue".to_string(),
+        on_click: crate::Message::KodiReq(KodiCommand::PlaylistAdd { playlist_id, item }),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LibraryItem {
+    Movie(u32),
+    TVShow(u32),
+    Episode(u32),
+}
+
+impl LibraryItem {
+    pub fn id(&self) -> u32 {
+        match self {
+            LibraryItem::Movie(id) | LibraryItem::TVShow(id) | LibraryItem::Episode(id) => *id,
+        }
+    }
+
+    // Name of the id param in kodi's api
+    pub fn id_field(&self) -> &'static str {
+        match self {
+            LibraryItem::Movie(_) => "movieid",
+            LibraryItem::TVShow(_) => "tvshowid",
+            LibraryItem::Episode(_) => "episodeid",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EditChange {
+    PlayCount(i16),
+    UserRating(u8), // 0 clears it
+}
+
+// Watched/rating changes made from the lists, see data::Get::EditItem
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LibraryEdit {
+    pub item: LibraryItem,
+    pub change: EditChange,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct UserState {
+    pub playcount: i16,
+    pub userrating: u8,
+}
+
+fn edit_action(label: &str, item: LibraryItem, change: EditChange) -> MenuLink {
+    MenuLink {
+        label: label.to_string(),
+        on_click: crate::Message::EditItem(LibraryEdit { item, change }),
+    }
+}
+
+// Shows get both since they can be partly watched
+fn library_actions(title: &str, item: LibraryItem, playcount: i16) -> Vec<MenuLink> {
+    let mut actions = Vec::new();
+    if playcount <= 0 || matches!(item, LibraryItem::TVShow(_)) {
+        actions.push(edit_action("Mark watched", item, EditChange::PlayCount(1)));
+    }
+    if playcount > 0 || matches!(item, LibraryItem::TVShow(_)) {
+        actions.push(edit_action(
+            "Mark unwatched",
+            item,
+            EditChange::PlayCount(0),
+        ));
+    }
+
+    let mut ratings: Vec<MenuLink> = (1..=10)
+        .rev()
+        .map(|r| edit_action(&r.to_string(), item, EditChange::UserRating(r)))
+        .collect();
+    ratings.push(edit_action("Clear rating", item, EditChange::UserRating(0)));
+    actions.push(MenuLink {
+        label: "Set rating".to_string(),
+        on_click: crate::Message::ShowModal(crate::Modals::ContextMenu {
+            title: format!("Rate {}", title).into(),
+            actions: ratings,
+        }),
+    });
+    actions
+}
+
+fn rating_label(rating: f64, userrating: u8) -> String {
+    if userrating > 0 {
+        format!("Rating: {:.1} (yours: {})", rating, userrating)
+    } else {
+        format!("Rating: {:.1}", rating)
+    }
 }
 
 impl Debug for dyn IntoListData + Send {
@@ -184,9 +490,16 @@ impl Pic {
             h,
         }
     }
+
+    // The image caches are keyed by namehash alone
+    // so a pic that isn't list-sized needs its own key.
+    pub fn with_size_key(mut self) -> Self {
+        self.namehash = fxhash::hash(&(self.namehash, self.w, self.h));
+        self
+    }
 }
 
-fn get_filename(path: &str) -> &str {
+pub(crate) fn get_filename(path: &str) -> &str {
     path.rsplit_once('/').map(|(_, f)| f).unwrap_or("")
 }
 
@@ -223,16 +536,64 @@ pub struct PlayerProps {
     #[serde(deserialize_with = "treat_error_as_none")]
     pub currentsubtitle: Option<Subtitle>,
     pub subtitles: Vec<Subtitle>,
-    // #[serde(deserialize_with = "treat_error_as_none")]
-    // pub currentvideostream: VideoStream,
-    // pub videostreams: Vec<VideoStream>,
-    // playlistid: u8,
-    // position: u8, //might rename playlist_position..
-    // repeat: String //(could be enum?)
-    // shuffled: bool,
+    #[serde(default, deserialize_with = "treat_error_as_none")]
+    pub currentvideostream: Option<VideoStream>,
+    #[serde(default)]
+    pub videostreams: Vec<VideoStream>,
+    // -1 when not playing from a playlist
+    pub playlistid: i8,
+    pub position: i32,
+    #[serde(default)]
+    pub repeat: Repeat,
+    #[serde(default)]
+    pub shuffled: bool,
+    // Only ever on for music
+    #[serde(default)]
+    pub partymode: bool,
     pub subtitleenabled: bool,
-    // #[serde(rename = "type")]
-    // type_: MediaType,
+    #[serde(rename = "type", default)]
+    pub player_type: PlayerType,
+    // Player.GetProperties knows nothing of chapters, filled in from infolabels
+    #[serde(skip)]
+    pub chapters: Chapters,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Chapters {
+    // 1-based, 0 when there are none
+    pub current: u32,
+    pub count: u32,
+    // Only the current chapter's name can be asked for
+    pub name: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Repeat {
+    #[default]
+    Off,
+    One,
+    All,
+}
+
+impl Repeat {
+    /// Same order as kodi's own repeat button
+    pub fn next(self) -> Self {
+        match self {
+            Repeat::Off => Repeat::All,
+            Repeat::All => Repeat::One,
+            Repeat::One => Repeat::Off,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlayerType {
+    #[default]
+    Video,
+    Audio,
+    Picture,
 }
 
 #[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
@@ -269,15 +630,81 @@ impl std::fmt::Display for AudioStream {
     }
 }
 
-// #[derive(Deserialize, Clone, Debug, Default)]
-// pub struct VideoStream {
-//     codec: String,
-//     height: u16,
-//     index: u8,
-//     language: String,
-//     name: String,
-//     width: u16,
-// }
+#[derive(Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct VideoStream {
+    codec: String,
+    height: u16,
+    pub index: u8,
+    language: String,
+    name: String,
+    width: u16,
+    // Only kodi 20 and up send this
+    #[serde(default)]
+    hdrtype: String,
+}
+
+impl std::fmt::Display for VideoStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} - {} - {} - {}x{} {}",
+            self.index, self.language, self.name, self.width, self.height, self.codec,
+        )?;
+        if !self.hdrtype.is_empty() {
+            write!(f, " {}", hdr_label(&self.hdrtype))?;
+        }
+        Ok(())
+    }
+}
+
+fn hdr_label(hdrtype: &str) -> String {
+    match hdrtype {
+        "dolbyvision" => "Dolby Vision".to_string(),
+        "hdr10plus" => "HDR10+".to_string(),
+        other => other.to_uppercase(),
+    }
+}
+
+fn channel_label(channels: u8) -> String {
+    match channels {
+        1 => "Mono".to_string(),
+        2 => "2.0".to_string(),
+        6 => "5.1".to_string(),
+        8 => "7.1".to_string(),
+        n => format!("{}ch", n),
+    }
+}
+
+impl PlayerProps {
+    /// "1920x1080 HEVC HDR10 · EAC3 5.1", empty when there's nothing to say
+    pub fn tech_info(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(video) = &self.currentvideostream
+            && video.width > 0
+        {
+            let mut info = format!(
+                "{}x{} {}",
+                video.width,
+                video.height,
+                video.codec.to_uppercase()
+            );
+            if !video.hdrtype.is_empty() {
+                info = format!("{} {}", info, hdr_label(&video.hdrtype));
+            }
+            parts.push(info);
+        }
+        if let Some(audio) = &self.currentaudiostream
+            && !audio.codec.is_empty()
+        {
+            parts.push(format!(
+                "{} {}",
+                audio.codec.to_uppercase(),
+                channel_label(audio.channels)
+            ));
+        }
+        parts.join(" · ")
+    }
+}
 
 #[derive(Deserialize, Clone, Debug, Eq, PartialEq)]
 pub struct Subtitle {
@@ -335,6 +762,15 @@ impl KodiTime {
         self.seconds as u32 + self.minutes as u32 * 60 + self.hours as u32 * 60 * 60
     }
 
+    pub fn total_millis(&self) -> u64 {
+        self.total_seconds() as u64 * 1000 + self.milliseconds.max(0) as u64
+    }
+
+    pub fn set_from_millis(&mut self, millis: u64) {
+        self.set_from_seconds((millis / 1000) as u32);
+        self.milliseconds = (millis % 1000) as i16;
+    }
+
     pub fn set_from_seconds(&mut self, seconds: u32) {
         self.hours = (seconds / 60 / 60) as u8;
         self.minutes = ((seconds / 60).saturating_sub(self.hours as u32 * 60)) as u8;
@@ -347,23 +783,35 @@ impl KodiTime {
 #[derive(Deserialize, Debug, Clone)]
 pub struct KodiAppStatus {
     pub muted: bool,
-    //volume: u8,
+    // 0-100
+    pub volume: u8,
 }
 
-#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum MediaType {
+    #[default]
     Video,
-    // Music,
+    Music,
     // Pictures,
     // Files,
     // Programs,
 }
 
+impl MediaType {
+    pub fn playlist_id(&self) -> u8 {
+        match self {
+            MediaType::Video => VIDEO_PLAYLIST,
+            MediaType::Music => MUSIC_PLAYLIST,
+        }
+    }
+}
+
 impl MediaType {
     pub fn as_str(&self) -> &'static str {
         match self {
             MediaType::Video => "video",
+            MediaType::Music => "music",
         }
     }
 }
@@ -380,6 +828,9 @@ pub struct ActivePlayer {
 pub struct Sources {
     pub label: String,
     pub file: String,
+    // filled in by GetSources, kodi doesn't return it
+    #[serde(skip)]
+    pub media_type: MediaType,
 }
 
 impl IntoListData for Sources {
@@ -396,12 +847,13 @@ impl IntoListData for Sources {
             label: self.label.as_str().into(),
             on_click: crate::Message::GetData(data::Get::Directory {
                 path: self.file.clone(),
-                media_type: MediaType::Video,
+                media_type: self.media_type.clone(),
             }),
             play_count: None,
-            bottom_right: None,
+            bottom_right: Some(self.media_type.as_str().into()),
             bottom_left: None,
             image: None,
+            progress: None,
         }
     }
 
@@ -468,10 +920,13 @@ pub struct DirList {
     pub episode: Option<i16>,
     pub playcount: Option<i16>,
     #[serde(default)]
-    // pub resume: Option<ResumePoint>,
+    pub resume: ResumePoint,
     pub year: Option<u16>,
     #[serde(rename = "type")]
     pub type_: VideoType,
+    // filled in by GetDirectory
+    #[serde(skip)]
+    pub media_type: MediaType,
 }
 
 impl IntoListData for DirList {
@@ -530,8 +985,15 @@ impl IntoListData for DirList {
             on_click: match self.filetype.as_str() {
                 "directory" => crate::Message::GetData(data::Get::Directory {
                     path: self.file.clone(),
-                    media_type: MediaType::Video,
+                    media_type: self.media_type.clone(),
                 }),
+                // Part watched files ask whether to resume
+                "file" if self.resume.position > 0.0 => {
+                    crate::Message::ShowModal(crate::Modals::Resume {
+                        file: self.file.clone(),
+                        resume: self.resume.clone(),
+                    })
+                }
                 "file" => crate::Message::KodiReq(KodiCommand::PlayerOpen(self.file.clone())),
                 _ => panic!("Impossible kodi filetype {}", self.filetype),
             },
@@ -539,6 +1001,18 @@ impl IntoListData for DirList {
             bottom_right,
             bottom_left,
             image: None,
+            progress: self.resume.progress(),
+        }
+    }
+
+    fn context_actions(&self) -> Vec<MenuLink> {
+        if self.filetype == "file" {
+            vec![queue_action(
+                self.media_type.playlist_id(),
+                PlaylistItemRef::File(self.file.clone()),
+            )]
+        } else {
+            Vec::new()
         }
     }
 
@@ -571,17 +1045,19 @@ pub enum VideoType {
     Episode,
     Movie,
     TVShow,
+    // songs/albums/etc from music directories
     #[default]
+    #[serde(other)]
     Unknown,
 }
 
 // I'm not sure these need to be Option<>?
 // They just return blank / DefaultVideo.png otherwise.
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, Default)]
 pub struct Art {
     pub thumb: Option<String>,
     pub poster: Option<String>,
-    // fanart: String,
+    pub fanart: Option<String>,
     // landscape: Option<String>,
     // clearlogo: Option<String>,
     // icon: String (never used)
@@ -626,22 +1102,39 @@ pub const PLAYING_ITEM_PROPS: [&'static str; 28] = [
 pub struct PlayingItem {
     pub label: String,
     pub title: String,
-    // album: String,
-    // artist: Struct // TODO!
+    // Everything else depends on what's playing, a song has no cast
+    // and a stream may have nothing but a label.
+    #[serde(default)]
+    pub album: String,
+    #[serde(default)]
+    pub artist: Vec<String>,
     // track: i16,
-    // cast: Vec<Cast>
-    // director: Vec<String>
+    #[serde(default)]
+    pub art: HashMap<String, String>,
+    #[serde(default)]
+    pub cast: Vec<Cast>,
+    #[serde(default)]
+    pub director: Vec<String>,
     #[serde(rename = "file")]
     pub _file: String, // TODO!? Not actually sure what to do with it...
-    // firstaired: String, //Could convert this to date myself?
+    #[serde(default)]
+    pub firstaired: String,
+    #[serde(default)]
+    pub genre: Vec<String>,
     // playcount: u8,
-    // plot: String,
-    // rating: f64,
+    #[serde(default)]
+    pub plot: String,
+    #[serde(default)]
+    pub rating: f64,
     // runtime: u32, // useless for currently playing item. Might be used for ListItem?
-    // streamdetails: StreamDetails,
-    // studio: Struct // TODO!
-    // tagline: String,
-    // writer: Struct // TODO!
+    #[serde(default)]
+    pub streamdetails: StreamDetails,
+    #[serde(default)]
+    pub studio: Vec<String>,
+    #[serde(default)]
+    pub tagline: String,
+    #[serde(default)]
+    pub writer: Vec<String>,
     // usually these items have a default, but some video streams just leave them out entirely
     pub year: Option<u16>,
     pub showtitle: Option<String>,
@@ -655,12 +1148,11 @@ pub struct PlayingItem {
 }
 
 impl PlayingItem {
-    // this might change once the full PlayingItem is stored in main
-    pub fn make_title(self) -> String {
+    pub fn make_title(&self) -> String {
         if self.type_ == VideoType::Episode {
             format!(
                 "{} - S{:02}E{:02} - {}",
-                self.showtitle.unwrap_or("".to_string()),
+                self.showtitle.as_deref().unwrap_or(""),
                 self.season.unwrap_or(0),
                 self.episode.unwrap_or(0),
                 self.title,
@@ -668,45 +1160,83 @@ impl PlayingItem {
         } else if self.type_ == VideoType::Movie {
             format!("{} ({})", self.title, self.year.unwrap_or(0),)
         } else {
-            self.label
+            self.label.clone()
         }
     }
+
+    // Episodes and songs only have their show's or album's poster
+    pub fn poster(&self) -> Option<&str> {
+        self.art_for(&["poster", "tvshow.poster", "album.thumb", "thumb"])
+    }
+
+    pub fn fanart(&self) -> Option<&str> {
+        self.art_for(&["fanart", "tvshow.fanart", "artist.fanart"])
+    }
+
+    fn art_for(&self, keys: &[&str]) -> Option<&str> {
+        keys.iter()
+            .filter_map(|key| self.art.get(*key))
+            .map(String::as_str)
+            .find(|path| !path.is_empty())
+    }
+}
+
+// Everything in MINIMAL_MOVIE_PROPS has to be here too, see MovieDetails
+pub const DETAILED_MOVIE_PROPS: [&str; 23] = [
+    "title",
+    "genre",
+    "year",
+    "rating",
+    "director",
+    "tagline",
+    "plot",
+    "originaltitle",
+    "playcount",
+    "writer",
+    "studio",
+    "mpaa",
+    "cast",
+    "country",
+    "runtime",
+    "streamdetails",
+    "file",
+    "resume",
+    "dateadded",
+    "art",
+    "premiered",
+    "userrating",
+    "lastplayed",
+];
+
+// Enough to tell whether a cached movie/show/episode is out of date
+// without fetching all of it. These are also stored in the list tables.
+pub const FINGERPRINT_PROPS: [&str; 6] = [
+    "playcount",
+    "lastplayed",
+    "dateadded",
+    "userrating",
+    "title",
+    "art",
+];
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Fingerprint {
+    #[serde(alias = "movieid", alias = "tvshowid", alias = "episodeid")]
+    pub id: u32,
+    pub playcount: i16,
+    pub lastplayed: String,
+    pub dateadded: String,
+    #[serde(default)]
+    pub userrating: u8,
+    #[serde(default)]
+    pub title: String,
+    // Only the art the list table keeps (poster, or thumb for episodes),
+    // filled in by the client
+    #[serde(skip)]
+    pub art: String,
 }
 
-// pub const DETAILED_MOVIE_PROPS: [&'static str; 25] = [
-//     "title",
-//     "genre",
-//     "year",
-//     "rating",
-//     "director",
-//     "trailer",
-//     "tagline",
-//     "plot",
-//     "originaltitle",
-//     "lastplayed",
-//     "playcount",
-//     "writer",
-//     // "studio",
-//     "mpaa",
-//     "cast",
-//     "country",
-//     // "imdbnumber",
-//     "runtime",
-//     // "set",
-//     "streamdetails",
-//     // "votes",
-//     "file",
-//     // "sorttitle",
-//     "resume",
-//     "setid",
-//     "dateadded",
-//     "tag",
-//     "art",
-//     "premiered",
-//     "uniqueid",
-// ];
-
-pub const MINIMAL_TV_PROPS: [&'static str; 10] = [
+pub const MINIMAL_TV_PROPS: [&str; 12] = [
     "title",
     "year",
     "file",    // just returns the folder, not sure I even need this.
@@ -718,10 +1248,35 @@ pub const MINIMAL_TV_PROPS: [&'static str; 10] = [
     // "premiered",
     "playcount",
     "art",
+    "userrating",
+    "lastplayed",
     // sorttitle //? might be useless?
 ];
 
-pub const MINIMAL_EP_PROPS: [&'static str; 12] = [
+// Everything in MINIMAL_TV_PROPS has to be here too, see TVShowDetails
+pub const DETAILED_TV_PROPS: [&str; 19] = [
+    "title",
+    "year",
+    "file",
+    "season",
+    "episode",
+    "dateadded",
+    "genre",
+    "rating",
+    "playcount",
+    "art",
+    "originaltitle",
+    "plot",
+    "studio",
+    "mpaa",
+    "status",
+    "premiered",
+    "cast",
+    "userrating",
+    "lastplayed",
+];
+
+pub const MINIMAL_EP_PROPS: [&str; 15] = [
     "title",
     "tvshowid",
     "file",
@@ -734,9 +1289,39 @@ pub const MINIMAL_EP_PROPS: [&'static str; 12] = [
     "art",
     "specialsortseason",
     "specialsortepisode",
+    "resume",
+    "userrating",
+    "lastplayed",
 ];
 
-#[derive(Deserialize, Debug, Clone)]
+// Everything in MINIMAL_EP_PROPS has to be here too, see EpisodeDetails
+pub const DETAILED_EP_PROPS: [&str; 23] = [
+    "title",
+    "tvshowid",
+    "file",
+    "season",
+    "episode",
+    "dateadded",
+    "rating",
+    "firstaired",
+    "playcount",
+    "art",
+    "specialsortseason",
+    "specialsortepisode",
+    "showtitle",
+    "originaltitle",
+    "plot",
+    "director",
+    "writer",
+    "cast",
+    "runtime",
+    "streamdetails",
+    "resume",
+    "userrating",
+    "lastplayed",
+];
+
+#[derive(Deserialize, Debug, Clone)]
 pub struct TVShowListItem {
     pub tvshowid: u32,
     pub title: String,
@@ -750,6 +1335,25 @@ pub struct TVShowListItem {
     // pub premiered: String,
     pub playcount: i16,
     pub art: Art,
+    #[serde(default)]
+    pub userrating: u8,
+    #[serde(default)]
+    pub lastplayed: String,
+}
+
+// Same idea as MovieDetails, the rest lives in the tvshowdetails table.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TVShowDetails {
+    #[serde(flatten)]
+    pub item: TVShowListItem,
+    pub originaltitle: String,
+    pub plot: String,
+    pub studio: Vec<String>,
+    pub mpaa: String,
+    #[serde(default)] // only newer kodi versions have this
+    pub status: String,
+    pub premiered: String,
+    pub cast: Vec<Cast>,
 }
 
 pub const TV_SEASON_PROPS: [&'static str; 4] = ["tvshowid", "title", "season", "episode"];
@@ -779,6 +1383,27 @@ pub struct TVEpisodeListItem {
     pub art: Art,
     pub specialsortseason: i16, // annoyingly these are -1 for non-special
     pub specialsortepisode: i16,
+    #[serde(default)]
+    pub resume: ResumePoint,
+    #[serde(default)]
+    pub userrating: u8,
+    #[serde(default)]
+    pub lastplayed: String,
+}
+
+// For episodes `cast` also includes the guest stars.
+#[derive(Deserialize, Debug, Clone)]
+pub struct EpisodeDetails {
+    #[serde(flatten)]
+    pub item: TVEpisodeListItem,
+    pub showtitle: String,
+    pub originaltitle: String,
+    pub plot: String,
+    pub director: Vec<String>,
+    pub writer: Vec<String>,
+    pub runtime: u32,
+    pub cast: Vec<Cast>,
+    pub streamdetails: StreamDetails,
 }
 
 impl IntoListData for TVShowListItem {
@@ -797,7 +1422,7 @@ impl IntoListData for TVShowListItem {
             crate::Message::GetData(data::Get::TVSeasons(self.tvshowid))
         };
 
-        let bottom_left = Some(format!("Rating: {:.1}", self.rating));
+        let bottom_left = Some(rating_label(self.rating, self.userrating));
         crate::ListData {
             label: self.title.as_str().into(),
             on_click,
@@ -805,7 +1430,27 @@ impl IntoListData for TVShowListItem {
             bottom_left,
             bottom_right: Some(self.genre.join(", ")),
             image: None,
+            progress: None,
+        }
+    }
+
+    fn context_actions(&self) -> Vec<MenuLink> {
+        library_actions(
+            &self.title,
+            LibraryItem::TVShow(self.tvshowid),
+            self.playcount,
+        )
+    }
+
+    fn apply_edit(&mut self, edit: &LibraryEdit) -> bool {
+        if edit.item != LibraryItem::TVShow(self.tvshowid) {
+            return false;
+        }
+        match edit.change {
+            EditChange::PlayCount(count) => self.playcount = count,
+            EditChange::UserRating(rating) => self.userrating = rating,
         }
+        true
     }
 
     fn get_art_data(&self, svr: &Option<Arc<KodiServer>>) -> Pic {
@@ -842,6 +1487,7 @@ impl IntoListData for TVSeasonListItem {
             bottom_left: None,
             bottom_right: Some(format!("{} Episodes", self.episode)),
             image: None,
+            progress: None,
         }
     }
 
@@ -864,10 +1510,14 @@ impl IntoListData for TVEpisodeListItem {
     }
 
     fn into_listdata(&self) -> crate::ListData {
-        let on_click = crate::Message::KodiReq(KodiCommand::PlayerOpen(self.file.clone()));
+        let on_click = crate::Message::GetData(data::Get::EpisodeDetails(self.episodeid));
 
         let filename = get_filename(&self.file);
-        let bottom_left = Some(format!("Rating: {:.1} - {}", self.rating, filename));
+        let bottom_left = Some(format!(
+            "{} - {}",
+            rating_label(self.rating, self.userrating),
+            filename
+        ));
 
         let label = format!("S{:02}E{:02} - {}", self.season, self.episode, self.title,);
 
@@ -878,7 +1528,40 @@ impl IntoListData for TVEpisodeListItem {
             bottom_left,
             bottom_right: Some(self.firstaired.clone()),
             image: None,
+            progress: self.resume.progress(),
+        }
+    }
+
+    fn context_actions(&self) -> Vec<MenuLink> {
+        let mut actions = vec![queue_action(
+            VIDEO_PLAYLIST,
+            PlaylistItemRef::File(self.file.clone()),
+        )];
+        actions.extend(library_actions(
+            &self.title,
+            LibraryItem::Episode(self.episodeid),
+            self.playcount,
+        ));
+        actions
+    }
+
+    fn apply_edit(&mut self, edit: &LibraryEdit) -> bool {
+        // Marking a whole show also marks its episodes
+        let show_watched = edit.item == LibraryItem::TVShow(self.tvshowid)
+            && matches!(edit.change, EditChange::PlayCount(_));
+        if edit.item != LibraryItem::Episode(self.episodeid) && !show_watched {
+            return false;
+        }
+        match edit.change {
+            EditChange::PlayCount(count) => {
+                self.playcount = count;
+                if count == 0 {
+                    self.resume = ResumePoint::default();
+                }
+            }
+            EditChange::UserRating(rating) => self.userrating = rating,
         }
+        true
     }
 
     fn get_art_data(&self, svr: &Option<Arc<KodiServer>>) -> Pic {
@@ -895,9 +1578,69 @@ impl IntoListData for TVEpisodeListItem {
         self.title.to_lowercase().contains(&find.to_lowercase())
     }
 }
-// should add originaltitle for searching, and resume?
+
+pub const CONTINUE_EP_PROPS: [&str; 16] = [
+    "title",
+    "tvshowid",
+    "file",
+    "season",
+    "episode",
+    "dateadded",
+    "rating",
+    "firstaired",
+    "playcount",
+    "art",
+    "specialsortseason",
+    "specialsortepisode",
+    "resume",
+    "userrating",
+    "lastplayed",
+    "showtitle",
+];
+
+// Episodes listed outside of their show need the show title in the label
+#[derive(Deserialize, Debug, Clone)]
+pub struct ContinueEpisode {
+    #[serde(flatten)]
+    pub item: TVEpisodeListItem,
+    pub showtitle: String,
+}
+
+impl IntoListData for ContinueEpisode {
+    fn dyn_clone(&self) -> Box<dyn IntoListData + Send> {
+        Box::new(self.clone())
+    }
+
+    fn source_data(&self) -> &'static str {
+        "ContinueEpisode"
+    }
+
+    fn into_listdata(&self) -> crate::ListData {
+        let mut data = self.item.into_listdata();
+        data.label = format!("{} - {}", self.showtitle, data.label).into();
+        data
+    }
+
+    fn context_actions(&self) -> Vec<MenuLink> {
+        self.item.context_actions()
+    }
+
+    fn apply_edit(&mut self, edit: &LibraryEdit) -> bool {
+        self.item.apply_edit(edit)
+    }
+
+    fn get_art_data(&self, svr: &Option<Arc<KodiServer>>) -> Pic {
+        self.item.get_art_data(svr)
+    }
+
+    fn label_contains(&self, find: &String) -> bool {
+        self.showtitle.to_lowercase().contains(&find.to_lowercase())
+            || self.item.label_contains(find)
+    }
+}
+// should add originaltitle for searching?
 //   runtime might also be nice for list display
-pub const MINIMAL_MOVIE_PROPS: [&'static str; 9] = [
+pub const MINIMAL_MOVIE_PROPS: [&str; 12] = [
     "title",
     "year",
     "file",
@@ -907,9 +1650,12 @@ pub const MINIMAL_MOVIE_PROPS: [&'static str; 9] = [
     "premiered",
     "playcount",
     "art",
+    "resume",
+    "userrating",
+    "lastplayed",
 ];
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, Default)]
 pub struct MovieListItem {
     pub movieid: u32,
     pub title: String,
@@ -921,6 +1667,12 @@ pub struct MovieListItem {
     pub premiered: String,
     pub playcount: i16,
     pub art: Art,
+    #[serde(default)]
+    pub resume: ResumePoint,
+    #[serde(default)]
+    pub userrating: u8,
+    #[serde(default)]
+    pub lastplayed: String,
 }
 
 impl IntoListData for MovieListItem {
@@ -933,11 +1685,15 @@ impl IntoListData for MovieListItem {
     }
 
     fn into_listdata(&self) -> crate::ListData {
-        let on_click = crate::Message::KodiReq(KodiCommand::PlayerOpen(self.file.clone()));
+        let on_click = crate::Message::GetData(data::Get::MovieDetails(self.movieid));
 
         let filename = get_filename(&self.file);
 
-        let bottom_left = Some(format!("Rating: {:.1} - {}", self.rating, filename));
+        let bottom_left = Some(format!(
+            "{} - {}",
+            rating_label(self.rating, self.userrating),
+            filename
+        ));
         crate::ListData {
             label: self.title.as_str().into(),
             on_click,
@@ -945,7 +1701,37 @@ impl IntoListData for MovieListItem {
             bottom_left,
             bottom_right: Some(self.year.to_string()),
             image: None,
+            progress: self.resume.progress(),
+        }
+    }
+
+    fn context_actions(&self) -> Vec<MenuLink> {
+        let mut actions = vec![queue_action(
+            VIDEO_PLAYLIST,
+            PlaylistItemRef::File(self.file.clone()),
+        )];
+        actions.extend(library_actions(
+            &self.title,
+            LibraryItem::Movie(self.movieid),
+            self.playcount,
+        ));
+        actions
+    }
+
+    fn apply_edit(&mut self, edit: &LibraryEdit) -> bool {
+        if edit.item != LibraryItem::Movie(self.movieid) {
+            return false;
+        }
+        match edit.change {
+            EditChange::PlayCount(count) => {
+                self.playcount = count;
+                if count == 0 {
+                    self.resume = ResumePoint::default();
+                }
+            }
+            EditChange::UserRating(rating) => self.userrating = rating,
         }
+        true
     }
 
     fn get_art_data(&self, svr: &Option<Arc<KodiServer>>) -> Pic {
@@ -962,43 +1748,31 @@ impl IntoListData for MovieListItem {
     }
 }
 
-// #[derive(Deserialize, Debug, Clone)]
-// pub struct MovieProps {
-//     pub movieid: u32,
-//     pub title: String,
-//     pub genre: Vec<String>,
-//     pub year: u16,
-//     pub rating: f64,
-//     pub director: Vec<String>,
-//     pub trailer: String,
-//     pub tagline: String,
-//     pub plot: String,
-//     pub originaltitle: String,
-//     pub lastplayed: String, // maybe date?
-//     pub playcount: u16,
-//     pub writer: Vec<String>,
-//     pub mpaa: String,
-//     pub cast: Vec<Cast>,
-//     pub country: Vec<String>,
-//     pub runtime: u32,
-//     pub streamdetails: StreamDetails,
-//     // votes: String,
-//     pub file: String,
-//     pub resume: ResumePoint,
-//     pub setid: u16,
-//     pub dateadded: String,
-//     pub tag: Vec<String>,
-//     pub art: Art,
-//     pub premiered: String,
-//     pub uniqueid: UniqueId,
-// }
+// The list fields are flattened in so the movielist row can be refreshed
+// from the same response, the rest lives in the moviedetails table.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct MovieDetails {
+    #[serde(flatten)]
+    pub item: MovieListItem,
+    pub originaltitle: String,
+    pub tagline: String,
+    pub plot: String,
+    pub director: Vec<String>,
+    pub writer: Vec<String>,
+    pub studio: Vec<String>,
+    pub mpaa: String,
+    pub country: Vec<String>,
+    pub runtime: u32,
+    pub cast: Vec<Cast>,
+    pub streamdetails: StreamDetails,
+}
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Cast {
-    name: String,
-    order: u16,
-    role: String,
-    thumbnail: Option<String>,
+    pub name: String,
+    pub order: u16,
+    pub role: String,
+    pub thumbnail: Option<String>,
 }
 
 // #[derive(Deserialize, Debug, Clone)]
@@ -1006,46 +1780,86 @@ pub struct Cast {
 //     pub imdb: String,
 // }
 
-// #[derive(Deserialize, Debug, Clone, Default)]
-// pub struct ResumePoint {
-//     pub position: f64, // Position of resume in seconds
-//     pub total: f64,    // total runtime again for some reason
-// }
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct ResumePoint {
+    pub position: f64, // Position of resume in seconds
+    pub total: f64,    // total runtime again for some reason
+}
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+impl ResumePoint {
+    pub fn as_time(&self) -> KodiTime {
+        let mut time = KodiTime::default();
+        time.set_from_seconds(self.position as u32);
+        time
+    }
+
+    // Fraction watched, for the list progress bar
+    pub fn progress(&self) -> Option<f32> {
+        (self.position > 0.0 && self.total > 0.0).then(|| (self.position / self.total) as f32)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
 pub struct StreamDetails {
-    audio: Vec<ItemAudio>,
-    subtitle: Vec<ItemSubtitle>,
-    video: Vec<ItemVideo>,
+    pub audio: Vec<ItemAudio>,
+    pub subtitle: Vec<ItemSubtitle>,
+    pub video: Vec<ItemVideo>,
 }
 // very similar to AudioStream but with less fields
 // Seemed easier to make a new type than option a bunch of stuff
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct ItemAudio {
-    channels: u8,
-    codec: String,
-    language: String,
+    pub channels: u8,
+    pub codec: String,
+    pub language: String,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct ItemSubtitle {
-    language: String,
+    pub language: String,
 }
 
 // unlike the last 2 this has a bit more/different info than VideoStream
-#[derive(Deserialize, Serialize, Debug, Clone)]
+// older kodi versions leave out hdrtype
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
 pub struct ItemVideo {
-    aspect: f64,
-    codec: String,
-    duration: u32,
-    hdrtype: String,
-    height: u16,
-    width: u16,
-    language: String,
-    stereomode: String,
+    pub aspect: f64,
+    pub codec: String,
+    pub duration: u32,
+    pub hdrtype: String,
+    pub height: u16,
+    pub width: u16,
+    pub language: String,
+    pub stereomode: String,
+}
+
+impl std::fmt::Display for ItemVideo {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}x{} {}", self.width, self.height, self.codec)?;
+        if !self.hdrtype.is_empty() {
+            write!(f, " {}", self.hdrtype)?;
+        }
+        if !self.stereomode.is_empty() {
+            write!(f, " 3D")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for ItemAudio {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} {}ch", self.codec, self.channels)?;
+        if !self.language.is_empty() {
+            write!(f, " ({})", self.language)?;
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Default, Hash)]
+#[derive(Clone, PartialEq, Eq, Default, Hash)]
 pub struct KodiServer {
     pub id: u8,
     pub name: String,
@@ -1055,7 +1869,111 @@ pub struct KodiServer {
     pub username: String,
     pub password: String,
     pub db_id: u8, // The movie/tv info database number for sharing
-                   // KodiServer.id==1 can use the same db_id=0 as id==0
+    // KodiServer.id==1 can use the same db_id=0 as id==0
+    pub transport: Transport,
+    // Wake-on-LAN, empty when not set
+    pub mac: String,
+    pub broadcast: String,
+    // The stored password when it couldn't be decrypted, `password` is empty then.
+    // Saved back as is unless a new password is entered.
+    pub locked_password: Option<String>,
+}
+
+/// Reconnect delays, doubling from `min` up to `max`. Each is randomly
+/// off by up to `jitter` (0-1) so several remotes don't all retry at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backoff {
+    pub min: Duration,
+    pub max: Duration,
+    pub jitter: f64,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            min: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+            jitter: 0.2,
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay after `failures` failed attempts in a row, starting at 0
+    pub fn delay(&self, failures: u32) -> Duration {
+        let base = self
+            .min
+            .saturating_mul(2u32.saturating_pow(failures.min(16)))
+            .min(self.max);
+        let jitter = self.jitter.clamp(0.0, 1.0) * (fastrand::f64() * 2.0 - 1.0);
+        base.mul_f64(1.0 + jitter).min(self.max)
+    }
+}
+
+// How the client talks to kodi. Auto tries the websocket first and
+// falls back to HTTP POSTs to the web server if it can't connect,
+// every time it connects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Hash)]
+pub enum Transport {
+    #[default]
+    Auto,
+    WebSocket,
+    Http,
+}
+
+impl Transport {
+    pub const ALL: [Transport; 3] = [Transport::Auto, Transport::WebSocket, Transport::Http];
+}
+
+// Stored as an integer in the servers table
+impl From<u8> for Transport {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => Transport::WebSocket,
+            2 => Transport::Http,
+            _ => Transport::Auto,
+        }
+    }
+}
+
+impl From<Transport> for u8 {
+    fn from(value: Transport) -> Self {
+        match value {
+            Transport::Auto => 0,
+            Transport::WebSocket => 1,
+            Transport::Http => 2,
+        }
+    }
+}
+
+impl std::fmt::Display for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Transport::Auto => "Auto",
+            Transport::WebSocket => "WebSocket",
+            Transport::Http => "HTTP only",
+        })
+    }
+}
+
+// Servers get logged a lot, keep the password out of it
+impl std::fmt::Debug for KodiServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("KodiServer")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("ip", &self.ip)
+            .field("websocket_port", &self.websocket_port)
+            .field("webserver_port", &self.webserver_port)
+            .field("username", &self.username)
+            .field("password", &"<hidden>")
+            .field("db_id", &self.db_id)
+            .field("transport", &self.transport)
+            .field("mac", &self.mac)
+            .field("broadcast", &self.broadcast)
+            .field("locked_password", &self.locked_password.is_some())
+            .finish()
+    }
 }
 
 impl KodiServer {
@@ -1076,6 +1994,10 @@ impl KodiServer {
             username,
             password,
             db_id: 0,
+            transport: Transport::Auto,
+            mac: String::new(),
+            broadcast: String::new(),
+            locked_password: None,
         }
     }
 
@@ -1085,4 +2007,539 @@ impl KodiServer {
     pub fn http_url(&self) -> String {
         format!("http://{}:{}", self.ip, self.webserver_port)
     }
+    pub fn jsonrpc_url(&self) -> String {
+        format!("{}/jsonrpc", self.http_url())
+    }
+
+    /// Basic auth for the web server, kodi only asks for it when a username is set
+    pub fn credentials(&self) -> Option<(&str, &str)> {
+        if self.username.is_empty() {
+            None
+        } else {
+            Some((&self.username, &self.password))
+        }
+    }
+}
+
+pub const MINIMAL_ARTIST_PROPS: [&str; 3] = ["genre", "thumbnail", "dateadded"];
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ArtistListItem {
+    pub artistid: u32,
+    pub artist: String,
+    pub genre: Vec<String>,
+    #[serde(default)]
+    pub thumbnail: String,
+    #[serde(default)]
+    pub dateadded: String,
+}
+
+impl IntoListData for ArtistListItem {
+    fn dyn_clone(&self) -> Box<dyn IntoListData + Send> {
+        Box::new(self.clone())
+    }
+
+    fn source_data(&self) -> &'static str {
+        "Artist"
+    }
+
+    fn into_listdata(&self) -> crate::ListData {
+        crate::ListData {
+            label: self.artist.as_str().into(),
+            on_click: crate::Message::GetData(data::Get::Albums(self.artistid, true)),
+            play_count: None,
+            bottom_left: None,
+            bottom_right: Some(self.genre.join(", ")),
+            image: None,
+            progress: None,
+        }
+    }
+
+    fn get_art_data(&self, svr: &Option<Arc<KodiServer>>) -> Pic {
+        if self.thumbnail.is_empty() {
+            Pic::from_path(svr, ICON_FOLDER, ALBUM_ART_SIZE, ALBUM_ART_SIZE)
+        } else {
+            Pic::from_path(svr, &self.thumbnail, ALBUM_ART_SIZE, ALBUM_ART_SIZE)
+        }
+    }
+
+    fn label_contains(&self, find: &String) -> bool {
+        self.artist.to_lowercase().contains(&find.to_lowercase())
+    }
+}
+
+pub const MINIMAL_ALBUM_PROPS: [&str; 8] = [
+    "title",
+    "displayartist",
+    "artistid",
+    "year",
+    "genre",
+    "thumbnail",
+    "playcount",
+    "dateadded",
+];
+
+// `artistid` holds all the album artists, it's stored in the albumartist table
+#[derive(Deserialize, Debug, Clone)]
+pub struct AlbumListItem {
+    pub albumid: u32,
+    pub title: String,
+    pub displayartist: String,
+    pub artistid: Vec<u32>,
+    pub year: u16,
+    pub genre: Vec<String>,
+    #[serde(default)]
+    pub thumbnail: String,
+    pub playcount: i16,
+    #[serde(default)]
+    pub dateadded: String,
+}
+
+impl IntoListData for AlbumListItem {
+    fn dyn_clone(&self) -> Box<dyn IntoListData + Send> {
+        Box::new(self.clone())
+    }
+
+    fn source_data(&self) -> &'static str {
+        "Album"
+    }
+
+    fn into_listdata(&self) -> crate::ListData {
+        let bottom_right = (self.year > 0).then(|| self.year.to_string());
+        crate::ListData {
+            label: self.title.as_str().into(),
+            on_click: crate::Message::GetData(data::Get::Songs(self.albumid, true)),
+            play_count: Some(self.playcount),
+            bottom_left: Some(self.displayartist.clone()),
+            bottom_right,
+            image: None,
+            progress: None,
+        }
+    }
+
+    fn context_actions(&self) -> Vec<MenuLink> {
+        vec![queue_action(
+            MUSIC_PLAYLIST,
+            PlaylistItemRef::AlbumId(self.albumid),
+        )]
+    }
+
+    fn get_art_data(&self, svr: &Option<Arc<KodiServer>>) -> Pic {
+        if self.thumbnail.is_empty() {
+            Pic::from_path(svr, ICON_FOLDER, ALBUM_ART_SIZE, ALBUM_ART_SIZE)
+        } else {
+            Pic::from_path(svr, &self.thumbnail, ALBUM_ART_SIZE, ALBUM_ART_SIZE)
+        }
+    }
+
+    fn label_contains(&self, find: &String) -> bool {
+        self.title.to_lowercase().contains(&find.to_lowercase())
+    }
+}
+
+pub const MINIMAL_SONG_PROPS: [&str; 9] = [
+    "title",
+    "albumid",
+    "displayartist",
+    "track",
+    "disc",
+    "duration",
+    "file",
+    "thumbnail",
+    "playcount",
+];
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SongListItem {
+    pub songid: u32,
+    pub albumid: u32,
+    pub title: String,
+    pub displayartist: String,
+    pub track: u16,
+    pub disc: u16,
+    pub duration: u32,
+    pub file: String,
+    #[serde(default)]
+    pub thumbnail: String,
+    pub playcount: i16,
+}
+
+impl IntoListData for SongListItem {
+    fn dyn_clone(&self) -> Box<dyn IntoListData + Send> {
+        Box::new(self.clone())
+    }
+
+    fn source_data(&self) -> &'static str {
+        "Song"
+    }
+
+    fn into_listdata(&self) -> crate::ListData {
+        let on_click = crate::Message::KodiReq(KodiCommand::PlayerOpen(self.file.clone()));
+
+        let duration = format!("{}:{:02}", self.duration / 60, self.duration % 60);
+
+        crate::ListData {
+            label: format!("{:02}. {}", self.track, self.title).into(),
+            on_click,
+            play_count: Some(self.playcount),
+            bottom_left: Some(self.displayartist.clone()),
+            bottom_right: Some(duration),
+            image: None,
+            progress: None,
+        }
+    }
+
+    fn context_actions(&self) -> Vec<MenuLink> {
+        vec![queue_action(
+            MUSIC_PLAYLIST,
+            PlaylistItemRef::File(self.file.clone()),
+        )]
+    }
+
+    fn get_art_data(&self, svr: &Option<Arc<KodiServer>>) -> Pic {
+        if self.thumbnail.is_empty() {
+            Pic::from_path(svr, ICON_FILE, ALBUM_ART_SIZE, ALBUM_ART_SIZE)
+        } else {
+            Pic::from_path(svr, &self.thumbnail, ALBUM_ART_SIZE, ALBUM_ART_SIZE)
+        }
+    }
+
+    fn label_contains(&self, find: &String) -> bool {
+        self.title.to_lowercase().contains(&find.to_lowercase())
+    }
+}
+
+// Plain link entries mixed in to other lists (like "- Recordings" in Live TV)
+#[derive(Debug, Clone)]
+pub struct MenuLink {
+    pub(crate) label: String,
+    pub(crate) on_click: crate::Message,
+}
+
+impl IntoListData for MenuLink {
+    fn dyn_clone(&self) -> Box<dyn IntoListData + Send> {
+        Box::new(self.clone())
+    }
+
+    fn source_data(&self) -> &'static str {
+        "MenuLink"
+    }
+
+    fn into_listdata(&self) -> crate::ListData {
+        crate::ListData {
+            label: self.label.as_str().into(),
+            on_click: self.on_click.clone(),
+            play_count: None,
+            bottom_left: None,
+            bottom_right: None,
+            image: None,
+            progress: None,
+        }
+    }
+
+    fn get_art_data(&self, _: &Option<Arc<KodiServer>>) -> Pic {
+        Pic::default()
+    }
+
+    fn label_contains(&self, find: &String) -> bool {
+        self.label.to_lowercase().contains(&find.to_lowercase())
+    }
+}
+
+// PVR times are "YYYY-MM-DD HH:MM:SS" in UTC
+pub fn pvr_time(time: &str) -> Option<chrono::DateTime<chrono::Local>> {
+    chrono::NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|t| t.and_utc().with_timezone(&chrono::Local))
+}
+
+fn pvr_time_str(time: &str, format: &str) -> String {
+    pvr_time(time)
+        .map(|t| t.format(format).to_string())
+        .unwrap_or_default()
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PVRChannelGroup {
+    pub channelgroupid: u32,
+    pub label: String,
+}
+
+impl IntoListData for PVRChannelGroup {
+    fn dyn_clone(&self) -> Box<dyn IntoListData + Send> {
+        Box::new(self.clone())
+    }
+
+    fn source_data(&self) -> &'static str {
+        "PVRChannelGroup"
+    }
+
+    fn into_listdata(&self) -> crate::ListData {
+        crate::ListData {
+            label: self.label.as_str().into(),
+            on_click: crate::Message::GetData(data::Get::PVRChannels {
+                channelgroupid: self.channelgroupid,
+                label: self.label.clone(),
+            }),
+            play_count: None,
+            bottom_left: None,
+            bottom_right: None,
+            image: None,
+            progress: None,
+        }
+    }
+
+    fn get_art_data(&self, svr: &Option<Arc<KodiServer>>) -> Pic {
+        Pic::from_path(svr, ICON_FOLDER, ICON_SIZE, ICON_SIZE)
+    }
+
+    fn label_contains(&self, find: &String) -> bool {
+        self.label.to_lowercase().contains(&find.to_lowercase())
+    }
+}
+
+pub const PVR_CHANNEL_PROPS: [&str; 4] = [
+    "channelnumber",
+    "thumbnail",
+    "broadcastnow",
+    "broadcastnext",
+];
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PVRChannel {
+    pub channelid: u32,
+    pub label: String,
+    #[serde(default)]
+    pub channelnumber: u32,
+    #[serde(default)]
+    pub thumbnail: String,
+    pub broadcastnow: Option<PVRBroadcast>,
+    pub broadcastnext: Option<PVRBroadcast>,
+}
+
+impl IntoListData for PVRChannel {
+    fn dyn_clone(&self) -> Box<dyn IntoListData + Send> {
+        Box::new(self.clone())
+    }
+
+    fn source_data(&self) -> &'static str {
+        "PVRChannel"
+    }
+
+    fn into_listdata(&self) -> crate::ListData {
+        let now_next =
+            |b: &PVRBroadcast| format!("{} {}", pvr_time_str(&b.starttime, "%H:%M"), b.title);
+
+        crate::ListData {
+            label: format!("{} {}", self.channelnumber, self.label).into(),
+            on_click: crate::Message::GetData(data::Get::PVRGuide {
+                channelid: self.channelid,
+                label: self.label.clone(),
+            }),
+            play_count: None,
+            bottom_left: self.broadcastnow.as_ref().map(now_next),
+            bottom_right: self.broadcastnext.as_ref().map(now_next),
+            image: None,
+            progress: None,
+        }
+    }
+
+    fn get_art_data(&self, svr: &Option<Arc<KodiServer>>) -> Pic {
+        if self.thumbnail.is_empty() {
+            Pic::from_path(svr, ICON_FILE, ICON_SIZE, ICON_SIZE)
+        } else {
+            Pic::from_path(svr, &self.thumbnail, ICON_SIZE, ICON_SIZE)
+        }
+    }
+
+    fn label_contains(&self, find: &String) -> bool {
+        self.label.to_lowercase().contains(&find.to_lowercase())
+    }
+}
+
+pub const PVR_BROADCAST_PROPS: [&str; 6] = [
+    "title",
+    "starttime",
+    "endtime",
+    "plotoutline",
+    "isactive",
+    "hastimer",
+];
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PVRBroadcast {
+    #[serde(default)]
+    pub broadcastid: u32,
+    pub title: String,
+    pub starttime: String,
+    pub endtime: String,
+    #[serde(default)]
+    pub plotoutline: String,
+    #[serde(default)]
+    pub isactive: bool,
+    #[serde(default)]
+    pub hastimer: bool,
+    // The guide fills these in, they're needed for Play/AddTimer
+    #[serde(skip)]
+    pub channelid: u32,
+    #[serde(skip)]
+    pub channel: String,
+}
+
+impl IntoListData for PVRBroadcast {
+    fn dyn_clone(&self) -> Box<dyn IntoListData + Send> {
+        Box::new(self.clone())
+    }
+
+    fn source_data(&self) -> &'static str {
+        "PVRBroadcast"
+    }
+
+    // Live ones play the channel, later ones set a timer
+    fn into_listdata(&self) -> crate::ListData {
+        let on_click = if self.isactive {
+            crate::Message::KodiReq(KodiCommand::PlayerOpenChannel(self.channelid))
+        } else if !self.hastimer {
+            crate::Message::GetData(data::Get::PVRAddTimer {
+                broadcastid: self.broadcastid,
+                channelid: self.channelid,
+                channel: self.channel.clone(),
+            })
+        } else {
+            crate::Message::None
+        };
+
+        let bottom_right = if self.isactive {
+            "Now".to_string()
+        } else if self.hastimer {
+            "Recording".to_string()
+        } else {
+            "Record".to_string()
+        };
+
+        crate::ListData {
+            label: format!(
+                "{} - {}  {}",
+                pvr_time_str(&self.starttime, "%a %H:%M"),
+                pvr_time_str(&self.endtime, "%H:%M"),
+                self.title
+            )
+            .into(),
+            on_click,
+            play_count: None,
+            bottom_left: Some(self.plotoutline.clone()),
+            bottom_right: Some(bottom_right),
+            image: None,
+            progress: None,
+        }
+    }
+
+    fn get_art_data(&self, _: &Option<Arc<KodiServer>>) -> Pic {
+        Pic::default()
+    }
+
+    fn label_contains(&self, find: &String) -> bool {
+        self.title.to_lowercase().contains(&find.to_lowercase())
+    }
+}
+
+pub const PVR_RECORDING_PROPS: [&str; 6] = [
+    "title",
+    "channel",
+    "starttime",
+    "runtime",
+    "art",
+    "playcount",
+];
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PVRRecording {
+    pub recordingid: u32,
+    pub title: String,
+    pub channel: String,
+    pub starttime: String,
+    pub runtime: u32,
+    #[serde(default)]
+    pub art: Art,
+    pub playcount: i16,
+}
+
+impl IntoListData for PVRRecording {
+    fn dyn_clone(&self) -> Box<dyn IntoListData + Send> {
+        Box::new(self.clone())
+    }
+
+    fn source_data(&self) -> &'static str {
+        "PVRRecording"
+    }
+
+    fn into_listdata(&self) -> crate::ListData {
+        crate::ListData {
+            label: self.title.as_str().into(),
+            on_click: crate::Message::KodiReq(KodiCommand::PlayerOpenRecording(self.recordingid)),
+            play_count: Some(self.playcount),
+            bottom_left: Some(format!("{} - {} min", self.channel, self.runtime / 60)),
+            bottom_right: Some(pvr_time_str(&self.starttime, "%Y-%m-%d %H:%M")),
+            image: None,
+            progress: None,
+        }
+    }
+
+    fn get_art_data(&self, svr: &Option<Arc<KodiServer>>) -> Pic {
+        self.art
+            .thumb
+            .as_ref()
+            .map(|t| Pic::from_path(svr, t, THUMB_W, THUMB_H))
+            .unwrap_or_else(|| Pic::from_path(svr, ICON_FILE, ICON_SIZE, ICON_SIZE))
+    }
+
+    fn label_contains(&self, find: &String) -> bool {
+        self.title.to_lowercase().contains(&find.to_lowercase())
+    }
+}
+
+pub const PVR_TIMER_PROPS: [&str; 4] = ["title", "starttime", "endtime", "state"];
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct PVRTimer {
+    pub timerid: u32,
+    pub title: String,
+    pub starttime: String,
+    pub endtime: String,
+    pub state: String,
+}
+
+impl IntoListData for PVRTimer {
+    fn dyn_clone(&self) -> Box<dyn IntoListData + Send> {
+        Box::new(self.clone())
+    }
+
+    fn source_data(&self) -> &'static str {
+        "PVRTimer"
+    }
+
+    // Clicking a timer deletes it
+    fn into_listdata(&self) -> crate::ListData {
+        crate::ListData {
+            label: self.title.as_str().into(),
+            on_click: crate::Message::GetData(data::Get::PVRDeleteTimer(self.timerid)),
+            play_count: None,
+            bottom_left: Some(format!(
+                "{} - {}",
+                pvr_time_str(&self.starttime, "%a %Y-%m-%d %H:%M"),
+                pvr_time_str(&self.endtime, "%H:%M")
+            )),
+            bottom_right: Some(format!("{} - click to delete", self.state)),
+            image: None,
+            progress: None,
+        }
+    }
+
+    fn get_art_data(&self, _: &Option<Arc<KodiServer>>) -> Pic {
+        Pic::default()
+    }
+
+    fn label_contains(&self, find: &String) -> bool {
+        self.title.to_lowercase().contains(&find.to_lowercase())
+    }
 }
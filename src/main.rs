@@ -4,7 +4,7 @@
 use iced::widget::{center, column, container, image, mouse_area, row, scrollable, stack};
 use iced::widget::{opaque, operation};
 
-use iced::{Element, Event, Length, Subscription, Task as Command, event, font, window};
+use iced::{Element, Event, Length, Subscription, Task as Command, event, font, mouse, window};
 
 use ::image as imagelib;
 use reqwest;
@@ -14,11 +14,14 @@ use tokio::sync::Semaphore;
 
 use directories_next::ProjectDirs;
 use indexmap::IndexMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::error::Error;
 use std::fs as stdfs;
+use std::mem::{Discriminant, discriminant};
 use std::sync::Mutex;
 use std::sync::{Arc, LazyLock, OnceLock};
+use std::time::Instant;
 use tokio;
 use tracing::{debug, error, info};
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
@@ -26,11 +29,16 @@ use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 mod client;
 mod data;
 mod db;
+mod discovery;
 mod icons;
+mod keyfile;
 mod koditypes;
+#[cfg(test)]
+mod mockkodi;
 mod settingsui;
 mod themes;
 mod uiparts;
+mod wol;
 mod widgets {
     pub mod listitem;
 }
@@ -42,6 +50,10 @@ pub const ITEM_HEIGHT: u32 = 55;
 const MENU_WIDTH_OPEN: u32 = 120;
 const DEFAULT_IMAGE_W: u32 = 80;
 const DEFAULT_IMAGE_H: u32 = 120;
+const TOAST_TIME: std::time::Duration = std::time::Duration::from_secs(6);
+const MAX_TOASTS: usize = 3;
+// Per scroll wheel notch
+const VOLUME_STEP: f32 = 5.0;
 
 static BLANK_IMAGE: OnceLock<image::Handle> = OnceLock::new();
 static PROJECT_DIRS: LazyLock<ProjectDirs> = LazyLock::new(|| {
@@ -49,6 +61,7 @@ static PROJECT_DIRS: LazyLock<ProjectDirs> = LazyLock::new(|| {
         .expect("Unlikely to ever run on an OS that doesn't support it")
 });
 
+static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(reqwest::Client::new);
 static DECODED_IMAGE_CACHE: LazyLock<Mutex<IndexMap<usize, image::Handle>>> =
     LazyLock::new(|| Mutex::new(IndexMap::with_capacity(100)));
 
@@ -103,11 +116,52 @@ struct Krustmote {
     state: State,
     menu_width: u32,
     kodi_status: KodiStatus,
+    servers: Vec<KodiServer>,
     item_list: ItemList,
     slider_grabbed: bool,
+    volume_grabbed: bool,
+    minimized: bool,
     send_text: String,
     content_area: ContentArea,
+    now_playing: NowPlaying,
     modal: Modals,
+    queue: Queue,
+    backoff: Backoff,
+    toasts: Toasts,
+    // Commands sent and not yet answered, by kind, so their buttons can wait
+    pending: HashMap<Discriminant<KodiCommand>, usize>,
+}
+
+// Kept separately from the modal so it can be refreshed while open
+#[derive(Debug, Default)]
+struct Queue {
+    playlist_id: u8,
+    items: Vec<String>,
+}
+
+// Opened from the playing bar in place of the content, which carries on underneath.
+// The art paths are what's loaded (or loading) so it's only redone when the item changes.
+#[derive(Debug, Default)]
+struct NowPlaying {
+    open: bool,
+    poster_path: Option<String>,
+    fanart_path: Option<String>,
+    poster: Option<image::Handle>,
+    fanart: Option<image::Handle>,
+}
+
+// Shown over the content for TOAST_TIME, or until closed
+#[derive(Debug, Default)]
+struct Toasts {
+    next_id: usize,
+    shown: Vec<Toast>,
+}
+
+#[derive(Debug)]
+struct Toast {
+    id: usize,
+    message: String,
+    is_error: bool,
 }
 
 #[derive(Default)]
@@ -128,15 +182,36 @@ enum Modals {
     None,
     Subtitles,
     RequestText,
-    _Video,
+    Video,
     Audio,
+    Queue,
+    Resume {
+        file: String,
+        resume: ResumePoint,
+    },
+    ContextMenu {
+        title: Arc<str>,
+        actions: Vec<MenuLink>,
+    },
 }
 
 enum ContentArea {
     Files,
     Loading,
-    Settings(settingsui::Settings),
-    _ItemInfo,
+    Settings(Box<settingsui::Settings>),
+    ItemInfo(Box<ItemInfo>),
+}
+
+struct ItemInfo {
+    details: data::ItemDetails,
+    poster: Option<image::Handle>,
+    fanart: Option<image::Handle>,
+}
+
+#[derive(Debug, Clone)]
+enum InfoArt {
+    Poster,
+    Fanart,
 }
 
 // TODO: consider directly using PlayerProps and PlayingItem
@@ -144,11 +219,64 @@ enum ContentArea {
 #[derive(Debug, Clone, Default)]
 struct KodiStatus {
     server: Option<Arc<KodiServer>>,
+    // The player the transport controls act on,
+    // player_props, playing_item and playing_title are copies of its status
     active_player_id: Option<u8>,
     muted: bool,
+    volume: u8,
     playing_title: String,
-    // playing_item: PlayingItem,
+    connection: client::ConnectionStatus,
+    playing_item: PlayingItem,
     player_props: PlayerProps,
+    players: BTreeMap<u8, PlayerStatus>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct PlayerStatus {
+    props: PlayerProps,
+    item: PlayingItem,
+    title: String,
+    // When props.time was read from kodi or last moved on
+    synced_at: Option<Instant>,
+}
+
+impl KodiStatus {
+    // Sticks with the current player while it's still active,
+    // otherwise falls back to the lowest player id.
+    fn select_player(&mut self, player_id: Option<u8>) {
+        let selected = player_id
+            .or(self.active_player_id)
+            .filter(|id| self.players.contains_key(id))
+            .or_else(|| self.players.keys().next().copied());
+        let player = selected.and_then(|id| self.players.get(&id));
+        self.active_player_id = selected;
+        self.player_props = player.map(|p| p.props.clone()).unwrap_or_default();
+        self.playing_item = player.map(|p| p.item.clone()).unwrap_or_default();
+        self.playing_title = player.map(|p| p.title.clone()).unwrap_or_default();
+    }
+
+    // Moves playback time on at each player's speed, kodi is only
+    // polled now and then so this keeps the seek bar going in between.
+    fn advance(&mut self, now: Instant) {
+        for player in self.players.values_mut() {
+            let Some(synced_at) = player.synced_at else {
+                continue;
+            };
+            let elapsed = now.saturating_duration_since(synced_at).as_millis() as f64;
+            let mut time = player.props.time.total_millis() as f64 + elapsed * player.props.speed;
+            let total = player.props.totaltime.total_millis();
+            if total > 0 {
+                time = time.min(total as f64);
+            }
+            player.props.time.set_from_millis(time.max(0.0) as u64);
+            player.synced_at = Some(now);
+        }
+        self.select_player(None);
+    }
+
+    fn is_moving(&self) -> bool {
+        self.players.values().any(|p| p.props.speed != 0.0)
+    }
 }
 
 #[derive(Debug)]
@@ -160,6 +288,7 @@ pub struct ListData {
     bottom_left: Option<String>,  // container/element?
     bottom_right: Option<String>, // container/element?
     image: Option<image::Handle>,
+    progress: Option<f32>, // resume point, 0.0 to 1.0
 }
 
 #[derive(Debug, Clone)]
@@ -167,6 +296,11 @@ enum Message {
     ToggleLeftMenu,
     UpBreadCrumb,
     KodiReq(KodiCommand),
+    KodiDone {
+        kind: Discriminant<KodiCommand>,
+        result: Result<(), client::KodiError>,
+        message: Option<&'static str>,
+    },
     DataEvent(data::DataEvent),
     GetData(data::Get),
     Settings(settingsui::Message),
@@ -178,13 +312,37 @@ enum Message {
     WindowResized(u32),
     SliderChanged(u32),
     SliderReleased,
+    SelectPlayer(u8),
+    PlaybackTick,
+    CheckMinimized(window::Id),
+    Minimized(bool),
+    VolumeChanged(u8),
+    VolumeReleased,
+    VolumeScrolled(mouse::ScrollDelta),
     HideModalAndKodiReq(KodiCommand),
     ShowModal(Modals),
+    ShowQueue(u8),
+    ShowContextMenu(usize),
+    ContextAction(Box<Message>),
+    EditItem(LibraryEdit),
     SubtitlePicked(Subtitle),
     SubtitleToggle(bool),
     AudioStreamPicked(AudioStream),
+    VideoStreamPicked(VideoStream),
     SendTextInput(String),
-    ImageLoaded { index: usize, handle: image::Handle },
+    DismissToast(usize),
+    CancelLoad,
+    ImageLoaded {
+        index: usize,
+        handle: image::Handle,
+    },
+    InfoArtLoaded(InfoArt, image::Handle),
+    ToggleNowPlaying,
+    NowPlayingArtLoaded {
+        art: InfoArt,
+        path: String,
+        handle: image::Handle,
+    },
     None,
 }
 
@@ -206,11 +364,19 @@ impl Krustmote {
                 state: State::Disconnected,
                 menu_width: MENU_WIDTH_OPEN,
                 kodi_status: Default::default(),
+                servers: Vec::new(),
                 item_list: Default::default(),
                 slider_grabbed: false,
+                volume_grabbed: false,
+                minimized: false,
                 send_text: String::from(""),
                 content_area: ContentArea::Files,
+                now_playing: Default::default(),
                 modal: Modals::None,
+                queue: Default::default(),
+                backoff: Default::default(),
+                toasts: Default::default(),
+                pending: HashMap::new(),
             },
             font::load(include_bytes!("../fonts/MaterialIcons-Regular.ttf").as_slice())
                 .map(Message::FontLoaded),
@@ -236,22 +402,39 @@ impl Krustmote {
 
             Message::SettingsEvent(event) => match event {
                 settingsui::Event::AddServer(srv) => {
-                    self.content_area = ContentArea::Files;
                     let q = data::Get::AddOrEditServer(srv);
                     return Command::perform(async { q }, move |q| Message::GetData(q));
                 }
+                settingsui::Event::SelectServer(id) => {
+                    self.content_area = ContentArea::Files;
+                    let q = data::Get::SelectServer(id);
+                    return Command::perform(async { q }, Message::GetData);
+                }
+                settingsui::Event::DeleteServer(id) => {
+                    let q = data::Get::DeleteServer(id);
+                    return Command::perform(async { q }, Message::GetData);
+                }
+                settingsui::Event::SetBackoff { min, max } => {
+                    let q = data::Get::SetBackoff { min, max };
+                    return Command::perform(async { q }, Message::GetData);
+                }
+                settingsui::Event::Discover => {
+                    return Command::perform(discovery::discover(), |found| {
+                        Message::Settings(settingsui::Message::Discovered(found))
+                    });
+                }
                 settingsui::Event::Cancel => {
                     self.content_area = ContentArea::Files;
                 }
             },
 
             Message::ShowSettings => {
-                let settings = if let Some(server) = &self.kodi_status.server {
-                    settingsui::Settings::load(Arc::clone(server))
-                } else {
-                    settingsui::Settings::new()
-                };
-                self.content_area = ContentArea::Settings(settings);
+                let settings = settingsui::Settings::new(
+                    self.servers.clone(),
+                    self.kodi_status.server.as_ref().map(|s| s.id),
+                    self.backoff,
+                );
+                self.content_area = ContentArea::Settings(Box::new(settings));
             }
 
             Message::ToggleLeftMenu => {
@@ -277,6 +460,41 @@ impl Krustmote {
                 self.modal = modal;
             }
 
+            Message::ShowQueue(playlist_id) => {
+                self.modal = Modals::Queue;
+                if self.queue.playlist_id != playlist_id {
+                    self.queue.playlist_id = playlist_id;
+                    self.queue.items.clear();
+                }
+                return self.handle_get_data(data::Get::Playlist(playlist_id));
+            }
+
+            Message::ShowContextMenu(index) => {
+                let Some(&raw_idx) = self.item_list.filtered_indices.get(index) else {
+                    return Command::none();
+                };
+                let actions = self.item_list.raw_data[raw_idx].context_actions();
+                if !actions.is_empty() {
+                    let title = self
+                        .item_list
+                        .virtual_list
+                        .get(&index)
+                        .map(|d| d.label.clone())
+                        .unwrap_or_default();
+                    self.modal = Modals::ContextMenu { title, actions };
+                }
+            }
+
+            Message::ContextAction(msg) => {
+                self.modal = Modals::None;
+                return Command::perform(async { *msg }, move |msg| msg);
+            }
+
+            Message::EditItem(edit) => {
+                let cmd = self.apply_edit(&edit);
+                return Command::batch([cmd, self.handle_get_data(data::Get::EditItem(edit))]);
+            }
+
             Message::WindowResized(height) => {
                 // Window height instead of scrollable height is a few extra items
                 // but getting the scrollable height is more tedious for little gain.
@@ -335,10 +553,32 @@ impl Krustmote {
                 return Command::perform(async { cmd }, move |c| Message::KodiReq(c));
             }
 
+            Message::VideoStreamPicked(val) => {
+                let cmd = KodiCommand::PlayerSetVideoStream {
+                    player_id: self
+                        .kodi_status
+                        .active_player_id
+                        .expect("Should be playing if this is called"),
+                    video_index: val.index,
+                };
+                return Command::perform(async { cmd }, Message::KodiReq);
+            }
+
             Message::SendTextInput(text) => {
                 self.send_text = text;
             }
 
+            Message::CancelLoad => {
+                if matches!(self.content_area, ContentArea::Loading) {
+                    self.stop_loading();
+                    return self.handle_get_data(data::Get::CancelLoad);
+                }
+            }
+
+            Message::DismissToast(id) => {
+                self.toasts.shown.retain(|toast| toast.id != id);
+            }
+
             Message::FilterFileList(filter) => {
                 let mut cmds = vec![operation::snap_to(
                     "files",
@@ -364,6 +604,37 @@ impl Krustmote {
                 }
             }
 
+            Message::InfoArtLoaded(art, handle) => {
+                if let ContentArea::ItemInfo(info) = &mut self.content_area {
+                    match art {
+                        InfoArt::Poster => info.poster = Some(handle),
+                        InfoArt::Fanart => info.fanart = Some(handle),
+                    }
+                }
+            }
+
+            Message::ToggleNowPlaying => {
+                self.now_playing.open = !self.now_playing.open;
+                return Command::batch([
+                    self.handle_get_data(data::Get::NowPlayingOpen(self.now_playing.open)),
+                    self.load_now_playing_art(),
+                ]);
+            }
+
+            Message::NowPlayingArtLoaded { art, path, handle } => {
+                // Might be for an item that's already finished
+                let now_playing = &mut self.now_playing;
+                match art {
+                    InfoArt::Poster if now_playing.poster_path.as_ref() == Some(&path) => {
+                        now_playing.poster = Some(handle)
+                    }
+                    InfoArt::Fanart if now_playing.fanart_path.as_ref() == Some(&path) => {
+                        now_playing.fanart = Some(handle)
+                    }
+                    _ => {}
+                }
+            }
+
             Message::SliderChanged(new) => {
                 self.slider_grabbed = true;
                 self.kodi_status.player_props.time.set_from_seconds(new);
@@ -381,6 +652,54 @@ impl Krustmote {
                 return Command::perform(async { cmd }, move |c| Message::KodiReq(c));
             }
 
+            Message::SelectPlayer(player_id) => {
+                // Data does the same, this just saves waiting on it
+                self.kodi_status.select_player(Some(player_id));
+                return self.handle_get_data(data::Get::SelectPlayer(player_id));
+            }
+
+            // Dragging the seek bar wins
+            Message::PlaybackTick if !self.slider_grabbed => {
+                self.kodi_status.advance(Instant::now());
+            }
+
+            Message::CheckMinimized(id) => {
+                return window::is_minimized(id)
+                    .map(|minimized| Message::Minimized(minimized.unwrap_or(false)));
+            }
+
+            Message::Minimized(minimized) if minimized != self.minimized => {
+                self.minimized = minimized;
+                return self.handle_get_data(data::Get::Minimized(minimized));
+            }
+
+            Message::VolumeChanged(volume) => {
+                self.volume_grabbed = true;
+                self.kodi_status.volume = volume;
+            }
+
+            Message::VolumeReleased => {
+                self.volume_grabbed = false;
+                let cmd = KodiCommand::SetVolume(self.kodi_status.volume);
+                return Command::perform(async { cmd }, Message::KodiReq);
+            }
+
+            Message::VolumeScrolled(delta) => {
+                let notches = match delta {
+                    mouse::ScrollDelta::Lines { y, .. } => y,
+                    // Touchpads scroll in many small steps
+                    mouse::ScrollDelta::Pixels { y, .. } => y / 50.0,
+                };
+                let change = (notches * VOLUME_STEP).round() as i16;
+                if change == 0 || self.volume_grabbed {
+                    return Command::none();
+                }
+                let volume = (self.kodi_status.volume as i16 + change).clamp(0, 100) as u8;
+                self.kodi_status.volume = volume;
+                let cmd = KodiCommand::SetVolume(volume);
+                return Command::perform(async { cmd }, Message::KodiReq);
+            }
+
             Message::DataEvent(event) => {
                 return self.handle_data_event(event);
             }
@@ -391,15 +710,41 @@ impl Krustmote {
 
             Message::KodiReq(command) => match &mut self.state {
                 State::Connected(_, connection) => {
-                    connection.send(command);
+                    let kind = discriminant(&command);
+                    let message = command.done_message();
+                    *self.pending.entry(kind).or_default() += 1;
+                    return Command::perform(connection.request(command), move |result| {
+                        Message::KodiDone {
+                            kind,
+                            result,
+                            message,
+                        }
+                    });
                 }
 
                 State::Offline(_) | State::Disconnected => {
-                    println!("TODO: Kodi is disconnected UI state")
-                    //panic!("Kodi is apparently disconnected so I can't");
+                    return self.show_toast(client::KodiError::NotConnected.to_string(), true);
                 }
             },
 
+            Message::KodiDone {
+                kind,
+                result,
+                message,
+            } => {
+                if let Some(count) = self.pending.get_mut(&kind) {
+                    *count -= 1;
+                    if *count == 0 {
+                        self.pending.remove(&kind);
+                    }
+                }
+                match (result, message) {
+                    (Err(err), _) => return self.show_toast(err.to_string(), true),
+                    (Ok(()), Some(message)) => return self.show_toast(message.to_string(), false),
+                    (Ok(()), None) => {}
+                }
+            }
+
             _ => {}
         }
 
@@ -407,15 +752,24 @@ impl Krustmote {
     }
 
     fn subscription(&self) -> iced::Subscription<Message> {
-        let subs = vec![
-            event::listen_with(|mevent, _, _| match mevent {
+        let mut subs = vec![
+            event::listen_with(|mevent, _, id| match mevent {
                 Event::Window(window::Event::Resized(sz)) => {
                     Some(Message::WindowResized(sz.height as u32))
                 }
+                // Minimising always loses focus, there's no event of its own
+                Event::Window(window::Event::Focused | window::Event::Unfocused) => {
+                    Some(Message::CheckMinimized(id))
+                }
                 _ => None,
             }),
             Subscription::run(data::connect).map(Message::DataEvent),
         ];
+        if self.kodi_status.is_moving() {
+            subs.push(
+                iced::time::every(std::time::Duration::from_secs(1)).map(|_| Message::PlaybackTick),
+            );
+        }
 
         iced::Subscription::batch(subs)
     }
@@ -448,10 +802,20 @@ impl Krustmote {
             Modals::Subtitles => Some(uiparts::make_subtitle_modal(self)),
             Modals::RequestText => Some(uiparts::request_text_modal(self)),
             Modals::Audio => Some(uiparts::make_audio_modal(self)),
+            Modals::Video => Some(uiparts::make_video_modal(self)),
+            Modals::Queue => Some(uiparts::make_queue_modal(self)),
+            Modals::Resume {
+                ref file,
+                ref resume,
+            } => Some(uiparts::make_resume_modal(file, resume)),
+            Modals::ContextMenu {
+                ref title,
+                ref actions,
+            } => Some(uiparts::make_context_modal(title, actions)),
             _ => None,
         };
 
-        if let Some(modal) = modal {
+        let content = if let Some(modal) = modal {
             stack![
                 content,
                 opaque(
@@ -461,9 +825,46 @@ impl Krustmote {
             .into()
         } else {
             content
+        };
+
+        if self.toasts.shown.is_empty() {
+            content
+        } else {
+            stack![content, uiparts::toasts(&self.toasts.shown)].into()
         }
     }
 
+    fn is_pending(&self, command: &KodiCommand) -> bool {
+        self.pending.contains_key(&discriminant(command))
+    }
+
+    // For buttons that shouldn't be pressed again until kodi has answered
+    fn kodi_req(&self, command: KodiCommand) -> Option<Message> {
+        (!self.is_pending(&command)).then_some(Message::KodiReq(command))
+    }
+
+    // Back to the list that was showing before the load started
+    fn stop_loading(&mut self) {
+        self.item_list.breadcrumb.pop();
+        self.content_area = ContentArea::Files;
+    }
+
+    fn show_toast(&mut self, message: String, is_error: bool) -> Command<Message> {
+        let id = self.toasts.next_id;
+        self.toasts.next_id += 1;
+        if self.toasts.shown.len() == MAX_TOASTS {
+            self.toasts.shown.remove(0);
+        }
+        self.toasts.shown.push(Toast {
+            id,
+            message,
+            is_error,
+        });
+        Command::perform(tokio::time::sleep(TOAST_TIME), move |_| {
+            Message::DismissToast(id)
+        })
+    }
+
     fn up_breadcrumb(&mut self) -> Message {
         // dbg!(&self.breadcrumb);
         let _ = self.item_list.breadcrumb.pop();
@@ -482,16 +883,34 @@ impl Krustmote {
                 self.state = State::Connected(conn, kodiconn);
                 Command::none()
             }
-            data::DataEvent::Servers(servers) => {
-                if servers.is_empty() {
-                    // Only switch to or reset settings if we aren't already there.
-                    // This prevents background reconnect cycles from clobbering active input.
-                    if !matches!(self.content_area, ContentArea::Settings(_)) {
-                        let new_server = settingsui::Settings::new();
-                        self.content_area = ContentArea::Settings(new_server);
-                    }
-                } else {
-                    self.kodi_status.server = Some(Arc::new(servers[0].clone()));
+            data::DataEvent::Servers { servers, active } => {
+                let active_id = active.as_ref().map(|s| s.id);
+                self.servers = servers;
+                self.kodi_status.server = active;
+
+                // Only switch to or reset settings if we aren't already there.
+                // This prevents background reconnect cycles from clobbering active input.
+                if let ContentArea::Settings(set) = &mut self.content_area {
+                    set.set_servers(self.servers.clone(), active_id);
+                } else if self.servers.is_empty() {
+                    let new_server = settingsui::Settings::new(Vec::new(), None, self.backoff);
+                    self.content_area = ContentArea::Settings(Box::new(new_server));
+                }
+                Command::none()
+            }
+            data::DataEvent::ServerChanged(server) => {
+                // Everything in the list came from the old server's library.
+                self.kodi_status = KodiStatus {
+                    server,
+                    ..Default::default()
+                };
+                self.item_list = ItemList {
+                    visible_count: self.item_list.visible_count,
+                    ..Default::default()
+                };
+                self.queue = Default::default();
+                if !matches!(self.content_area, ContentArea::Settings(_)) {
+                    self.content_area = ContentArea::Files;
                 }
                 Command::none()
             }
@@ -529,6 +948,9 @@ impl Krustmote {
                         (data::Get::TVEpisodes(s1, e1, _), data::Get::TVEpisodes(s2, e2, _)) => {
                             s1 == s2 && e1 == e2
                         }
+                        (data::Get::Artists(_), data::Get::Artists(_)) => true,
+                        (data::Get::Albums(a1, _), data::Get::Albums(a2, _)) => a1 == a2,
+                        (data::Get::Songs(a1, _), data::Get::Songs(a2, _)) => a1 == a2,
                         _ => current_req == &request,
                     };
                 }
@@ -552,21 +974,98 @@ impl Krustmote {
                     operation::snap_to("files", scrollable::RelativeOffset { x: 0.0, y: 0.0 }),
                 ])
             }
+            data::DataEvent::ItemDetails { request, details } => {
+                // Only if the user is still waiting on this one
+                if !matches!(
+                    self.item_list.breadcrumb.last(),
+                    Some(Message::GetData(current)) if current == &request
+                ) {
+                    return Command::none();
+                }
+
+                let server = &self.kodi_status.server;
+                let mut tasks = Vec::new();
+                if let Some(poster) = details.poster() {
+                    let (w, h) = details.poster_size();
+                    let pic = Pic::from_path(server, poster, w, h);
+                    tasks.push(
+                        self.load_pic(pic.with_size_key())
+                            .map(|handle| match handle {
+                                Some(handle) => Message::InfoArtLoaded(InfoArt::Poster, handle),
+                                None => Message::None,
+                            }),
+                    );
+                }
+                if let Some(fanart) = details.fanart() {
+                    let pic = Pic::from_path(server, fanart, FANART_W, FANART_H);
+                    tasks.push(
+                        self.load_pic(pic.with_size_key())
+                            .map(|handle| match handle {
+                                Some(handle) => Message::InfoArtLoaded(InfoArt::Fanart, handle),
+                                None => Message::None,
+                            }),
+                    );
+                }
+
+                self.content_area = ContentArea::ItemInfo(Box::new(ItemInfo {
+                    details,
+                    poster: None,
+                    fanart: None,
+                }));
+                Command::batch(tasks)
+            }
+            data::DataEvent::Backoff(backoff) => {
+                self.backoff = backoff;
+                if let ContentArea::Settings(set) = &mut self.content_area {
+                    set.set_backoff(backoff);
+                }
+                Command::none()
+            }
             data::DataEvent::KodiStatus(kodistatus) => {
-                if !self.slider_grabbed {
-                    self.kodi_status = kodistatus;
-                } else {
-                    let selected_time = self.kodi_status.player_props.time.clone();
-                    self.kodi_status = kodistatus;
+                let selected_time = self.kodi_status.player_props.time.clone();
+                let selected_volume = self.kodi_status.volume;
+                self.kodi_status = *kodistatus;
+                self.kodi_status.advance(Instant::now());
+                if self.slider_grabbed {
                     self.kodi_status.player_props.time = selected_time;
                 }
-                Command::none()
+                if self.volume_grabbed {
+                    self.kodi_status.volume = selected_volume;
+                }
+                self.load_now_playing_art()
             }
             data::DataEvent::InputRequested(input) => {
                 self.send_text = input;
                 self.modal = Modals::RequestText;
                 Command::none()
             }
+            data::DataEvent::Playlist { playlist_id, items } => {
+                if self.queue.playlist_id == playlist_id {
+                    self.queue.items = items;
+                }
+                Command::none()
+            }
+            data::DataEvent::PlaylistChanged(playlist_id) => {
+                // Only worth fetching while the queue is on screen
+                if matches!(self.modal, Modals::Queue) && self.queue.playlist_id == playlist_id {
+                    self.handle_get_data(data::Get::Playlist(playlist_id))
+                } else {
+                    Command::none()
+                }
+            }
+            data::DataEvent::ItemEdited(edit) => self.apply_edit(&edit),
+            data::DataEvent::LoadFailed(request) => {
+                let waiting = matches!(
+                    self.item_list.breadcrumb.last(),
+                    Some(Message::GetData(current)) if *current == request
+                );
+                if waiting && matches!(self.content_area, ContentArea::Loading) {
+                    self.stop_loading();
+                }
+                Command::none()
+            }
+            data::DataEvent::CommandFailed(err) => self.show_toast(err.to_string(), true),
+            data::DataEvent::Error(message) => self.show_toast(message, true),
         }
     }
 
@@ -587,7 +1086,9 @@ impl Krustmote {
                     .unwrap_or(false);
 
                 match &cmd {
-                    data::Get::Movies(sync) | data::Get::TVShows(sync) => {
+                    data::Get::Movies(sync)
+                    | data::Get::TVShows(sync)
+                    | data::Get::Artists(sync) => {
                         if *sync {
                             if !is_duplicate {
                                 self.item_list.breadcrumb.clear();
@@ -598,7 +1099,7 @@ impl Krustmote {
                             self.content_area = ContentArea::Loading;
                         }
                     }
-                    data::Get::Sources => {
+                    data::Get::Sources | data::Get::LiveTV | data::Get::ContinueWatching => {
                         if !is_duplicate {
                             self.item_list.breadcrumb.clear();
                             self.item_list
@@ -607,7 +1108,9 @@ impl Krustmote {
                         }
                         self.content_area = ContentArea::Loading;
                     }
-                    data::Get::TVEpisodes(_, _, sync) => {
+                    data::Get::TVEpisodes(_, _, sync)
+                    | data::Get::Albums(_, sync)
+                    | data::Get::Songs(_, sync) => {
                         if *sync {
                             if !is_duplicate {
                                 self.item_list
@@ -617,7 +1120,15 @@ impl Krustmote {
                             self.content_area = ContentArea::Loading;
                         }
                     }
-                    data::Get::TVSeasons(_) | data::Get::Directory { .. } => {
+                    data::Get::TVSeasons(_)
+                    | data::Get::Directory { .. }
+                    | data::Get::PVRChannels { .. }
+                    | data::Get::PVRGuide { .. }
+                    | data::Get::PVRRecordings
+                    | data::Get::PVRTimers
+                    | data::Get::MovieDetails(_)
+                    | data::Get::TVShowDetails(_)
+                    | data::Get::EpisodeDetails(_) => {
                         if !is_duplicate {
                             self.item_list
                                 .breadcrumb
@@ -627,6 +1138,11 @@ impl Krustmote {
                     }
                     _ => {}
                 }
+                // Going somewhere else in the library
+                if matches!(self.content_area, ContentArea::Loading) && self.now_playing.open {
+                    self.now_playing.open = false;
+                    connection.send(data::Get::NowPlayingOpen(false));
+                }
                 connection.send(cmd);
             }
             _ => {}
@@ -634,6 +1150,19 @@ impl Krustmote {
         Command::none()
     }
 
+    // Updates the current list in place instead of waiting on a reload
+    fn apply_edit(&mut self, edit: &LibraryEdit) -> Command<Message> {
+        let mut changed = false;
+        for item in self.item_list.raw_data.iter_mut() {
+            changed |= item.apply_edit(edit);
+        }
+        if !changed {
+            return Command::none();
+        }
+        self.item_list.virtual_list.clear();
+        self.update_virtual_list()
+    }
+
     fn recompute_filter(&mut self) {
         self.item_list.filtered_indices = self
             .item_list
@@ -688,11 +1217,65 @@ impl Krustmote {
     }
 
     fn load_art_task(&self, index: usize, pic: Pic) -> Command<Message> {
+        self.load_pic(pic).map(move |handle| match handle {
+            Some(handle) => Message::ImageLoaded { index, handle },
+            None => Message::None,
+        })
+    }
+
+    // Follows the selected player's item, only while the panel is open
+    fn load_now_playing_art(&mut self) -> Command<Message> {
+        if !self.now_playing.open {
+            return Command::none();
+        }
+        let item = &self.kodi_status.playing_item;
+        let poster = item.poster().map(str::to_string);
+        let fanart = item.fanart().map(str::to_string);
+
+        let mut tasks = Vec::new();
+        if poster != self.now_playing.poster_path {
+            if let Some(path) = &poster {
+                let pic = Pic::from_path(
+                    &self.kodi_status.server,
+                    path,
+                    DETAILS_POSTER_W,
+                    DETAILS_POSTER_H,
+                );
+                tasks.push(self.now_playing_art_task(InfoArt::Poster, path.clone(), pic));
+            }
+            self.now_playing.poster = None;
+            self.now_playing.poster_path = poster;
+        }
+        if fanart != self.now_playing.fanart_path {
+            if let Some(path) = &fanart {
+                let pic = Pic::from_path(&self.kodi_status.server, path, FANART_W, FANART_H);
+                tasks.push(self.now_playing_art_task(InfoArt::Fanart, path.clone(), pic));
+            }
+            self.now_playing.fanart = None;
+            self.now_playing.fanart_path = fanart;
+        }
+        Command::batch(tasks)
+    }
+
+    fn now_playing_art_task(&self, art: InfoArt, path: String, pic: Pic) -> Command<Message> {
+        self.load_pic(pic.with_size_key())
+            .map(move |handle| match handle {
+                Some(handle) => Message::NowPlayingArtLoaded {
+                    art: art.clone(),
+                    path: path.clone(),
+                    handle,
+                },
+                None => Message::None,
+            })
+    }
+
+    fn load_pic(&self, pic: Pic) -> Command<Option<image::Handle>> {
         if pic.url.is_none() && pic.namehash == 0 {
             return Command::none();
         }
 
         let online = matches!(self.state, State::Connected(_, _));
+        let server = self.kodi_status.server.clone();
 
         Command::future(async move {
             let namehash = pic.namehash;
@@ -706,23 +1289,24 @@ impl Krustmote {
                     if online && pic.url.is_some() {
                         // semaphore limits it to 10 simultaneous DLs from svr
                         let _permit = SEM.acquire().await;
-                        Krustmote::download_pic(pic, &path).await
+                        Krustmote::download_pic(pic, &path, server.as_deref()).await
                     } else {
-                        return Message::None;
+                        return None;
                     }
                 }
             };
 
-            if let Ok(res) = res {
-                if let Ok(mut cache) = DECODED_IMAGE_CACHE.lock() {
-                    cache.insert(namehash, res.clone());
+            match res {
+                Ok(res) => {
+                    if let Ok(mut cache) = DECODED_IMAGE_CACHE.lock() {
+                        cache.insert(namehash, res.clone());
+                    }
+                    Some(res)
+                }
+                Err(err) => {
+                    error!("Art task error: {:?}", err);
+                    None
                 }
-                Message::ImageLoaded { index, handle: res }
-            } else if let Err(err) = res {
-                error!("Art task error: {:?}", err);
-                Message::None
-            } else {
-                Message::None
             }
         })
     }
@@ -742,9 +1326,14 @@ impl Krustmote {
     async fn download_pic(
         pic: Pic,
         cache_path: &Path,
+        server: Option<&KodiServer>,
     ) -> Result<image::Handle, Box<dyn Error + Send + Sync>> {
         let url = pic.url.expect("Must exist if gotten here");
-        let img = reqwest::get(url).await?.error_for_status()?;
+        let mut request = HTTP_CLIENT.get(url);
+        if let Some((username, password)) = server.and_then(|s| s.credentials()) {
+            request = request.basic_auth(username, Some(password));
+        }
+        let img = request.send().await?.error_for_status()?;
         let img = img.bytes().await?;
 
         let fmt = imagelib::guess_format(&img)?;
@@ -780,3 +1369,151 @@ fn make_cache_dir() -> Result<(), Box<dyn Error>> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mockkodi::MockKodi;
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    fn png(w: u32, h: u32) -> Vec<u8> {
+        let img = imagelib::RgbaImage::from_pixel(w, h, imagelib::Rgba([200, 50, 50, 255]));
+        let mut bytes = Cursor::new(Vec::new());
+        img.write_to(&mut bytes, imagelib::ImageFormat::Png)
+            .unwrap();
+        bytes.into_inner()
+    }
+
+    fn cache_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("krustmote-test-{}", std::process::id()));
+        stdfs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    #[tokio::test]
+    async fn downloads_and_resizes_images() {
+        let mock = MockKodi::start().await;
+        let path = "image://%2fposters%2fmovie.png/";
+        mock.add_image(path, png(40, 60));
+
+        let pic = Pic::from_path(&Some(mock.server.clone()), path, 20, 30);
+        let cache = cache_path("poster");
+        Krustmote::download_pic(pic, &cache, Some(&mock.server))
+            .await
+            .unwrap();
+
+        assert_eq!(mock.image_requests(), vec![path.to_string()]);
+        let saved = imagelib::open(cache.with_extension("png")).unwrap();
+        assert_eq!((saved.width(), saved.height()), (20, 30));
+        let _ = stdfs::remove_file(cache.with_extension("png"));
+    }
+
+    #[tokio::test]
+    async fn missing_images_are_errors() {
+        let mock = MockKodi::start().await;
+        let pic = Pic::from_path(&Some(mock.server.clone()), "image://missing.jpg/", 20, 30);
+        let cache = cache_path("missing");
+
+        assert!(
+            Krustmote::download_pic(pic, &cache, Some(&mock.server))
+                .await
+                .is_err()
+        );
+        assert!(!cache.with_extension("jpg").exists());
+    }
+
+    #[tokio::test]
+    async fn image_downloads_use_credentials() {
+        let mock = MockKodi::start().await;
+        mock.require_auth("kodi", "secret");
+        let path = "image://thumb.png/";
+        mock.add_image(path, png(10, 10));
+        let server = KodiServer {
+            username: "kodi".to_string(),
+            password: "secret".to_string(),
+            ..mock.server.as_ref().clone()
+        };
+
+        let cache = cache_path("authed");
+        let pic = Pic::from_path(&Some(mock.server.clone()), path, 10, 10);
+        assert!(
+            Krustmote::download_pic(pic.clone(), &cache, Some(&mock.server))
+                .await
+                .is_err()
+        );
+        Krustmote::download_pic(pic, &cache, Some(&server))
+            .await
+            .unwrap();
+        let _ = stdfs::remove_file(cache.with_extension("png"));
+    }
+
+    #[test]
+    fn keeps_the_selected_player_while_it_plays() {
+        let player = |title: &str| PlayerStatus {
+            title: title.to_string(),
+            ..Default::default()
+        };
+        let mut status = KodiStatus {
+            players: BTreeMap::from([(0, player("Song")), (2, player("Photo"))]),
+            ..Default::default()
+        };
+        status.select_player(None);
+        assert_eq!(status.active_player_id, Some(0));
+
+        status.select_player(Some(2));
+        status.select_player(None);
+        assert_eq!(status.active_player_id, Some(2));
+        assert_eq!(status.playing_title, "Photo");
+
+        status.players.remove(&2);
+        status.select_player(None);
+        assert_eq!(status.active_player_id, Some(0));
+        assert_eq!(status.playing_title, "Song");
+
+        status.players.clear();
+        status.select_player(Some(0));
+        assert_eq!(status.active_player_id, None);
+        assert!(status.playing_title.is_empty());
+    }
+
+    #[test]
+    fn playback_time_moves_on_at_the_players_speed() {
+        let start = Instant::now();
+        let mut props = PlayerProps {
+            speed: 2.0,
+            ..Default::default()
+        };
+        props.time.set_from_seconds(60);
+        props.totaltime.set_from_seconds(65);
+        let mut status = KodiStatus {
+            players: BTreeMap::from([(
+                1,
+                PlayerStatus {
+                    props,
+                    synced_at: Some(start),
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        };
+
+        status.advance(start + std::time::Duration::from_millis(1500));
+        assert_eq!(status.player_props.time.total_millis(), 63_000);
+        // Stops at the end rather than running past it
+        status.advance(start + std::time::Duration::from_secs(10));
+        assert_eq!(status.player_props.time.total_seconds(), 65);
+
+        status.players.get_mut(&1).unwrap().props.speed = 0.0;
+        status
+            .players
+            .get_mut(&1)
+            .unwrap()
+            .props
+            .time
+            .set_from_seconds(30);
+        status.advance(start + std::time::Duration::from_secs(20));
+        assert_eq!(status.player_props.time.total_seconds(), 30);
+        assert!(!status.is_moving());
+    }
+}
@@ -0,0 +1,394 @@
+// In-process fake kodi for the tests.
+//
+// Speaks JSON-RPC over a websocket like kodi's tcp/ws api, and over
+// `POST /jsonrpc` plus `/image/<path>` on http like kodi's webserver. Responses are scripted
+// per method, anything unscripted gets kodi's "Method not found" error.
+// The status polling methods have defaults so a connected client stays connected.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use iced::futures::StreamExt;
+use iced::futures::channel::mpsc::{Receiver, channel};
+use serde_json::{Value, json};
+use soketto::handshake::{Server, server::Response};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, mpsc};
+use tokio_util::compat::TokioAsyncReadCompatExt;
+
+use crate::client::{self, Event};
+use crate::koditypes::{Backoff, KodiServer};
+
+type Handler = Box<dyn Fn(&Value) -> Result<Value, String> + Send>;
+
+#[derive(Debug, Clone)]
+enum Outgoing {
+    Notification(String),
+    Disconnect,
+}
+
+#[derive(Default)]
+struct MockState {
+    handlers: HashMap<String, Handler>,
+    // Never answered, like kodi stuck waiting on a share
+    stalled: HashSet<String>,
+    calls: Vec<(String, Value)>,
+    images: HashMap<String, Vec<u8>>,
+    image_requests: Vec<String>,
+    connections: usize,
+    // Basic auth for the http side, like kodi's web server login
+    auth: Option<String>,
+}
+
+pub struct MockKodi {
+    pub server: Arc<KodiServer>,
+    state: Arc<Mutex<MockState>>,
+    outgoing: broadcast::Sender<Outgoing>,
+}
+
+impl MockKodi {
+    pub async fn start() -> Self {
+        let ws = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let http = TcpListener::bind("127.0.0.1:0").await.unwrap();
+
+        let server = Arc::new(KodiServer {
+            name: "Mock".to_string(),
+            ip: "127.0.0.1".to_string(),
+            websocket_port: ws.local_addr().unwrap().port(),
+            webserver_port: http.local_addr().unwrap().port(),
+            ..Default::default()
+        });
+
+        let (outgoing, _) = broadcast::channel(100);
+        let mock = MockKodi {
+            server,
+            state: Arc::new(Mutex::new(MockState::default())),
+            outgoing,
+        };
+        mock.respond(
+            "Application.GetProperties",
+            json!({"volume": 100, "muted": false}),
+        );
+        mock.respond("Player.GetActivePlayers", json!([]));
+        mock.respond("JSONRPC.Ping", json!("pong"));
+        // What the HTTP transport polls in place of notifications
+        mock.respond("Playlist.GetProperties", json!({"size": 0}));
+        mock.respond(
+            "GUI.GetProperties",
+            json!({"currentwindow": {"id": 10000, "label": "Home"}}),
+        );
+        mock.respond(
+            "XBMC.GetInfoBooleans",
+            json!({"Library.IsScanningVideo": false}),
+        );
+        mock.respond(
+            "XBMC.GetInfoLabels",
+            json!({"Player.Chapter": "", "Player.ChapterCount": "", "Player.ChapterName": ""}),
+        );
+
+        tokio::spawn(accept_ws(ws, mock.state.clone(), mock.outgoing.clone()));
+        tokio::spawn(accept_http(http, mock.state.clone()));
+        mock
+    }
+
+    /// Always answer `method` with `result`
+    pub fn respond(&self, method: &str, result: Value) {
+        self.handle(method, move |_| Ok(result.clone()));
+    }
+
+    /// Answer `method` based on its params, Err becomes an "Invalid params" style error
+    pub fn handle<F>(&self, method: &str, handler: F)
+    where
+        F: Fn(&Value) -> Result<Value, String> + Send + 'static,
+    {
+        let mut state = self.state.lock().unwrap();
+        state.handlers.insert(method.to_string(), Box::new(handler));
+    }
+
+    /// Never answer `method`
+    pub fn stall(&self, method: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.stalled.insert(method.to_string());
+    }
+
+    /// Params of every call to `method` so far
+    pub fn calls(&self, method: &str) -> Vec<Value> {
+        let state = self.state.lock().unwrap();
+        state
+            .calls
+            .iter()
+            .filter(|(m, _)| m == method)
+            .map(|(_, params)| params.clone())
+            .collect()
+    }
+
+    /// Served at `/image/<urlencoded path>`, the same path kodi's art fields hold
+    pub fn add_image(&self, path: &str, bytes: Vec<u8>) {
+        let mut state = self.state.lock().unwrap();
+        state.images.insert(path.to_string(), bytes);
+    }
+
+    /// Decoded image paths requested so far, including ones that 404'd
+    pub fn image_requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().image_requests.clone()
+    }
+
+    /// Http requests without these credentials get a 401
+    pub fn require_auth(&self, username: &str, password: &str) {
+        let credentials = BASE64.encode(format!("{}:{}", username, password));
+        self.state.lock().unwrap().auth = Some(format!("Basic {}", credentials));
+    }
+
+    /// Websocket connections accepted so far
+    pub fn connections(&self) -> usize {
+        self.state.lock().unwrap().connections
+    }
+
+    /// Sends a notification to every connected client, shaped like kodi's
+    pub fn notify(&self, method: &str, data: Value) {
+        let msg = json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": {"sender": "xbmc", "data": data},
+        });
+        let _ = self.outgoing.send(Outgoing::Notification(msg.to_string()));
+    }
+
+    /// Closes every open connection, new connections are still accepted
+    pub fn disconnect(&self) {
+        let _ = self.outgoing.send(Outgoing::Disconnect);
+    }
+
+    /// Starts a real client against this mock and waits for it to connect
+    pub async fn connect_client(&self) -> (client::Connection, Receiver<Event>) {
+        self.connect_client_to(self.server.as_ref().clone()).await
+    }
+
+    /// Same as connect_client with a changed server, e.g. another transport
+    pub async fn connect_client_to(
+        &self,
+        server: KodiServer,
+    ) -> (client::Connection, Receiver<Event>) {
+        let (tx, mut rx) = channel(100);
+        tokio::spawn(client::connect(Arc::new(server), tx, Backoff::default()));
+        let Event::Connected(connection) =
+            wait_for(&mut rx, |e| matches!(e, Event::Connected(_))).await
+        else {
+            unreachable!()
+        };
+        (connection, rx)
+    }
+}
+
+/// Skips events (mostly the status polling) until one matches, panics after 5s
+pub async fn wait_for<F>(rx: &mut Receiver<Event>, matches: F) -> Event
+where
+    F: Fn(&Event) -> bool,
+{
+    let find = async {
+        while let Some(event) = rx.next().await {
+            if matches(&event) {
+                return event;
+            }
+        }
+        panic!("Client event stream ended");
+    };
+    tokio::time::timeout(Duration::from_secs(5), find)
+        .await
+        .expect("Timed out waiting for client event")
+}
+
+async fn accept_ws(
+    listener: TcpListener,
+    state: Arc<Mutex<MockState>>,
+    outgoing: broadcast::Sender<Outgoing>,
+) {
+    while let Ok((socket, _)) = listener.accept().await {
+        state.lock().unwrap().connections += 1;
+        tokio::spawn(handle_ws(socket, state.clone(), outgoing.subscribe()));
+    }
+}
+
+async fn handle_ws(
+    socket: TcpStream,
+    state: Arc<Mutex<MockState>>,
+    mut outgoing: broadcast::Receiver<Outgoing>,
+) {
+    let mut server = Server::new(socket.compat());
+    let key = match server.receive_request().await {
+        Ok(req) => req.key(),
+        Err(_) => return,
+    };
+    let accept = Response::Accept {
+        key,
+        protocol: None,
+    };
+    if server.send_response(&accept).await.is_err() {
+        return;
+    }
+    let (mut sender, mut receiver) = server.into_builder().finish();
+
+    // soketto's futures aren't cancel safe so reading gets its own task
+    let (reply_tx, mut reply_rx) = mpsc::unbounded_channel();
+    let reader = tokio::spawn(async move {
+        let mut data = Vec::new();
+        while receiver.receive_data(&mut data).await.is_ok() {
+            let reply = handle_request(&state, &data);
+            data.clear();
+            if let Some(reply) = reply
+                && reply_tx.send(reply).is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    loop {
+        tokio::select! {
+            Some(reply) = reply_rx.recv() => {
+                if sender.send_text(reply).await.is_err() || sender.flush().await.is_err() {
+                    break;
+                }
+            }
+            msg = outgoing.recv() => match msg {
+                Ok(Outgoing::Notification(text)) => {
+                    if sender.send_text(text).await.is_err() || sender.flush().await.is_err() {
+                        break;
+                    }
+                }
+                Ok(Outgoing::Disconnect) | Err(_) => {
+                    let _ = sender.close().await;
+                    break;
+                }
+            },
+            else => break,
+        }
+    }
+    reader.abort();
+}
+
+fn handle_request(state: &Mutex<MockState>, data: &[u8]) -> Option<String> {
+    let request: Value = serde_json::from_slice(data).ok()?;
+    let method = request["method"].as_str()?.to_string();
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    let mut state = state.lock().unwrap();
+    state.calls.push((method.clone(), params.clone()));
+    if state.stalled.contains(&method) {
+        return None;
+    }
+    let result = match state.handlers.get(&method) {
+        Some(handler) => handler(&params).map_err(|message| (-32602, message)),
+        None => Err((-32601, "Method not found.".to_string())),
+    };
+
+    let reply = match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": request["id"], "result": result}),
+        Err((code, message)) => json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "error": {"code": code, "message": message},
+        }),
+    };
+    Some(reply.to_string())
+}
+
+// Just enough http for reqwest, one request per connection
+async fn accept_http(listener: TcpListener, state: Arc<Mutex<MockState>>) {
+    while let Ok((mut socket, _)) = listener.accept().await {
+        let state = state.clone();
+        tokio::spawn(async move {
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            let header_end = loop {
+                if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break end + 4;
+                }
+                match socket.read(&mut buf).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(n) => request.extend_from_slice(&buf[..n]),
+                }
+            };
+
+            let head = String::from_utf8_lossy(&request[..header_end]).into_owned();
+            let content_length = head
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+                .unwrap_or_default();
+            while request.len() < header_end + content_length {
+                match socket.read(&mut buf).await {
+                    Ok(0) | Err(_) => return,
+                    Ok(n) => request.extend_from_slice(&buf[..n]),
+                }
+            }
+
+            let required = state.lock().unwrap().auth.clone();
+            if let Some(required) = required {
+                let authorized =
+                    head.lines()
+                        .filter_map(|line| line.split_once(':'))
+                        .any(|(name, value)| {
+                            name.eq_ignore_ascii_case("authorization") && value.trim() == required
+                        });
+                if !authorized {
+                    let _ = socket
+                        .write_all(b"HTTP/1.1 401 Unauthorized\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                        .await;
+                    let _ = socket.shutdown().await;
+                    return;
+                }
+            }
+
+            let path = head.split_whitespace().nth(1).unwrap_or_default();
+            if path == "/jsonrpc" {
+                let Some(reply) = handle_request(&state, &request[header_end..]) else {
+                    // Stalled, hang on to the connection without answering
+                    return std::future::pending().await;
+                };
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    reply.len(),
+                    reply
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.shutdown().await;
+                return;
+            }
+
+            let image = path
+                .strip_prefix("/image/")
+                .and_then(|p| urlencoding::decode(p).ok())
+                .map(|p| p.into_owned());
+
+            let body = {
+                let mut state = state.lock().unwrap();
+                state
+                    .image_requests
+                    .push(image.clone().unwrap_or(path.to_string()));
+                image.and_then(|p| state.images.get(&p).cloned())
+            };
+
+            let response = match body {
+                Some(body) => {
+                    let mut response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        body.len()
+                    )
+                    .into_bytes();
+                    response.extend(body);
+                    response
+                }
+                None => b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                    .to_vec(),
+            };
+            let _ = socket.write_all(&response).await;
+            let _ = socket.shutdown().await;
+        });
+    }
+}
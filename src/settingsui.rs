@@ -2,25 +2,45 @@ use iced::Color;
 use iced::Element;
 use iced::Task as Command;
 use iced::widget::space;
-use iced::widget::{button, column, row, text, text_input};
-use std::net::IpAddr;
-use std::sync::Arc;
+use iced::widget::{button, column, container, pick_list, row, rule, text, text_input};
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
 
-use crate::koditypes::KodiServer;
+use crate::discovery::Discovered;
+use crate::koditypes::{Backoff, KodiServer, Transport};
+use crate::wol;
 
 #[derive(Clone)]
 pub struct Settings {
-    // Serverlist/ etc to select servers eventually
+    servers: Vec<KodiServer>,
+    active_id: Option<u8>,
+    // Some while the form is visible, the id is the server being edited or the new id
+    edit_server_id: Option<u8>,
+    edit_server_db_id: u8,
     edit_server_name: String,
     edit_server_ip: String,
     edit_server_ws_port: String,
     edit_server_http_port: String,
     edit_server_username: String,
     edit_server_password: String,
+    // See KodiServer.locked_password
+    edit_server_locked_password: Option<String>,
+    edit_server_transport: Transport,
+    edit_server_mac: String,
+    edit_server_broadcast: String,
     name_is_valid: bool,
     ip_is_valid: bool,
     ws_port_is_valid: bool,
     http_port_is_valid: bool,
+    mac_is_valid: bool,
+    broadcast_is_valid: bool,
+    discovering: bool,
+    discovered: Vec<Discovered>,
+    discover_error: Option<String>,
+    // Seconds, for every server
+    reconnect_min: String,
+    reconnect_max: String,
+    backoff: Backoff,
 }
 
 #[derive(Debug, Clone)]
@@ -31,47 +51,169 @@ pub enum Message {
     ServerHttpPortChanged(String),
     ServerUnChanged(String),
     ServerPwChanged(String),
+    ServerTransportChanged(Transport),
+    ServerMacChanged(String),
+    ServerBroadcastChanged(String),
     SubmitForm,
     Cancel,
+    NewServer,
+    EditServer(u8),
+    SelectServer(u8),
+    DeleteServer(u8),
+    Close,
+    Discover,
+    Discovered(Result<Vec<Discovered>, String>),
+    UseDiscovered(usize),
+    ReconnectMinChanged(String),
+    ReconnectMaxChanged(String),
+    SaveReconnect,
 }
 
 #[derive(Debug, Clone)]
 pub enum Event {
     AddServer(KodiServer),
+    SelectServer(u8),
+    DeleteServer(u8),
+    Discover,
+    SetBackoff { min: Duration, max: Duration },
     Cancel,
 }
 
 impl Settings {
-    pub fn new() -> Self {
-        Settings {
-            edit_server_name: "kodi".to_string(),
-            edit_server_ip: "127.0.0.1".to_string(),
-            edit_server_ws_port: "9090".to_string(),
-            edit_server_http_port: "8080".to_string(),
-            edit_server_username: "".to_string(),
-            edit_server_password: "".to_string(),
+    pub fn new(servers: Vec<KodiServer>, active_id: Option<u8>, backoff: Backoff) -> Self {
+        let mut settings = Settings {
+            servers,
+            active_id,
+            edit_server_id: None,
+            edit_server_db_id: 0,
+            edit_server_name: String::new(),
+            edit_server_ip: String::new(),
+            edit_server_ws_port: String::new(),
+            edit_server_http_port: String::new(),
+            edit_server_username: String::new(),
+            edit_server_password: String::new(),
+            edit_server_locked_password: None,
+            edit_server_transport: Transport::Auto,
+            edit_server_mac: String::new(),
+            edit_server_broadcast: String::new(),
             name_is_valid: true,
             ip_is_valid: true,
             ws_port_is_valid: true,
             http_port_is_valid: true,
+            mac_is_valid: true,
+            broadcast_is_valid: true,
+            discovering: false,
+            discovered: Vec::new(),
+            discover_error: None,
+            reconnect_min: backoff.min.as_secs().to_string(),
+            reconnect_max: backoff.max.as_secs().to_string(),
+            backoff,
+        };
+        // Nothing to pick from yet so go straight to the form
+        if settings.servers.is_empty() {
+            settings.new_server();
         }
+        settings
     }
 
-    pub fn load(server: Arc<KodiServer>) -> Self {
-        Settings {
-            edit_server_name: server.name.clone(),
-            edit_server_ip: server.ip.clone(),
-            edit_server_ws_port: server.websocket_port.to_string(),
-            edit_server_http_port: server.webserver_port.to_string(),
-            edit_server_username: server.username.clone(),
-            edit_server_password: server.password.clone(),
-            name_is_valid: true,
-            ip_is_valid: true,
-            ws_port_is_valid: true,
-            http_port_is_valid: true,
+    pub fn set_servers(&mut self, servers: Vec<KodiServer>, active_id: Option<u8>) {
+        self.servers = servers;
+        self.active_id = active_id;
+        if self.servers.is_empty() && self.edit_server_id.is_none() {
+            self.new_server();
         }
     }
 
+    pub fn set_backoff(&mut self, backoff: Backoff) {
+        self.reconnect_min = backoff.min.as_secs().to_string();
+        self.reconnect_max = backoff.max.as_secs().to_string();
+        self.backoff = backoff;
+    }
+
+    // Both whole seconds, at least 1 and min <= max
+    fn reconnect_range(&self) -> Option<(Duration, Duration)> {
+        let min = self.reconnect_min.parse::<u64>().ok().filter(|&s| s > 0)?;
+        let max = self
+            .reconnect_max
+            .parse::<u64>()
+            .ok()
+            .filter(|&s| s >= min)?;
+        Some((Duration::from_secs(min), Duration::from_secs(max)))
+    }
+
+    fn new_server(&mut self) {
+        // New servers get their own library db. Ids freed by a delete are
+        // fine to reuse, the library db goes along with the server.
+        let Some(id) =
+            (0..=u8::MAX).find(|&id| !self.servers.iter().any(|s| s.id == id || s.db_id == id))
+        else {
+            // Every id is taken
+            return;
+        };
+        self.edit_server_id = Some(id);
+        self.edit_server_db_id = id;
+        self.edit_server_name = "kodi".to_string();
+        self.edit_server_ip = "127.0.0.1".to_string();
+        self.edit_server_ws_port = "9090".to_string();
+        self.edit_server_http_port = "8080".to_string();
+        self.edit_server_username = "".to_string();
+        self.edit_server_password = "".to_string();
+        self.edit_server_locked_password = None;
+        self.edit_server_transport = Transport::Auto;
+        self.edit_server_mac = "".to_string();
+        self.edit_server_broadcast = "".to_string();
+        self.name_is_valid = true;
+        self.ip_is_valid = true;
+        self.ws_port_is_valid = true;
+        self.http_port_is_valid = true;
+        self.mac_is_valid = true;
+        self.broadcast_is_valid = true;
+    }
+
+    fn load(&mut self, server: &KodiServer) {
+        self.edit_server_id = Some(server.id);
+        self.edit_server_db_id = server.db_id;
+        self.edit_server_name = server.name.clone();
+        self.edit_server_ip = server.ip.clone();
+        self.edit_server_ws_port = server.websocket_port.to_string();
+        self.edit_server_http_port = server.webserver_port.to_string();
+        self.edit_server_username = server.username.clone();
+        self.edit_server_password = server.password.clone();
+        self.edit_server_locked_password = server.locked_password.clone();
+        self.edit_server_transport = server.transport;
+        self.edit_server_mac = server.mac.clone();
+        self.edit_server_broadcast = server.broadcast.clone();
+        self.name_is_valid = true;
+        self.ip_is_valid = true;
+        self.ws_port_is_valid = true;
+        self.http_port_is_valid = true;
+        self.mac_is_valid = true;
+        self.broadcast_is_valid = true;
+    }
+
+    // Fills the form, an edit in progress keeps its name and login
+    fn use_discovered(&mut self, found: &Discovered) {
+        if self.edit_server_id.is_none() {
+            self.new_server();
+            self.edit_server_name = found.name.clone();
+        }
+        self.edit_server_ip = found.ip.to_string();
+        if let Some(port) = found.websocket_port {
+            self.edit_server_ws_port = port.to_string();
+        }
+        if let Some(port) = found.webserver_port {
+            self.edit_server_http_port = port.to_string();
+        }
+        // Only the web server is advertised when tcp control is off
+        if found.websocket_port.is_none() {
+            self.edit_server_transport = Transport::Http;
+        }
+        self.name_is_valid = !self.edit_server_name.is_empty();
+        self.ip_is_valid = true;
+        self.ws_port_is_valid = true;
+        self.http_port_is_valid = true;
+    }
+
     pub fn update(&mut self, message: Message) -> Command<Event> {
         match message {
             Message::ServerNameChanged(name) => {
@@ -95,6 +237,16 @@ impl Settings {
             }
             Message::ServerUnChanged(un) => self.edit_server_username = un,
             Message::ServerPwChanged(pw) => self.edit_server_password = pw,
+            Message::ServerTransportChanged(transport) => self.edit_server_transport = transport,
+            // Both optional
+            Message::ServerMacChanged(mac) => {
+                self.mac_is_valid = mac.is_empty() || wol::parse_mac(&mac).is_some();
+                self.edit_server_mac = mac
+            }
+            Message::ServerBroadcastChanged(addr) => {
+                self.broadcast_is_valid = addr.is_empty() || addr.parse::<Ipv4Addr>().is_ok();
+                self.edit_server_broadcast = addr
+            }
             Message::SubmitForm => {
                 let ws_port: u16 = self
                     .edit_server_ws_port
@@ -104,24 +256,195 @@ impl Settings {
                     .edit_server_http_port
                     .parse()
                     .expect("String should already be validated");
-                let server = KodiServer::new(
-                    self.edit_server_name.clone(),
-                    self.edit_server_ip.clone(),
-                    ws_port,
-                    http_port,
-                    self.edit_server_username.clone(),
-                    self.edit_server_password.clone(),
-                );
+                let server = KodiServer {
+                    id: self.edit_server_id.expect("Form should be visible"),
+                    db_id: self.edit_server_db_id,
+                    transport: self.edit_server_transport,
+                    mac: self.edit_server_mac.trim().to_string(),
+                    broadcast: self.edit_server_broadcast.clone(),
+                    locked_password: self.edit_server_locked_password.clone(),
+                    ..KodiServer::new(
+                        self.edit_server_name.clone(),
+                        self.edit_server_ip.clone(),
+                        ws_port,
+                        http_port,
+                        self.edit_server_username.clone(),
+                        self.edit_server_password.clone(),
+                    )
+                };
+                self.edit_server_id = None;
                 return Command::perform(async {}, move |_| Event::AddServer(server.clone()));
             }
+            Message::NewServer => self.new_server(),
+            Message::EditServer(id) => {
+                if let Some(server) = self.servers.iter().find(|s| s.id == id).cloned() {
+                    self.load(&server);
+                }
+            }
+            Message::SelectServer(id) => {
+                return Command::perform(async {}, move |_| Event::SelectServer(id));
+            }
+            Message::DeleteServer(id) => {
+                if self.edit_server_id == Some(id) {
+                    self.edit_server_id = None;
+                }
+                return Command::perform(async {}, move |_| Event::DeleteServer(id));
+            }
             Message::Cancel => {
+                if self.servers.is_empty() {
+                    return Command::perform(async {}, |_| Event::Cancel);
+                }
+                self.edit_server_id = None;
+            }
+            Message::Close => {
                 return Command::perform(async {}, |_| Event::Cancel);
             }
+            Message::Discover => {
+                self.discovering = true;
+                self.discover_error = None;
+                return Command::perform(async {}, |_| Event::Discover);
+            }
+            Message::Discovered(found) => {
+                self.discovering = false;
+                match found {
+                    Ok(found) => {
+                        if found.is_empty() {
+                            self.discover_error = Some("No Kodi found".to_string());
+                        }
+                        self.discovered = found;
+                    }
+                    Err(err) => self.discover_error = Some(err),
+                }
+            }
+            Message::ReconnectMinChanged(secs) => self.reconnect_min = secs,
+            Message::ReconnectMaxChanged(secs) => self.reconnect_max = secs,
+            Message::SaveReconnect => {
+                if let Some((min, max)) = self.reconnect_range() {
+                    return Command::perform(async {}, move |_| Event::SetBackoff { min, max });
+                }
+            }
+            Message::UseDiscovered(index) => {
+                if let Some(found) = self.discovered.get(index).cloned() {
+                    self.use_discovered(&found);
+                }
+            }
         }
         Command::none()
     }
 
     pub fn view<'a>(&'a self) -> Element<'a, Message> {
+        let servers = self.servers.iter().map(|server| {
+            let active = self.active_id == Some(server.id);
+            row![
+                text(format!("{} ({})", server.name, server.ip)),
+                space::horizontal(),
+                if active {
+                    button("Active")
+                } else {
+                    button("Connect").on_press(Message::SelectServer(server.id))
+                },
+                button("Edit").on_press(Message::EditServer(server.id)),
+                button("Delete").on_press(Message::DeleteServer(server.id)),
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center)
+            .into()
+        });
+
+        let mut content = column![
+            row![
+                text("Servers").size(20),
+                space::horizontal(),
+                if self.discovering {
+                    button("Searching...")
+                } else {
+                    button("Discover").on_press(Message::Discover)
+                },
+                button("Add server").on_press(Message::NewServer),
+                button("Close").on_press(Message::Close),
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center),
+            rule::horizontal(2),
+            column(servers).spacing(5),
+        ]
+        .spacing(10)
+        .padding(10);
+
+        if !self.discovered.is_empty() || self.discover_error.is_some() {
+            content = content
+                .push(rule::horizontal(2))
+                .push(self.discovered_list());
+        }
+
+        if self.edit_server_id.is_some() {
+            content = content.push(rule::horizontal(2)).push(self.server_form());
+        }
+
+        content = content
+            .push(rule::horizontal(2))
+            .push(self.reconnect_form());
+
+        container(content).into()
+    }
+
+    fn discovered_list<'a>(&'a self) -> Element<'a, Message> {
+        let port = |port: Option<u16>| port.map_or("-".to_string(), |p| p.to_string());
+        let found = self.discovered.iter().enumerate().map(|(index, found)| {
+            row![
+                text(format!(
+                    "{} ({}, websocket {}, web {})",
+                    found.name,
+                    found.ip,
+                    port(found.websocket_port),
+                    port(found.webserver_port)
+                )),
+                space::horizontal(),
+                button("Use").on_press(Message::UseDiscovered(index)),
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center)
+            .into()
+        });
+
+        let mut list = column![text("Found on the network").size(16)].spacing(5);
+        if let Some(err) = &self.discover_error {
+            list = list.push(text(err));
+        }
+        list.push(column(found).spacing(5)).into()
+    }
+
+    fn reconnect_form<'a>(&'a self) -> Element<'a, Message> {
+        let red = Color::from_rgb8(255, 0, 0);
+        let range = self.reconnect_range();
+        let changed =
+            range.is_some_and(|(min, max)| min != self.backoff.min || max != self.backoff.max);
+        row![
+            if range.is_some() {
+                text("Reconnect delay (seconds), from")
+            } else {
+                text("Reconnect delay (seconds), from").color(red)
+            },
+            text_input("1", &self.reconnect_min)
+                .on_input(Message::ReconnectMinChanged)
+                .width(60),
+            text("doubling up to"),
+            text_input("60", &self.reconnect_max)
+                .on_input(Message::ReconnectMaxChanged)
+                .width(60),
+            space::horizontal(),
+            if changed {
+                button("Apply").on_press(Message::SaveReconnect)
+            } else {
+                button("Apply")
+            },
+        ]
+        .spacing(10)
+        .align_y(iced::Alignment::Center)
+        .into()
+    }
+
+    fn server_form<'a>(&'a self) -> Element<'a, Message> {
         let red = Color::from_rgb8(255, 0, 0);
         column![
             if self.name_is_valid {
@@ -152,7 +475,36 @@ impl Settings {
             text("Username:"),
             text_input("", &self.edit_server_username).on_input(Message::ServerUnChanged),
             text("Password"),
-            text_input("", &self.edit_server_password).on_input(Message::ServerPwChanged),
+            text_input(
+                if self.edit_server_locked_password.is_some() {
+                    "Saved password can't be read, enter it again"
+                } else {
+                    ""
+                },
+                &self.edit_server_password
+            )
+            .secure(true)
+            .on_input(Message::ServerPwChanged),
+            text("Connection:"),
+            pick_list(
+                Transport::ALL,
+                Some(self.edit_server_transport),
+                Message::ServerTransportChanged
+            ),
+            if self.mac_is_valid {
+                text("Wake-on-LAN MAC address (optional):")
+            } else {
+                text("Wake-on-LAN MAC address (optional):").color(red)
+            },
+            text_input("aa:bb:cc:dd:ee:ff", &self.edit_server_mac)
+                .on_input(Message::ServerMacChanged),
+            if self.broadcast_is_valid {
+                text("Wake-on-LAN broadcast address (optional):")
+            } else {
+                text("Wake-on-LAN broadcast address (optional):").color(red)
+            },
+            text_input("255.255.255.255", &self.edit_server_broadcast)
+                .on_input(Message::ServerBroadcastChanged),
             row![
                 space::horizontal(),
                 button("Cancel").on_press(Message::Cancel),
@@ -160,6 +512,8 @@ impl Settings {
                     && self.ws_port_is_valid
                     && self.http_port_is_valid
                     && self.name_is_valid
+                    && self.mac_is_valid
+                    && self.broadcast_is_valid
                 {
                     button("Save").on_press(Message::SubmitForm)
                 } else {
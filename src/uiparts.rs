@@ -1,4 +1,5 @@
 use iced::Color;
+use iced::ContentFit;
 use iced::Element;
 use iced::Length;
 use iced::widget::space;
@@ -6,15 +7,16 @@ use iced::widget::stack;
 
 use iced::font::{Family, Font, Weight};
 use iced::widget::{
-    Checkbox, Slider, Space, button, column, container, image, pick_list, row, rule, scrollable,
-    text, text_input,
+    Checkbox, Slider, Space, button, column, container, image, mouse_area, pick_list, progress_bar,
+    row, rule, scrollable, text, text_input,
 };
 
 use super::BLANK_IMAGE;
 use super::ITEM_HEIGHT;
 use super::Krustmote;
-use super::{ListData, Message, Modals, State};
+use super::{ItemInfo, ListData, Message, Modals, State, Toast};
 
+use crate::client;
 use crate::data;
 use crate::icons;
 use crate::koditypes::*;
@@ -47,13 +49,22 @@ pub(crate) fn make_subtitle_modal<'a>(
             .width(Length::Fill),
             Checkbox::new(krustmote.kodi_status.player_props.subtitleenabled)
                 .on_toggle(Message::SubtitleToggle),
-        ],
+            button("Next").on_press_maybe(
+                krustmote
+                    .kodi_status
+                    .active_player_id
+                    .map(|id| Message::KodiReq(KodiCommand::PlayerNextSubtitle(id)))
+            ),
+        ]
+        .spacing(5)
+        .align_y(iced::Alignment::Center),
+        // Kodi steps these 0.1s at a time and shows the offset on screen
         row![
-            button("-").on_press(Message::KodiReq(KodiCommand::InputExecuteAction(
+            button("-0.1s").on_press(Message::KodiReq(KodiCommand::InputExecuteAction(
                 "subtitledelayminus"
             ))),
-            text(" Delay "),
-            button("+").on_press(Message::KodiReq(KodiCommand::InputExecuteAction(
+            text(" Subtitle delay "),
+            button("+0.1s").on_press(Message::KodiReq(KodiCommand::InputExecuteAction(
                 "subtitledelayplus"
             )))
         ]
@@ -87,11 +98,11 @@ pub(crate) fn make_audio_modal<'a>(
         .placeholder("No Audio")
         .width(Length::Fill),
         row![
-            button("-").on_press(Message::KodiReq(KodiCommand::InputExecuteAction(
+            button("-0.1s").on_press(Message::KodiReq(KodiCommand::InputExecuteAction(
                 "audiodelayminus"
             ))),
-            text(" Delay "),
-            button("+").on_press(Message::KodiReq(KodiCommand::InputExecuteAction(
+            text(" Audio delay "),
+            button("+0.1s").on_press(Message::KodiReq(KodiCommand::InputExecuteAction(
                 "audiodelayplus"
             )))
         ]
@@ -102,6 +113,201 @@ pub(crate) fn make_audio_modal<'a>(
     .style(|_| container::Style::default().background(iced::Theme::Dracula.palette().background))
 }
 
+// Angles or versions, most files only have the one
+pub(crate) fn make_video_modal<'a>(
+    krustmote: &'a Krustmote,
+) -> iced::widget::Container<'a, Message> {
+    container(column![
+        row![
+            text("Video").height(40),
+            space::horizontal(),
+            button("x").on_press(Message::ShowModal(crate::Modals::None)),
+        ],
+        rule::horizontal(5),
+        pick_list(
+            &*krustmote.kodi_status.player_props.videostreams,
+            krustmote
+                .kodi_status
+                .player_props
+                .currentvideostream
+                .clone(),
+            Message::VideoStreamPicked
+        )
+        .placeholder("No Video")
+        .width(Length::Fill),
+    ])
+    .width(500)
+    .padding(10)
+    .style(|_| container::Style::default().background(iced::Theme::Dracula.palette().background))
+}
+
+pub(crate) fn make_queue_modal<'a>(
+    krustmote: &'a Krustmote,
+) -> iced::widget::Container<'a, Message> {
+    let playlist_id = krustmote.queue.playlist_id;
+    let status = &krustmote.kodi_status;
+
+    // Position of the playing item, if it's playing from this playlist
+    let current = status
+        .active_player_id
+        .filter(|_| status.player_props.playlistid == playlist_id as i8)
+        .map(|player_id| (player_id, status.player_props.position));
+
+    let play_from = |position: u32| match current {
+        Some((player_id, _)) => KodiCommand::PlayerGoTo {
+            player_id,
+            position,
+        },
+        None => KodiCommand::PlayerOpenPlaylist {
+            playlist_id,
+            position,
+        },
+    };
+
+    let last = krustmote.queue.items.len().saturating_sub(1) as u32;
+    let items = krustmote.queue.items.iter().enumerate().map(|(i, title)| {
+        let position = i as u32;
+        let is_current = current.is_some_and(|(_, pos)| pos == i as i32);
+        let swap = |to: u32| {
+            krustmote.kodi_req(KodiCommand::PlaylistSwap {
+                playlist_id,
+                position1: position,
+                position2: to,
+            })
+        };
+        let mut title = text(title.as_str())
+            .size(14)
+            .shaping(text::Shaping::Advanced)
+            .wrapping(text::Wrapping::None);
+        if is_current {
+            title = title.font(Font {
+                family: Family::SansSerif,
+                weight: Weight::Bold,
+                ..Default::default()
+            });
+        }
+
+        row![
+            button(
+                row![
+                    if is_current {
+                        icons::play_arrow()
+                    } else {
+                        text(" ")
+                    },
+                    title
+                ]
+                .spacing(5)
+            )
+            .on_press_maybe(krustmote.kodi_req(play_from(position)))
+            .width(Length::Fill)
+            .style(themes::listitem),
+            button(icons::arrow_upward())
+                .on_press_maybe((position > 0).then(|| swap(position - 1)).flatten())
+                .style(themes::bare_button),
+            button(icons::arrow_downward())
+                .on_press_maybe((position < last).then(|| swap(position + 1)).flatten())
+                .style(themes::bare_button),
+            // Kodi refuses to remove the playing item
+            button(icons::delete())
+                .on_press_maybe(
+                    krustmote
+                        .kodi_req(KodiCommand::PlaylistRemove {
+                            playlist_id,
+                            position,
+                        })
+                        .filter(|_| !is_current),
+                )
+                .style(themes::bare_button),
+        ]
+        .spacing(5)
+        .align_y(iced::Alignment::Center)
+        .into()
+    });
+
+    let tab = |label, id: u8| {
+        button(label)
+            .on_press_maybe((id != playlist_id).then_some(Message::ShowQueue(id)))
+            .style(themes::bare_button)
+    };
+
+    container(column![
+        row![
+            text("Queue").height(40),
+            Space::new().width(20),
+            tab("Video", VIDEO_PLAYLIST),
+            tab("Music", MUSIC_PLAYLIST),
+            space::horizontal(),
+            button("Clear").on_press_maybe(
+                krustmote
+                    .kodi_req(KodiCommand::PlaylistClear(playlist_id))
+                    .filter(|_| !krustmote.queue.items.is_empty())
+            ),
+            button("x").on_press(Message::ShowModal(crate::Modals::None)),
+        ]
+        .spacing(5)
+        .align_y(iced::Alignment::Center),
+        rule::horizontal(5),
+        if krustmote.queue.items.is_empty() {
+            Element::from(text("Nothing queued").width(Length::Fill).center())
+        } else {
+            scrollable(column(items).spacing(1)).height(400).into()
+        },
+    ])
+    .width(600)
+    .padding(10)
+    .style(|_| container::Style::default().background(iced::Theme::Dracula.palette().background))
+}
+
+pub(crate) fn make_resume_modal<'a>(
+    file: &'a str,
+    resume: &'a ResumePoint,
+) -> iced::widget::Container<'a, Message> {
+    container(
+        column![
+            text(get_filename(file)).height(40),
+            rule::horizontal(5),
+            button(text(format!("Resume from {}", resume.as_time())))
+                .on_press(Message::HideModalAndKodiReq(KodiCommand::PlayerOpenResume(
+                    file.to_string()
+                )))
+                .width(Length::Fill)
+                .style(themes::listitem),
+            button("Play from start")
+                .on_press(Message::HideModalAndKodiReq(KodiCommand::PlayerOpen(
+                    file.to_string()
+                )))
+                .width(Length::Fill)
+                .style(themes::listitem),
+        ]
+        .spacing(1),
+    )
+    .width(400)
+    .padding(10)
+    .style(|_| container::Style::default().background(iced::Theme::Dracula.palette().background))
+}
+
+pub(crate) fn make_context_modal<'a>(
+    title: &'a str,
+    actions: &'a [MenuLink],
+) -> iced::widget::Container<'a, Message> {
+    container(column![
+        text(title).height(40).shaping(text::Shaping::Advanced),
+        rule::horizontal(5),
+        column(actions.iter().map(|action| {
+            button(text(action.label.as_str()))
+                .on_press(Message::ContextAction(Box::new(action.on_click.clone())))
+                .width(Length::Fill)
+                .style(themes::listitem)
+                .into()
+        }))
+        .spacing(1),
+    ])
+    .width(300)
+    .padding(10)
+    .style(|_| container::Style::default().background(iced::Theme::Dracula.palette().background))
+}
+
 pub(crate) fn request_text_modal<'a>(
     krustmote: &'a Krustmote,
 ) -> iced::widget::Container<'a, Message> {
@@ -130,7 +336,7 @@ pub(crate) fn playing_bar<'a>(krustmote: &'a Krustmote) -> Element<'a, Message>
     let now = chrono::offset::Local::now();
     let end = now + chrono::Duration::seconds(timeleft as i64);
     let end = end.format("%I:%M %p");
-    if krustmote.kodi_status.active_player_id.is_some() {
+    if let Some(player_id) = krustmote.kodi_status.active_player_id {
         container(
             row![
                 Space::new().width(5).height(5),
@@ -140,56 +346,77 @@ pub(crate) fn playing_bar<'a>(krustmote: &'a Krustmote) -> Element<'a, Message>
                     row![
                         text(format!("{}", krustmote.kodi_status.player_props.time,)).size(14),
                         space::horizontal(),
+                        text(krustmote.kodi_status.player_props.tech_info()).size(14),
+                        space::horizontal(),
                         text(format!(
                             "{} ({end})",
                             krustmote.kodi_status.player_props.totaltime
                         ))
                         .size(14),
                     ],
-                    text(&krustmote.kodi_status.playing_title)
-                        .font(Font {
-                            family: Family::SansSerif,
-                            weight: Weight::Bold,
-                            ..Default::default()
-                        })
-                        .shaping(text::Shaping::Advanced)
-                        .wrapping(text::Wrapping::None)
-                        .height(20),
+                    button(
+                        row![
+                            if krustmote.now_playing.open {
+                                icons::expand_more()
+                            } else {
+                                icons::expand_less()
+                            },
+                            text(&krustmote.kodi_status.playing_title)
+                                .font(Font {
+                                    family: Family::SansSerif,
+                                    weight: Weight::Bold,
+                                    ..Default::default()
+                                })
+                                .shaping(text::Shaping::Advanced)
+                                .wrapping(text::Wrapping::None),
+                        ]
+                        .spacing(5)
+                    )
+                    .padding(0)
+                    .height(20)
+                    .style(themes::bare_button)
+                    .on_press(Message::ToggleNowPlaying),
                 ]
                 .width(Length::FillPortion(55)),
                 row![
                     space::horizontal(),
+                    player_picker(krustmote),
                     button(icons::skip_previous().size(32).height(48))
                         .style(themes::bare_button)
-                        .on_press(Message::KodiReq(KodiCommand::InputExecuteAction(
-                            "skipprevious"
-                        ))),
+                        .on_press(Message::KodiReq(KodiCommand::PlayerSkip {
+                            player_id,
+                            direction: "previous"
+                        })),
                     button(icons::fast_rewind().size(32).height(48))
                         .style(themes::bare_button)
-                        .on_press(Message::KodiReq(KodiCommand::InputExecuteAction("rewind"))),
+                        .on_press(Message::KodiReq(KodiCommand::PlayerSetSpeed {
+                            player_id,
+                            speed: "decrement"
+                        })),
                     button(if krustmote.kodi_status.player_props.speed != 0.0 {
                         icons::pause_clircle_filled().size(48)
                     } else {
                         icons::play_circle_filled().size(48)
                     })
-                    .on_press(Message::KodiReq(KodiCommand::InputExecuteAction(
-                        "playpause"
-                    )))
+                    .on_press(Message::KodiReq(KodiCommand::PlayerPlayPause(player_id)))
                     .style(themes::bare_button),
                     button(icons::fast_forward().size(32).height(48))
                         .style(themes::bare_button)
-                        .on_press(Message::KodiReq(KodiCommand::InputExecuteAction(
-                            "fastforward"
-                        ))),
+                        .on_press(Message::KodiReq(KodiCommand::PlayerSetSpeed {
+                            player_id,
+                            speed: "increment"
+                        })),
                     button(icons::skip_next().size(32).height(48))
                         .style(themes::bare_button)
-                        .on_press(Message::KodiReq(KodiCommand::InputExecuteAction(
-                            "skipnext"
-                        ))),
+                        .on_press(Message::KodiReq(KodiCommand::PlayerSkip {
+                            player_id,
+                            direction: "next"
+                        })),
                     button(icons::stop().size(32).height(48))
-                        .on_press(Message::KodiReq(KodiCommand::InputExecuteAction("stop")))
+                        .on_press(Message::KodiReq(KodiCommand::PlayerStop(player_id)))
                         .style(themes::bare_button),
                     Space::new().width(20).height(5),
+                    play_modes(krustmote, player_id),
                     column![
                         button(icons::subtitles())
                             .on_press(Message::ShowModal(Modals::Subtitles))
@@ -197,7 +424,9 @@ pub(crate) fn playing_bar<'a>(krustmote: &'a Krustmote) -> Element<'a, Message>
                         button(icons::hearing())
                             .on_press(Message::ShowModal(Modals::Audio))
                             .style(themes::bare_button),
-                        button(icons::videocam()).style(themes::bare_button),
+                        button(icons::videocam())
+                            .on_press(Message::ShowModal(Modals::Video))
+                            .style(themes::bare_button),
                     ],
                     Space::new().width(10).height(5),
                 ]
@@ -213,6 +442,90 @@ pub(crate) fn playing_bar<'a>(krustmote: &'a Krustmote) -> Element<'a, Message>
     }
 }
 
+// Shuffle, repeat and (music only) party mode, lit up when on
+fn play_modes<'a>(krustmote: &'a Krustmote, player_id: u8) -> Element<'a, Message> {
+    let props = &krustmote.kodi_status.player_props;
+    let mut modes = column![
+        toggle(
+            krustmote,
+            icons::shuffle(),
+            props.shuffled,
+            KodiCommand::PlayerSetShuffle {
+                player_id,
+                shuffle: !props.shuffled,
+            }
+        ),
+        toggle(
+            krustmote,
+            match props.repeat {
+                Repeat::One => icons::repeat_one(),
+                _ => icons::repeat(),
+            },
+            props.repeat != Repeat::Off,
+            KodiCommand::PlayerSetRepeat {
+                player_id,
+                repeat: props.repeat.next(),
+            }
+        ),
+    ];
+    if props.player_type == PlayerType::Audio {
+        modes = modes.push(toggle(
+            krustmote,
+            icons::celebration(),
+            props.partymode,
+            KodiCommand::PlayerSetPartymode {
+                player_id,
+                partymode: !props.partymode,
+            },
+        ));
+    }
+    modes.into()
+}
+
+// Disabled until kodi has answered, so a double click can't flip it back
+fn toggle<'a>(
+    krustmote: &Krustmote,
+    icon: iced::widget::Text<'a>,
+    on: bool,
+    cmd: KodiCommand,
+) -> Element<'a, Message> {
+    let style = if on {
+        button::primary
+    } else {
+        themes::bare_button
+    };
+    button(icon)
+        .style(style)
+        .on_press_maybe(krustmote.kodi_req(cmd))
+        .into()
+}
+
+// Only there when more than one thing is playing, e.g. music during a slideshow
+fn player_picker<'a>(krustmote: &'a Krustmote) -> Element<'a, Message> {
+    let players = &krustmote.kodi_status.players;
+    if players.len() < 2 {
+        return Space::new().into();
+    }
+    column(players.iter().map(|(id, player)| {
+        let icon = match player.props.player_type {
+            PlayerType::Video => icons::movie(),
+            PlayerType::Audio => icons::music_note(),
+            PlayerType::Picture => icons::photo(),
+        };
+        let style = if krustmote.kodi_status.active_player_id == Some(*id) {
+            button::primary
+        } else {
+            themes::bare_button
+        };
+        button(icon.size(20))
+            .style(style)
+            .on_press(Message::SelectPlayer(*id))
+            .into()
+    }))
+    .spacing(2)
+    .into()
+}
+
 pub(crate) fn top_bar<'a>(krustmote: &Krustmote) -> Element<'a, Message> {
     container(row![
         button(icons::menu())
@@ -243,19 +556,31 @@ pub(crate) fn top_bar<'a>(krustmote: &Krustmote) -> Element<'a, Message> {
 }
 
 pub(crate) fn center_area<'a>(krustmote: &'a Krustmote) -> Element<'a, Message> {
-    match krustmote.content_area {
+    if krustmote.now_playing.open && krustmote.kodi_status.active_player_id.is_some() {
+        return now_playing(krustmote);
+    }
+    match &krustmote.content_area {
         crate::ContentArea::Files => file_list(krustmote),
         crate::ContentArea::Loading => loading(krustmote),
+        crate::ContentArea::ItemInfo(info) => item_info(krustmote, info),
         _ => container("").into(),
     }
 }
 
 pub(crate) fn loading<'a>(_krustmote: &'a Krustmote) -> Element<'a, Message> {
     // TODO: Spinner.
-    container(text("...").size(48))
-        .width(Length::Fill)
-        .align_x(iced::alignment::Horizontal::Center)
-        .into()
+    container(
+        column![
+            text("...").size(48),
+            button("Cancel")
+                .on_press(Message::CancelLoad)
+                .style(themes::listitem),
+        ]
+        .align_x(iced::Alignment::Center),
+    )
+    .width(Length::Fill)
+    .align_x(iced::alignment::Horizontal::Center)
+    .into()
 }
 
 pub(crate) fn file_list<'a>(krustmote: &'a Krustmote) -> Element<'a, Message> {
@@ -273,7 +598,7 @@ pub(crate) fn file_list<'a>(krustmote: &'a Krustmote) -> Element<'a, Message> {
         .item_list
         .virtual_list
         .iter()
-        .map(|(_, d)| make_listitem(d))
+        .map(|(i, d)| make_listitem(*i, d))
         .map(Element::from);
 
     virtual_list.extend(files);
@@ -292,22 +617,38 @@ pub(crate) fn file_list<'a>(krustmote: &'a Krustmote) -> Element<'a, Message> {
 
     let virtual_list = column(virtual_list);
 
+    // Seasons/episodes lists get a button for the show's info page
+    let show_info = match krustmote.item_list.breadcrumb.last() {
+        Some(Message::GetData(data::Get::TVSeasons(tvshowid)))
+        | Some(Message::GetData(data::Get::TVEpisodes(tvshowid, _, _))) => Some(
+            button(icons::info().size(24).center().height(Length::Fill))
+                .on_press(Message::GetData(data::Get::TVShowDetails(*tvshowid)))
+                .width(50)
+                .height(50)
+                .style(themes::listitem),
+        ),
+        _ => None,
+    };
+
     column![
-        row![if krustmote.item_list.breadcrumb.len() > 1 {
-            button(column![
-                "..",
-                text(&krustmote.item_list.list_title).size(10)
-            ])
-            .on_press(Message::UpBreadCrumb)
-            .width(Length::Fill)
-            .height(50)
-            .style(themes::listitem)
-        } else {
-            button(text(&krustmote.item_list.list_title))
+        row![
+            if krustmote.item_list.breadcrumb.len() > 1 {
+                button(column![
+                    "..",
+                    text(&krustmote.item_list.list_title).size(10)
+                ])
+                .on_press(Message::UpBreadCrumb)
                 .width(Length::Fill)
                 .height(50)
                 .style(themes::listitem)
-        },]
+            } else {
+                button(text(&krustmote.item_list.list_title))
+                    .width(Length::Fill)
+                    .height(50)
+                    .style(themes::listitem)
+            },
+            show_info
+        ]
         .spacing(1)
         .padding(iced::Padding {
             left: 5.0,
@@ -328,7 +669,347 @@ pub(crate) fn file_list<'a>(krustmote: &'a Krustmote) -> Element<'a, Message> {
     .into()
 }
 
-pub(crate) fn make_listitem<'a>(data: &'a ListData) -> Element<'a, Message> {
+pub(crate) fn item_info<'a>(krustmote: &'a Krustmote, info: &'a ItemInfo) -> Element<'a, Message> {
+    let details = match &info.details {
+        data::ItemDetails::Movie(movie) => movie_details(movie, krustmote),
+        data::ItemDetails::TVShow(show) => tvshow_details(show),
+        data::ItemDetails::Episode(episode) => episode_details(episode, krustmote),
+    };
+
+    let (poster_w, poster_h) = info.details.poster_size();
+    let poster: Element<'a, Message> = match &info.poster {
+        Some(img) => image(img.clone()).width(poster_w as f32).into(),
+        None => Space::new()
+            .width(poster_w as f32)
+            .height(poster_h as f32)
+            .into(),
+    };
+
+    let fanart = info.fanart.as_ref().map(|img| {
+        image(img.clone())
+            .width(Length::Fill)
+            .height(200)
+            .content_fit(ContentFit::Cover)
+    });
+
+    column![
+        row![
+            button(column!["..", text(info.details.title()).size(10)])
+                .on_press(Message::UpBreadCrumb)
+                .width(Length::Fill)
+                .height(50)
+                .style(themes::listitem)
+        ]
+        .padding(5),
+        scrollable(
+            column![fanart, row![poster, details].spacing(20).padding(10),].width(Length::Fill)
+        ),
+    ]
+    .width(Length::Fill)
+    .into()
+}
+
+// The selected player's item in full, opened from the playing bar
+fn now_playing<'a>(krustmote: &'a Krustmote) -> Element<'a, Message> {
+    let status = &krustmote.kodi_status;
+    let item = &status.playing_item;
+    let now_playing = &krustmote.now_playing;
+
+    let poster: Element<'a, Message> = match &now_playing.poster {
+        Some(img) => image(img.clone()).width(DETAILS_POSTER_W as f32).into(),
+        None if now_playing.poster_path.is_some() => Space::new()
+            .width(DETAILS_POSTER_W as f32)
+            .height(DETAILS_POSTER_H as f32)
+            .into(),
+        None => Space::new().into(),
+    };
+
+    let fanart = now_playing.fanart.as_ref().map(|img| {
+        image(img.clone())
+            .width(Length::Fill)
+            .height(200)
+            .content_fit(ContentFit::Cover)
+    });
+
+    let rating = if item.rating > 0.0 {
+        format!("{:.1}", item.rating)
+    } else {
+        String::new()
+    };
+    let facts = [
+        ("Artist", item.artist.join(", ")),
+        ("Album", item.album.clone()),
+        ("Directed by", item.director.join(", ")),
+        ("Written by", item.writer.join(", ")),
+        ("Genre", item.genre.join(", ")),
+        ("Aired", item.firstaired.clone()),
+        ("Studio", item.studio.join(", ")),
+        ("Rating", rating),
+    ];
+
+    let details = column![
+        details_title(status.playing_title.clone()),
+        (!item.tagline.is_empty()).then(|| text(&item.tagline).font(Font {
+            style: iced::font::Style::Italic,
+            ..Default::default()
+        })),
+        detail_facts(facts),
+        chapter_list(&status.player_props.chapters, krustmote),
+        text(&item.plot).shaping(text::Shaping::Advanced),
+        cast_list(&item.cast),
+        stream_info(&item.streamdetails),
+    ]
+    .spacing(10);
+
+    column![
+        row![
+            button(column!["..", text(&status.playing_title).size(10)])
+                .on_press(Message::ToggleNowPlaying)
+                .width(Length::Fill)
+                .height(50)
+                .style(themes::listitem)
+        ]
+        .padding(5),
+        scrollable(
+            column![fanart, row![poster, details].spacing(20).padding(10),].width(Length::Fill)
+        ),
+    ]
+    .width(Length::Fill)
+    .into()
+}
+
+// Kodi only gives the current chapter's name, the rest are numbers
+fn chapter_list<'a>(chapters: &Chapters, krustmote: &Krustmote) -> Element<'a, Message> {
+    if chapters.count < 2 {
+        return Space::new().into();
+    }
+    let mut heading = format!("Chapter {} of {}", chapters.current, chapters.count);
+    if !chapters.name.is_empty() {
+        heading = format!("{heading}: {}", chapters.name);
+    }
+
+    let buttons = (1..=chapters.count).map(|chapter| {
+        let style = if chapter == chapters.current {
+            button::primary
+        } else {
+            button::secondary
+        };
+        button(text(chapter).size(14))
+            .style(style)
+            .on_press_maybe(krustmote.kodi_req(KodiCommand::PlayerGoToChapter(chapter)))
+            .into()
+    });
+    column![
+        text(heading).size(18).shaping(text::Shaping::Advanced),
+        row(buttons).spacing(5).wrap().vertical_spacing(5),
+    ]
+    .spacing(5)
+    .into()
+}
+
+fn movie_details<'a>(movie: &'a MovieDetails, krustmote: &Krustmote) -> Element<'a, Message> {
+    let item = &movie.item;
+    let facts = [
+        ("Directed by", movie.director.join(", ")),
+        ("Written by", movie.writer.join(", ")),
+        ("Genre", item.genre.join(", ")),
+        ("Runtime", format_runtime(movie.runtime)),
+        ("Rated", movie.mpaa.clone()),
+        ("Rating", format!("{:.1}", item.rating)),
+        ("Studio", movie.studio.join(", ")),
+    ];
+
+    column![
+        details_title(format!("{} ({})", item.title, item.year)),
+        (!movie.tagline.is_empty()).then(|| text(&movie.tagline).font(Font {
+            style: iced::font::Style::Italic,
+            ..Default::default()
+        })),
+        detail_facts(facts),
+        play_buttons(&item.file, &item.resume, krustmote),
+        text(&movie.plot).shaping(text::Shaping::Advanced),
+        cast_list(&movie.cast),
+        stream_info(&movie.streamdetails),
+    ]
+    .spacing(10)
+    .into()
+}
+
+fn tvshow_details<'a>(show: &'a TVShowDetails) -> Element<'a, Message> {
+    let item = &show.item;
+    let facts = [
+        ("Genre", item.genre.join(", ")),
+        ("Premiered", show.premiered.clone()),
+        ("Status", show.status.clone()),
+        ("Studio", show.studio.join(", ")),
+        ("Rated", show.mpaa.clone()),
+        ("Rating", format!("{:.1}", item.rating)),
+        (
+            "Episodes",
+            format!("{} in {} seasons", item.episode, item.season),
+        ),
+    ];
+
+    column![
+        details_title(format!("{} ({})", item.title, item.year)),
+        detail_facts(facts),
+        text(&show.plot).shaping(text::Shaping::Advanced),
+        cast_list(&show.cast),
+    ]
+    .spacing(10)
+    .into()
+}
+
+fn episode_details<'a>(episode: &'a EpisodeDetails, krustmote: &Krustmote) -> Element<'a, Message> {
+    let item = &episode.item;
+    let facts = [
+        ("First aired", item.firstaired.clone()),
+        ("Directed by", episode.director.join(", ")),
+        ("Written by", episode.writer.join(", ")),
+        ("Runtime", format_runtime(episode.runtime)),
+        ("Rating", format!("{:.1}", item.rating)),
+    ];
+
+    column![
+        text(&episode.showtitle)
+            .size(14)
+            .shaping(text::Shaping::Advanced),
+        details_title(format!(
+            "S{:02}E{:02} - {}",
+            item.season, item.episode, item.title
+        )),
+        detail_facts(facts),
+        play_buttons(&item.file, &item.resume, krustmote),
+        text(&episode.plot).shaping(text::Shaping::Advanced),
+        cast_list(&episode.cast),
+        stream_info(&episode.streamdetails),
+    ]
+    .spacing(10)
+    .into()
+}
+
+fn details_title<'a>(title: String) -> Element<'a, Message> {
+    text(title)
+        .size(24)
+        .font(Font {
+            weight: Weight::Bold,
+            ..Default::default()
+        })
+        .shaping(text::Shaping::Advanced)
+        .into()
+}
+
+fn detail_facts<'a, const N: usize>(facts: [(&'a str, String); N]) -> Element<'a, Message> {
+    column(
+        facts
+            .into_iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(label, value)| {
+                row![
+                    text(label).size(14).width(100),
+                    text(value).size(14).shaping(text::Shaping::Advanced)
+                ]
+                .into()
+            }),
+    )
+    .spacing(2)
+    .into()
+}
+
+fn play_buttons<'a>(
+    file: &str,
+    resume: &ResumePoint,
+    krustmote: &Krustmote,
+) -> Element<'a, Message> {
+    let online = matches!(krustmote.state, State::Connected(..));
+    let on_press = |cmd: KodiCommand| krustmote.kodi_req(cmd).filter(|_| online);
+
+    row![
+        button(
+            row![
+                icons::play_arrow(),
+                if resume.position > 0.0 {
+                    "Play from start"
+                } else {
+                    "Play"
+                }
+            ]
+            .align_y(iced::Alignment::Center)
+        )
+        .on_press_maybe(on_press(KodiCommand::PlayerOpen(file.to_string()))),
+        (resume.position > 0.0).then(|| {
+            button(
+                row![
+                    icons::play_arrow(),
+                    text(format!("Resume from {}", resume.as_time()))
+                ]
+                .align_y(iced::Alignment::Center),
+            )
+            .on_press_maybe(on_press(KodiCommand::PlayerOpenResume(file.to_string())))
+        }),
+        button(row![icons::playlist_add(), "Queue"].align_y(iced::Alignment::Center))
+            .on_press_maybe(on_press(KodiCommand::PlaylistAdd {
+                playlist_id: VIDEO_PLAYLIST,
+                item: PlaylistItemRef::File(file.to_string()),
+            })),
+    ]
+    .spacing(10)
+    .into()
+}
+
+fn cast_list<'a>(cast: &'a [Cast]) -> Element<'a, Message> {
+    if cast.is_empty() {
+        return Space::new().into();
+    }
+    // Some movies have hundreds of extras listed
+    let cast = cast.iter().take(20).map(|actor| {
+        row![
+            text(&actor.name).size(14).width(200),
+            text(&actor.role).size(14),
+        ]
+        .into()
+    });
+    column![text("Cast").size(18), column(cast).spacing(2)]
+        .spacing(5)
+        .into()
+}
+
+fn stream_info<'a>(streams: &'a StreamDetails) -> Element<'a, Message> {
+    let subtitles = streams
+        .subtitle
+        .iter()
+        .map(|s| s.language.as_str())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    column![
+        column(
+            streams
+                .video
+                .iter()
+                .map(|v| text(format!("Video: {v}")).size(12).into())
+        ),
+        column(
+            streams
+                .audio
+                .iter()
+                .map(|a| text(format!("Audio: {a}")).size(12).into())
+        ),
+        (!subtitles.is_empty()).then(|| text(format!("Subtitles: {subtitles}")).size(12)),
+    ]
+    .into()
+}
+
+fn format_runtime(seconds: u32) -> String {
+    if seconds == 0 {
+        return String::new();
+    }
+    let minutes = seconds / 60;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+pub(crate) fn make_listitem<'a>(index: usize, data: &'a ListData) -> Element<'a, Message> {
     // Let's stretch the definition of a 'button'
     // ___________________________________________________________
     // | picture |  Main Label Information                       |
@@ -363,7 +1044,14 @@ pub(crate) fn make_listitem<'a>(data: &'a ListData) -> Element<'a, Message> {
         },
         column![
             text(&*data.label).size(14).height(19),
-            text("").size(10),
+            match data.progress {
+                Some(progress) => Element::from(
+                    container(progress_bar(0.0..=1.0, progress).girth(3).length(150))
+                        .height(13)
+                        .align_y(iced::alignment::Vertical::Center)
+                ),
+                None => text("").size(10).into(),
+            },
             row![
                 match &data.bottom_left {
                     Some(d) => text(d.as_str()).size(10),
@@ -388,11 +1076,69 @@ pub(crate) fn make_listitem<'a>(data: &'a ListData) -> Element<'a, Message> {
 
     let list_item = ListItem::new(inner_content)
         .style(themes::listitem)
-        .on_press(data.on_click.clone());
+        .on_press(data.on_click.clone())
+        .on_context(Message::ShowContextMenu(index));
 
     list_item.into()
 }
 
+pub(crate) fn toasts<'a>(toasts: &'a [Toast]) -> Element<'a, Message> {
+    let list = column(toasts.iter().map(|toast| {
+        let palette = iced::Theme::Dracula.palette();
+        let background = if toast.is_error {
+            palette.danger
+        } else {
+            palette.primary
+        };
+        container(
+            row![
+                text(&toast.message).size(14).width(Length::Fill),
+                button(icons::close())
+                    .on_press(Message::DismissToast(toast.id))
+                    .style(themes::bare_button),
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center),
+        )
+        .width(400)
+        .padding(10)
+        .style(move |_| {
+            container::Style::default()
+                .background(background)
+                .color(Color::WHITE)
+                .border(iced::border::rounded(5))
+        })
+        .into()
+    }))
+    .spacing(5);
+
+    // Bottom right, above the playing bar
+    container(list)
+        .align_right(Length::Fill)
+        .align_bottom(Length::Fill)
+        .padding(iced::Padding::new(20.0).bottom(100))
+        .into()
+}
+
+fn connection_status<'a>(status: &client::ConnectionStatus) -> Element<'a, Message> {
+    match status {
+        client::ConnectionStatus::Connected => container("").into(),
+        client::ConnectionStatus::Connecting => text("Connecting...").size(10).into(),
+        client::ConnectionStatus::Retrying { retry_in, error } => {
+            // Transport errors can be a whole paragraph, the menu is narrow
+            let error = match error.char_indices().nth(80) {
+                Some((cut, _)) => format!("{}...", &error[..cut]),
+                None => error.clone(),
+            };
+            column![
+                text(format!("Retry in {}s", retry_in)).size(10),
+                text(error).size(10),
+            ]
+            .into()
+        }
+    }
+}
+
 pub(crate) fn left_menu<'a>(krustmote: &'a Krustmote) -> Element<'a, Message> {
     container(
         column![
@@ -408,7 +1154,27 @@ pub(crate) fn left_menu<'a>(krustmote: &'a Krustmote) -> Element<'a, Message> {
                 }
             ]
             .align_y(iced::Alignment::Center),
+            connection_status(&krustmote.kodi_status.connection),
             rule::horizontal(2),
+            if matches!(krustmote.state, State::Offline(_))
+                && krustmote
+                    .kodi_status
+                    .server
+                    .as_ref()
+                    .is_some_and(|s| !s.mac.is_empty())
+            {
+                container(
+                    button(
+                        row![icons::power_settings_new(), "Wake"].align_y(iced::Alignment::Center),
+                    )
+                    .on_press(Message::GetData(data::Get::Wake))
+                    .width(Length::Fill)
+                    .style(themes::bare_button),
+                )
+                .width(Length::Fill)
+            } else {
+                container("")
+            },
             if let crate::State::Connected(..) = krustmote.state {
                 container(
                     button(row![icons::folder(), "Files"].align_y(iced::Alignment::Center))
@@ -420,6 +1186,21 @@ pub(crate) fn left_menu<'a>(krustmote: &'a Krustmote) -> Element<'a, Message> {
             } else {
                 container("")
             },
+            if let crate::State::Connected(..) = krustmote.state {
+                container(
+                    button(row![icons::live_tv(), "Live TV"].align_y(iced::Alignment::Center))
+                        .on_press(Message::GetData(data::Get::LiveTV))
+                        .width(Length::Fill)
+                        .style(themes::bare_button),
+                )
+                .width(Length::Fill)
+            } else {
+                container("")
+            },
+            button(row![icons::history(), "Continue"].align_y(iced::Alignment::Center))
+                .on_press(Message::GetData(data::Get::ContinueWatching))
+                .width(Length::Fill)
+                .style(themes::bare_button),
             button(row![icons::movie(), "Movies"].align_y(iced::Alignment::Center))
                 .on_press(Message::GetData(data::Get::Movies(true)))
                 .width(Length::Fill)
@@ -428,6 +1209,10 @@ pub(crate) fn left_menu<'a>(krustmote: &'a Krustmote) -> Element<'a, Message> {
                 .on_press(Message::GetData(data::Get::TVShows(true)))
                 .width(Length::Fill)
                 .style(themes::bare_button),
+            button(row![icons::library_music(), "Music"].align_y(iced::Alignment::Center))
+                .on_press(Message::GetData(data::Get::Artists(true)))
+                .width(Length::Fill)
+                .style(themes::bare_button),
             button(row![icons::settings(), "Settings"].align_y(iced::Alignment::Center))
                 .width(Length::Fill)
                 .style(themes::bare_button)
@@ -449,7 +1234,7 @@ pub(crate) fn remote<'a>(krustmote: &Krustmote) -> Element<'a, Message> {
         return container("").into();
     }
     let red = Color::from_rgb8(255, 0, 0);
-    container(
+    let remote = container(
         column![
             // seems like I could template these buttons in some way
             button(icons::bug_report()).on_press(Message::KodiReq(KodiCommand::Test)),
@@ -478,6 +1263,17 @@ pub(crate) fn remote<'a>(krustmote: &Krustmote) -> Element<'a, Message> {
                 )),
             ]
             .spacing(10),
+            row![
+                Slider::new(
+                    0..=100,
+                    krustmote.kodi_status.volume,
+                    Message::VolumeChanged
+                )
+                .on_release(Message::VolumeReleased),
+                text(krustmote.kodi_status.volume).size(14).width(25),
+            ]
+            .spacing(5)
+            .align_y(iced::Alignment::Center),
             row![
                 button(icons::fullscreen().size(30)).on_press(Message::KodiReq(
                     KodiCommand::InputButtonEvent {
@@ -502,7 +1298,16 @@ pub(crate) fn remote<'a>(krustmote: &Krustmote) -> Element<'a, Message> {
                         keymap: "R1"
                     }
                 )),
-                Space::new().height(40).width(40), // Not sure what to put here.
+                button(icons::playlist_play().size(30)).on_press(Message::ShowQueue(
+                    // The playing playlist, if there is one
+                    krustmote
+                        .kodi_status
+                        .active_player_id
+                        .and_then(
+                            |_| u8::try_from(krustmote.kodi_status.player_props.playlistid).ok()
+                        )
+                        .unwrap_or(VIDEO_PLAYLIST)
+                )),
                 button(icons::format_list_bulleted().size(30)).on_press(Message::KodiReq(
                     KodiCommand::InputButtonEvent {
                         button: "title",
@@ -572,6 +1377,7 @@ pub(crate) fn remote<'a>(krustmote: &Krustmote) -> Element<'a, Message> {
         .spacing(5)
         .align_x(iced::Alignment::Center),
     )
-    .width(230)
-    .into()
+    .width(230);
+    // Scrolling anywhere over the remote changes the volume
+    mouse_area(remote).on_scroll(Message::VolumeScrolled).into()
 }
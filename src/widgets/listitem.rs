@@ -1,10 +1,15 @@
 use iced::advanced::{Clipboard, Shell, Widget, layout, mouse, renderer, widget::Tree};
 use iced::widget::button;
 use iced::{Element, Event, Length, Point, Rectangle, Size, touch};
+use std::time::{Duration, Instant};
+
+// Holding a press this long without moving counts as a long press
+const LONG_PRESS: Duration = Duration::from_millis(500);
 
 pub struct ListItem<'a, Message, Theme = iced::Theme, Renderer = iced::Renderer> {
     content: Element<'a, Message, Theme, Renderer>,
     on_press: Option<Message>,
+    on_context: Option<Message>,
     style: fn(&Theme, button::Status) -> button::Style,
 }
 
@@ -13,6 +18,7 @@ impl<'a, Message, Theme, Renderer> ListItem<'a, Message, Theme, Renderer> {
         Self {
             content: content.into(),
             on_press: None,
+            on_context: None,
             // Provide a default fallback style
             style: |_theme, _status| button::Style::default(),
         }
@@ -23,6 +29,12 @@ impl<'a, Message, Theme, Renderer> ListItem<'a, Message, Theme, Renderer> {
         self
     }
 
+    // Right click or long press
+    pub fn on_context(mut self, msg: Message) -> Self {
+        self.on_context = Some(msg);
+        self
+    }
+
     // Add a builder method to accept your theme function
     pub fn style(mut self, style: fn(&Theme, button::Status) -> button::Style) -> Self {
         self.style = style;
@@ -35,6 +47,7 @@ struct State {
     is_pressed: bool,
     is_hovered: bool,
     start_pos: Option<Point>,
+    pressed_at: Option<Instant>,
 }
 
 impl<'a, Message, Theme, Renderer> Widget<Message, Theme, Renderer>
@@ -116,9 +129,18 @@ where
                 if let Some(cursor_position) = cursor.position_over(bounds) {
                     state.is_pressed = true;
                     state.start_pos = Some(cursor_position);
+                    state.pressed_at = Some(Instant::now());
                     // Do NOT capture event to allow Scrollable to use it
                 }
             }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right)) => {
+                if let Some(on_context) = &self.on_context
+                    && cursor.is_over(bounds)
+                {
+                    shell.publish(on_context.clone());
+                    shell.capture_event();
+                }
+            }
             Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
             | Event::Touch(touch::Event::FingerLifted { .. }) => {
                 if state.is_pressed {
@@ -127,11 +149,19 @@ where
                     if let Some(cursor_position) = cursor.position_over(bounds) {
                         if let Some(start) = state.start_pos {
                             if start.distance(cursor_position) < 15.0 {
-                                shell.publish(self.on_press.clone().unwrap());
+                                let long_press =
+                                    state.pressed_at.is_some_and(|t| t.elapsed() >= LONG_PRESS);
+                                match &self.on_context {
+                                    Some(on_context) if long_press => {
+                                        shell.publish(on_context.clone())
+                                    }
+                                    _ => shell.publish(self.on_press.clone().unwrap()),
+                                }
                             }
                         }
                     }
                     state.start_pos = None;
+                    state.pressed_at = None;
                 }
             }
             Event::Mouse(mouse::Event::CursorMoved { .. })
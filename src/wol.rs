@@ -0,0 +1,86 @@
+// Wake-on-LAN magic packets for kodi boxes that sleep
+
+use std::error::Error;
+use std::net::{Ipv4Addr, SocketAddr};
+
+use tokio::net::UdpSocket;
+
+use crate::koditypes::KodiServer;
+
+// Port 9 (discard) is what most WOL senders use, the NIC doesn't care
+const WOL_PORT: u16 = 9;
+
+/// `aa:bb:cc:dd:ee:ff`, `-` separators work too
+pub fn parse_mac(mac: &str) -> Option<[u8; 6]> {
+    let parts: Vec<&str> = mac.trim().split([':', '-']).collect();
+    if parts.len() != 6 {
+        return None;
+    }
+    let mut bytes = [0; 6];
+    for (byte, part) in bytes.iter_mut().zip(parts) {
+        if part.len() != 2 {
+            return None;
+        }
+        *byte = u8::from_str_radix(part, 16).ok()?;
+    }
+    Some(bytes)
+}
+
+// 6 x 0xFF then the MAC 16 times
+fn magic_packet(mac: [u8; 6]) -> Vec<u8> {
+    let mut packet = vec![0xFF; 6];
+    for _ in 0..16 {
+        packet.extend(mac);
+    }
+    packet
+}
+
+async fn send_magic_packet(mac: [u8; 6], target: SocketAddr) -> std::io::Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.set_broadcast(true)?;
+    socket.send_to(&magic_packet(mac), target).await?;
+    Ok(())
+}
+
+/// Broadcasts to the server's broadcast address, or everywhere if it has none
+pub async fn wake(server: &KodiServer) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mac = parse_mac(&server.mac).ok_or("Server has no valid MAC address")?;
+    let broadcast = if server.broadcast.is_empty() {
+        Ipv4Addr::BROADCAST
+    } else {
+        server.broadcast.parse()?
+    };
+    send_magic_packet(mac, SocketAddr::new(broadcast.into(), WOL_PORT)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_macs() {
+        let mac = [0x00, 0x1A, 0x2b, 0x3C, 0x4d, 0xFF];
+        assert_eq!(parse_mac("00:1a:2B:3c:4D:ff"), Some(mac));
+        assert_eq!(parse_mac("00-1A-2B-3C-4D-FF"), Some(mac));
+        assert_eq!(parse_mac(""), None);
+        assert_eq!(parse_mac("00:1a:2b:3c:4d"), None);
+        assert_eq!(parse_mac("00:1a:2b:3c:4d:fg"), None);
+        assert_eq!(parse_mac("001:a:2b:3c:4d:ff"), None);
+    }
+
+    #[tokio::test]
+    async fn sends_magic_packet() {
+        let listener = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mac = [1, 2, 3, 4, 5, 6];
+        send_magic_packet(mac, listener.local_addr().unwrap())
+            .await
+            .unwrap();
+
+        let mut buf = [0; 200];
+        let len = listener.recv(&mut buf).await.unwrap();
+        assert_eq!(len, 102);
+        assert_eq!(buf[..6], [0xFF; 6]);
+        assert!(buf[6..len].chunks(6).all(|chunk| chunk == mac));
+    }
+}